@@ -1,3 +1,7 @@
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
 #[allow(unused)]
 #[cfg(stm32h7)]
 use pac::adc::vals::{Adcaldif, Difsel, Exten};
@@ -7,11 +11,12 @@ use pac::adc::vals::{Adcaldif, Difsel, Exten, Rovsm, Trovs};
 use pac::adccommon::vals::Presc;
 use stm32_metapac::adc::vals::{Adstp, Dmacfg, Dmaen};
 
-use super::{blocking_delay_us, Adc, AdcChannel, AnyAdcChannel, Instance, Resolution, RxDma, SampleTime};
+use super::{blocking_delay_us, Adc, AdcChannel, AnyAdcChannel, Instance, Resolution, RxDma, SampleTime, Trigger};
 use crate::adc::SealedAdcChannel;
 use crate::dma::Transfer;
+use crate::interrupt::typelevel::Interrupt;
 use crate::time::Hertz;
-use crate::{pac, rcc, Peripheral};
+use crate::{interrupt, pac, rcc, Peripheral};
 
 /// Default VREF voltage used for sample conversion to millivolts.
 pub const VREF_DEFAULT_MV: u32 = 3300;
@@ -65,6 +70,39 @@ impl<T: Instance> super::SealedAdcChannel<T> for Vbat {
     }
 }
 
+/// Interrupt handler.
+///
+/// Needed by the analog watchdog and injected conversion async APIs, see
+/// [`Adc::new_with_interrupt`].
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let isr = T::regs().isr().read();
+        let ier = T::regs().ier().read();
+        let awd = isr.awd(0) && ier.awdie(0);
+        let jeos = isr.jeos() && ier.jeosie();
+        if !awd && !jeos {
+            // ADCs of a pair share their interrupt
+            return;
+        }
+
+        // The waiting task clears the flags and enables the interrupts again
+        T::regs().ier().modify(|w| {
+            if awd {
+                w.set_awdie(0, false);
+            }
+            if jeos {
+                w.set_jeosie(false);
+            }
+        });
+
+        T::state().waker.wake();
+    }
+}
+
 // NOTE (unused): The prescaler enum closely copies the hardware capabilities,
 // but high prescaling doesn't make a lot of sense in the current implementation and is ommited.
 #[allow(unused)]
@@ -166,6 +204,20 @@ impl<'d, T: Instance> Adc<'d, T> {
         s
     }
 
+    /// Create a new ADC driver, with the interrupt used to wait for the analog watchdog and
+    /// injected conversions.
+    pub fn new_with_interrupt(
+        adc: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        let s = Self::new(adc);
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        s
+    }
+
     fn power_up(&mut self) {
         T::regs().cr().modify(|reg| {
             reg.set_deeppwd(false);
@@ -467,6 +519,193 @@ impl<'d, T: Instance> Adc<'d, T> {
         });
     }
 
+    /// Enable analog watchdog 1 on a single channel.
+    ///
+    /// The watchdog flags regular and injected conversions of `channel` whose raw 12-bit result
+    /// is outside of `low..=high`.
+    #[cfg(stm32g4)]
+    pub fn enable_analog_watchdog(&mut self, channel: &mut impl AdcChannel<T>, low: u16, high: u16) {
+        self.configure_analog_watchdog(Some(channel.channel()), low, high);
+    }
+
+    /// Enable analog watchdog 1 on all channels.
+    ///
+    /// The watchdog flags regular and injected conversions whose raw 12-bit result is outside of
+    /// `low..=high`.
+    #[cfg(stm32g4)]
+    pub fn enable_analog_watchdog_all_channels(&mut self, low: u16, high: u16) {
+        self.configure_analog_watchdog(None, low, high);
+    }
+
+    #[cfg(stm32g4)]
+    fn configure_analog_watchdog(&mut self, channel: Option<u8>, low: u16, high: u16) {
+        assert!(low <= high && high <= 0xFFF, "Invalid analog watchdog thresholds");
+
+        // The watchdog can only be configured while no conversion is ongoing
+        Self::cancel_conversions();
+        Self::cancel_injected_conversions();
+
+        T::regs().tr1().modify(|w| {
+            w.set_lt1(low);
+            w.set_ht1(high);
+        });
+        T::regs().cfgr().modify(|w| {
+            w.set_awd1sgl(channel.is_some());
+            w.set_awd1ch(channel.unwrap_or(0));
+            w.set_awd1en(true);
+            w.set_jawd1en(true);
+        });
+        T::regs().isr().write(|w| w.set_awd(0, true));
+    }
+
+    /// Disable analog watchdog 1.
+    #[cfg(stm32g4)]
+    pub fn disable_analog_watchdog(&mut self) {
+        Self::cancel_conversions();
+        Self::cancel_injected_conversions();
+
+        T::regs().cfgr().modify(|w| {
+            w.set_awd1en(false);
+            w.set_jawd1en(false);
+        });
+        T::regs().ier().modify(|w| w.set_awdie(0, false));
+        T::regs().isr().write(|w| w.set_awd(0, true));
+    }
+
+    /// Returns whether analog watchdog 1 triggered since the last call, and clears its flag.
+    #[cfg(stm32g4)]
+    pub fn analog_watchdog_triggered(&mut self) -> bool {
+        let triggered = T::regs().isr().read().awd(0);
+        T::regs().isr().write(|w| w.set_awd(0, true));
+        triggered
+    }
+
+    /// Wait for a conversion result outside of the window of analog watchdog 1.
+    ///
+    /// Requires the driver to be created with [`new_with_interrupt`](Self::new_with_interrupt).
+    #[cfg(stm32g4)]
+    pub async fn wait_analog_watchdog(&mut self) {
+        assert!(T::Interrupt::is_enabled(), "ADC interrupt not enabled");
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if T::regs().isr().read().awd(0) {
+                T::regs().isr().write(|w| w.set_awd(0, true));
+                Poll::Ready(())
+            } else {
+                T::regs().ier().modify(|w| w.set_awdie(0, true));
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Configure the injected group.
+    ///
+    /// Injected conversions preempt the regular sequence, which allows sampling at a precise
+    /// instant, such as phase currents synchronized to a PWM timer through `trigger`. Without
+    /// a trigger, each read starts the injected conversions by software.
+    pub fn configure_injected<'a>(
+        &mut self,
+        sequence: impl ExactSizeIterator<Item = (&'a mut AnyAdcChannel<T>, SampleTime)>,
+        trigger: Option<Trigger>,
+    ) where
+        T: 'a,
+    {
+        let len = sequence.len();
+        assert!(len != 0, "Injected sequence cannot be empty");
+        assert!(len <= 4, "Injected sequence cannot be more than 4 in length");
+
+        Self::cancel_injected_conversions();
+
+        let mut channels = [0u8; 4];
+        for (i, (channel, sample_time)) in sequence.enumerate() {
+            Self::configure_channel(channel, sample_time);
+            channels[i] = channel.channel();
+        }
+
+        T::regs().jsqr().write(|w| {
+            w.set_jl(len as u8 - 1);
+            for (i, &ch) in channels[..len].iter().enumerate() {
+                w.set_jsq(i, ch);
+            }
+            if let Some(trigger) = trigger {
+                w.set_jexten(trigger.exten());
+                w.set_jextsel(trigger.source);
+            }
+        });
+    }
+
+    /// Stop injected conversions, ignoring their trigger until the next read.
+    pub fn stop_injected(&mut self) {
+        Self::cancel_injected_conversions();
+    }
+
+    fn start_injected() -> usize {
+        T::regs().isr().write(|w| w.set_jeos(true));
+
+        // With a trigger, this arms the injected group once, conversions then start on every
+        // trigger event.
+        if !T::regs().cr().read().jadstart() {
+            T::regs().cr().modify(|w| w.set_jadstart(true));
+        }
+
+        T::regs().jsqr().read().jl() as usize + 1
+    }
+
+    fn read_injected_results(readings: &mut [u16]) {
+        T::regs().isr().write(|w| w.set_jeos(true));
+        for (i, reading) in readings.iter_mut().enumerate() {
+            *reading = T::regs().jdr(i).read().jdata();
+        }
+    }
+
+    /// Read the next conversions of the injected group.
+    ///
+    /// `readings` must have the length of the sequence given to
+    /// [`configure_injected`](Self::configure_injected).
+    pub fn blocking_read_injected(&mut self, readings: &mut [u16]) {
+        let len = Self::start_injected();
+        assert!(
+            readings.len() == len,
+            "Readings length must be equal to injected sequence length"
+        );
+
+        while !T::regs().isr().read().jeos() {}
+
+        Self::read_injected_results(readings);
+    }
+
+    /// Read the next conversions of the injected group.
+    ///
+    /// `readings` must have the length of the sequence given to
+    /// [`configure_injected`](Self::configure_injected). Requires the driver to be created with
+    /// [`new_with_interrupt`](Self::new_with_interrupt).
+    pub async fn read_injected(&mut self, readings: &mut [u16]) {
+        assert!(T::Interrupt::is_enabled(), "ADC interrupt not enabled");
+
+        let len = Self::start_injected();
+        assert!(
+            readings.len() == len,
+            "Readings length must be equal to injected sequence length"
+        );
+
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if T::regs().isr().read().jeos() {
+                Poll::Ready(())
+            } else {
+                T::regs().ier().modify(|w| w.set_jeosie(true));
+                Poll::Pending
+            }
+        })
+        .await;
+
+        Self::read_injected_results(readings);
+    }
+
     fn configure_channel(channel: &mut impl AdcChannel<T>, sample_time: SampleTime) {
         // Configure channel
         Self::set_channel_sample_time(channel.channel(), sample_time);
@@ -507,4 +746,13 @@ impl<'d, T: Instance> Adc<'d, T> {
             while T::regs().cr().read().adstart() {}
         }
     }
+
+    fn cancel_injected_conversions() {
+        if T::regs().cr().read().jadstart() && !T::regs().cr().read().addis() {
+            T::regs().cr().modify(|reg| {
+                reg.set_jadstp(true);
+            });
+            while T::regs().cr().read().jadstart() {}
+        }
+    }
 }
@@ -21,7 +21,7 @@ use core::marker::PhantomData;
 #[allow(unused)]
 #[cfg(not(any(adc_f3_v2, adc_u5)))]
 pub use _version::*;
-#[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
+#[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1, adc_g4))]
 use embassy_sync::waitqueue::AtomicWaker;
 
 #[cfg(not(any(adc_u5)))]
@@ -42,12 +42,12 @@ pub struct Adc<'d, T: Instance> {
     sample_time: SampleTime,
 }
 
-#[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
+#[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1, adc_g4))]
 pub struct State {
     pub waker: AtomicWaker,
 }
 
-#[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
+#[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1, adc_g4))]
 impl State {
     pub const fn new() -> Self {
         Self {
@@ -62,7 +62,8 @@ trait SealedInstance {
     #[cfg(not(any(adc_f1, adc_v1, adc_l0, adc_f3_v2, adc_f3_v1_1, adc_g0, adc_u5)))]
     #[allow(unused)]
     fn common_regs() -> crate::pac::adccommon::AdcCommon;
-    #[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
+    #[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1, adc_g4))]
+    #[allow(unused)]
     fn state() -> &'static State;
 }
 
@@ -88,6 +89,41 @@ pub(crate) fn blocking_delay_us(us: u32) {
     }
 }
 
+/// Edge of the external trigger starting conversions.
+#[cfg(any(adc_v2, adc_v3, adc_g0, adc_h5, adc_u0, adc_g4))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerEdge {
+    /// Conversions start on the rising edge of the trigger.
+    Rising = 1,
+    /// Conversions start on the falling edge of the trigger.
+    Falling = 2,
+    /// Conversions start on both edges of the trigger.
+    Both = 3,
+}
+
+/// External trigger of conversions, such as the TRGO output of a timer.
+///
+/// Sources are chip specific, see the `EXTSEL` (or `JEXTSEL` for injected conversions) field
+/// of the reference manual for the mapping of source numbers to timer events.
+#[cfg(any(adc_v2, adc_v3, adc_g0, adc_h5, adc_u0, adc_g4))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Trigger {
+    /// Value of the `EXTSEL` or `JEXTSEL` field selecting the trigger source.
+    pub source: u8,
+    /// Active edge of the trigger.
+    pub edge: TriggerEdge,
+}
+
+#[cfg(any(adc_v2, adc_v3, adc_g0, adc_h5, adc_u0, adc_g4))]
+impl Trigger {
+    #[allow(unused)]
+    fn exten(&self) -> vals::Exten {
+        vals::Exten::from_bits(self.edge as u8)
+    }
+}
+
 /// ADC instance.
 #[cfg(not(any(
     adc_f1,
@@ -170,7 +206,7 @@ foreach_adc!(
                 return crate::pac::$common_inst
             }
 
-            #[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
+            #[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1, adc_g4))]
             fn state() -> &'static State {
                 static STATE: State = State::new();
                 &STATE
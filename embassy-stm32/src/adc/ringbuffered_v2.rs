@@ -5,7 +5,7 @@ use core::sync::atomic::{compiler_fence, Ordering};
 use embassy_hal_internal::{into_ref, Peripheral};
 use stm32_metapac::adc::vals::SampleTime;
 
-use crate::adc::{Adc, AdcChannel, Instance, RxDma};
+use crate::adc::{Adc, AdcChannel, Instance, RxDma, Trigger};
 use crate::dma::{Priority, ReadableRingBuffer, TransferOptions};
 use crate::pac::adc::vals;
 use crate::rcc;
@@ -90,6 +90,7 @@ impl From<u8> for Sequence {
 pub struct RingBufferedAdc<'d, T: Instance> {
     _phantom: PhantomData<T>,
     ring_buf: ReadableRingBuffer<'d, u16>,
+    trigger: Option<Trigger>,
 }
 
 impl<'d, T: Instance> Adc<'d, T> {
@@ -129,6 +130,7 @@ impl<'d, T: Instance> Adc<'d, T> {
         RingBufferedAdc {
             _phantom: PhantomData,
             ring_buf,
+            trigger: None,
         }
     }
 }
@@ -226,6 +228,14 @@ impl<'d, T: Instance> RingBufferedAdc<'d, T> {
         Self::start_adc();
     }
 
+    /// Sets the external trigger of the conversions, such as the TRGO output of a timer.
+    ///
+    /// With a trigger, the whole sequence is converted once per trigger event instead of back to back.
+    /// Takes effect the next time conversions are started.
+    pub fn set_trigger(&mut self, trigger: Option<Trigger>) {
+        self.trigger = trigger;
+    }
+
     /// Turns on ADC if it is not already turned on and starts continuous DMA transfer.
     pub fn start(&mut self) -> Result<(), OverrunError> {
         self.setup_adc();
@@ -256,6 +266,8 @@ impl<'d, T: Instance> RingBufferedAdc<'d, T> {
             reg.set_swstart(false);
             // Stop DMA
             reg.set_dma(false);
+            // Ignore the trigger
+            reg.set_exten(vals::Exten::DISABLED);
         });
 
         r.cr1().modify(|w| {
@@ -307,18 +319,25 @@ impl<'d, T: Instance> RingBufferedAdc<'d, T> {
         r.cr2().modify(|w| {
             // Enable DMA mode
             w.set_dma(true);
-            // Enable continuous conversions
-            w.set_cont(true);
+            // Enable continuous conversions, unless an external trigger paces them
+            w.set_cont(self.trigger.is_none());
+            match self.trigger {
+                Some(trigger) => {
+                    w.set_exten(trigger.exten());
+                    w.set_extsel(trigger.source);
+                }
+                None => w.set_exten(vals::Exten::DISABLED),
+            }
             // DMA requests are issues as long as DMA=1 and data are converted.
             w.set_dds(vals::Dds::CONTINUOUS);
             // EOC flag is set at the end of each conversion.
             w.set_eocs(vals::Eocs::EACHCONVERSION);
         });
 
-        // Begin ADC conversions
+        // Begin ADC conversions, or wait for the trigger
         T::regs().cr2().modify(|reg| {
             reg.set_adon(true);
-            reg.set_swstart(true);
+            reg.set_swstart(self.trigger.is_none());
         });
 
         super::blocking_delay_us(3);
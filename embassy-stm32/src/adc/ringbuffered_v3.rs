@@ -0,0 +1,229 @@
+use core::marker::PhantomData;
+use core::mem;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embassy_hal_internal::{into_ref, Peripheral};
+use pac::adc::vals::{Dmacfg, Exten};
+
+use crate::adc::{Adc, AnyAdcChannel, Instance, RxDma, SampleTime, Trigger};
+use crate::dma::{Priority, ReadableRingBuffer, TransferOptions};
+use crate::{pac, rcc};
+
+/// The ADC converted samples faster than they were read out of the DMA buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OverrunError;
+
+/// ADC converting a sequence of channels continuously into a DMA ring buffer.
+pub struct RingBufferedAdc<'d, T: Instance> {
+    _phantom: PhantomData<T>,
+    ring_buf: ReadableRingBuffer<'d, u16>,
+    trigger: Option<Trigger>,
+}
+
+impl<'d, T: Instance> Adc<'d, T> {
+    /// Configures the ADC to continuously convert `sequence` into a DMA ring buffer.
+    ///
+    /// Without a `trigger`, the sequence is converted back to back as fast as the sample times
+    /// allow. With a `trigger`, such as the TRGO output of a timer, the whole sequence is
+    /// converted once per trigger event, which sets the sample rate.
+    ///
+    /// The length of `dma_buf` must be a multiple of the sequence length and large enough to
+    /// leave time to process one half while the DMA fills the other.
+    pub fn into_ring_buffered<'a>(
+        mut self,
+        dma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        dma_buf: &'d mut [u16],
+        sequence: impl ExactSizeIterator<Item = (&'a mut AnyAdcChannel<T>, SampleTime)>,
+        trigger: Option<Trigger>,
+    ) -> RingBufferedAdc<'d, T>
+    where
+        T: 'a,
+    {
+        assert!(sequence.len() != 0, "Sequence cannot be empty");
+        assert!(sequence.len() <= 16, "Sequence cannot be more than 16 in length");
+        assert!(!dma_buf.is_empty() && dma_buf.len() <= 0xFFFF);
+        assert!(
+            dma_buf.len() % (2 * sequence.len()) == 0,
+            "Each half of the DMA buffer must hold whole sequences"
+        );
+        into_ref!(dma);
+
+        Self::cancel_conversions();
+        self.enable();
+        Self::configure_sequence(sequence);
+
+        let opts = TransferOptions {
+            half_transfer_ir: true,
+            priority: Priority::VeryHigh,
+            ..Default::default()
+        };
+
+        let rx_src = T::regs().dr().as_ptr() as *mut u16;
+        let request = dma.request();
+        let ring_buf = unsafe { ReadableRingBuffer::new(dma, request, rx_src, dma_buf, opts) };
+
+        // Don't disable the clock
+        mem::forget(self);
+
+        RingBufferedAdc {
+            _phantom: PhantomData,
+            ring_buf,
+            trigger,
+        }
+    }
+}
+
+impl<'d, T: Instance> RingBufferedAdc<'d, T> {
+    /// Changes the trigger of the conversions.
+    ///
+    /// Takes effect the next time conversions are started.
+    pub fn set_trigger(&mut self, trigger: Option<Trigger>) {
+        self.trigger = trigger;
+    }
+
+    /// Starts conversions and the circular DMA transfer.
+    pub fn start(&mut self) {
+        compiler_fence(Ordering::SeqCst);
+
+        self.ring_buf.clear();
+        self.ring_buf.start();
+
+        let r = T::regs();
+
+        // Clear overrun flag before starting transfer.
+        r.isr().modify(|reg| {
+            reg.set_ovr(true);
+            reg.set_eoc(true);
+            reg.set_eos(true);
+        });
+
+        let (cont, exten, extsel) = match self.trigger {
+            Some(trigger) => (false, trigger.exten(), trigger.source),
+            None => (true, Exten::DISABLED, 0),
+        };
+
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        r.cfgr().modify(|reg| {
+            reg.set_discen(false);
+            reg.set_cont(cont);
+            reg.set_exten(exten);
+            reg.set_extsel(extsel);
+            reg.set_dmacfg(Dmacfg::CIRCULAR);
+            reg.set_dmaen(true);
+        });
+        #[cfg(any(adc_g0, adc_u0))]
+        r.cfgr1().modify(|reg| {
+            reg.set_discen(false);
+            reg.set_cont(cont);
+            reg.set_exten(exten);
+            reg.set_extsel(extsel);
+            reg.set_dmacfg(Dmacfg::CIRCULAR);
+            reg.set_dmaen(true);
+        });
+
+        // Start conversions, or wait for the trigger
+        r.cr().modify(|reg| {
+            reg.set_adstart(true);
+        });
+    }
+
+    /// Stops conversions and the DMA transfer, leaving the ADC enabled.
+    ///
+    /// Calling [`start`](Self::start) or [`read`](Self::read) starts them again.
+    pub fn stop(&mut self) {
+        Adc::<T>::cancel_conversions();
+
+        self.ring_buf.request_pause();
+
+        let r = T::regs();
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        r.cfgr().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_exten(Exten::DISABLED);
+            reg.set_dmaen(false);
+        });
+        #[cfg(any(adc_g0, adc_u0))]
+        r.cfgr1().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_exten(Exten::DISABLED);
+            reg.set_dmaen(false);
+        });
+
+        r.isr().modify(|reg| reg.set_ovr(true));
+
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn is_running() -> bool {
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        return T::regs().cfgr().read().dmaen();
+        #[cfg(any(adc_g0, adc_u0))]
+        return T::regs().cfgr1().read().dmaen();
+    }
+
+    /// Waits for the next half of the DMA buffer to be filled and copies it to `measurements`.
+    ///
+    /// `N` must be half of the DMA buffer length. Every call returns the next half, so calling
+    /// `read` in a loop yields an uninterrupted stream of samples, ordered as the sequence:
+    /// `[sq0 sq1 sq2 sq0 sq1 sq2 ...]`.
+    ///
+    /// Conversions are started if they are not running. On overrun they are stopped and an
+    /// error is returned, the next call to `read` starts them again.
+    ///
+    /// Example:
+    /// ```rust,ignore
+    /// const DMA_BUF_LEN: usize = 2 * 2 * 64;
+    /// let mut dma_buf = [0u16; DMA_BUF_LEN];
+    /// let mut adc = adc.into_ring_buffered(
+    ///     p.DMA1_CH1,
+    ///     &mut dma_buf,
+    ///     [
+    ///         (&mut pin0.degrade_adc(), SampleTime::CYCLES12_5),
+    ///         (&mut pin1.degrade_adc(), SampleTime::CYCLES12_5),
+    ///     ]
+    ///     .into_iter(),
+    ///     Some(Trigger { source: 9, edge: TriggerEdge::Rising }),
+    /// );
+    ///
+    /// let mut measurements = [0u16; DMA_BUF_LEN / 2];
+    /// loop {
+    ///     match adc.read(&mut measurements).await {
+    ///         Ok(_) => defmt::info!("adc: {}", measurements),
+    ///         Err(e) => defmt::warn!("Error: {:?}", e),
+    ///     }
+    /// }
+    /// ```
+    pub async fn read<const N: usize>(&mut self, measurements: &mut [u16; N]) -> Result<usize, OverrunError> {
+        assert_eq!(
+            self.ring_buf.capacity() / 2,
+            N,
+            "Buffer size must be half the size of the ring buffer"
+        );
+
+        if !Self::is_running() {
+            self.start();
+        }
+
+        if T::regs().isr().read().ovr() {
+            self.stop();
+            return Err(OverrunError);
+        }
+
+        match self.ring_buf.read_exact(measurements).await {
+            Ok(len) => Ok(len),
+            Err(_) => {
+                self.stop();
+                Err(OverrunError)
+            }
+        }
+    }
+}
+
+impl<T: Instance> Drop for RingBufferedAdc<'_, T> {
+    fn drop(&mut self) {
+        self.stop();
+        T::regs().cr().modify(|reg| reg.set_addis(true));
+        rcc::disable::<T>();
+    }
+}
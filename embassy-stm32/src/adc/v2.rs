@@ -7,7 +7,7 @@ use crate::time::Hertz;
 use crate::{rcc, Peripheral};
 
 mod ringbuffered_v2;
-pub use ringbuffered_v2::{RingBufferedAdc, Sequence};
+pub use ringbuffered_v2::{OverrunError, RingBufferedAdc, Sequence};
 
 /// Default VREF voltage used for sample conversion to millivolts.
 pub const VREF_DEFAULT_MV: u32 = 3300;
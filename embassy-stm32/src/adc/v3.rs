@@ -1,6 +1,8 @@
 use cfg_if::cfg_if;
 use embassy_hal_internal::into_ref;
 use pac::adc::vals::Dmacfg;
+#[cfg(not(any(adc_g0, adc_u0)))]
+use pac::adc::vals::{Adcaldif, Difsel};
 
 use super::{
     blocking_delay_us, Adc, AdcChannel, AnyAdcChannel, Instance, Resolution, RxDma, SampleTime, SealedAdcChannel,
@@ -8,6 +10,9 @@ use super::{
 use crate::dma::Transfer;
 use crate::{pac, rcc, Peripheral};
 
+mod ringbuffered_v3;
+pub use ringbuffered_v3::{OverrunError, RingBufferedAdc};
+
 /// Default VREF voltage used for sample conversion to millivolts.
 pub const VREF_DEFAULT_MV: u32 = 3300;
 /// VREF voltage used for factory calibration of VREFINTCAL register.
@@ -303,58 +308,7 @@ impl<'d, T: Instance> Adc<'d, T> {
         Self::cancel_conversions();
         self.enable();
 
-        // Set sequence length
-        #[cfg(not(any(adc_g0, adc_u0)))]
-        T::regs().sqr1().modify(|w| {
-            w.set_l(sequence.len() as u8 - 1);
-        });
-
-        #[cfg(any(adc_g0, adc_u0))]
-        let mut channel_mask = 0;
-
-        // Configure channels and ranks
-        for (_i, (channel, sample_time)) in sequence.enumerate() {
-            Self::configure_channel(channel, sample_time);
-
-            // Each channel is sampled according to sequence
-            #[cfg(not(any(adc_g0, adc_u0)))]
-            match _i {
-                0..=3 => {
-                    T::regs().sqr1().modify(|w| {
-                        w.set_sq(_i, channel.channel());
-                    });
-                }
-                4..=8 => {
-                    T::regs().sqr2().modify(|w| {
-                        w.set_sq(_i - 4, channel.channel());
-                    });
-                }
-                9..=13 => {
-                    T::regs().sqr3().modify(|w| {
-                        w.set_sq(_i - 9, channel.channel());
-                    });
-                }
-                14..=15 => {
-                    T::regs().sqr4().modify(|w| {
-                        w.set_sq(_i - 14, channel.channel());
-                    });
-                }
-                _ => unreachable!(),
-            }
-
-            #[cfg(any(adc_g0, adc_u0))]
-            {
-                channel_mask |= 1 << channel.channel();
-            }
-        }
-
-        // On G0 and U0 enabled channels are sampled from 0 to last channel.
-        // It is possible to add up to 8 sequences if CHSELRMOD = 1.
-        // However for supporting more than 8 channels alternative CHSELRMOD = 0 approach is used.
-        #[cfg(any(adc_g0, adc_u0))]
-        T::regs().chselr().modify(|reg| {
-            reg.set_chsel(channel_mask);
-        });
+        Self::configure_sequence(sequence);
 
         // Set continuous mode with oneshot dma.
         // Clear overrun flag before starting transfer.
@@ -410,6 +364,65 @@ impl<'d, T: Instance> Adc<'d, T> {
         });
     }
 
+    /// Configures the regular sequence, each channel being converted with its own sample time.
+    fn configure_sequence<'a>(sequence: impl ExactSizeIterator<Item = (&'a mut AnyAdcChannel<T>, SampleTime)>)
+    where
+        T: 'a,
+    {
+        // Set sequence length
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        T::regs().sqr1().modify(|w| {
+            w.set_l(sequence.len() as u8 - 1);
+        });
+
+        #[cfg(any(adc_g0, adc_u0))]
+        let mut channel_mask = 0;
+
+        // Configure channels and ranks
+        for (_i, (channel, sample_time)) in sequence.enumerate() {
+            Self::configure_channel(channel, sample_time);
+
+            // Each channel is sampled according to sequence
+            #[cfg(not(any(adc_g0, adc_u0)))]
+            match _i {
+                0..=3 => {
+                    T::regs().sqr1().modify(|w| {
+                        w.set_sq(_i, channel.channel());
+                    });
+                }
+                4..=8 => {
+                    T::regs().sqr2().modify(|w| {
+                        w.set_sq(_i - 4, channel.channel());
+                    });
+                }
+                9..=13 => {
+                    T::regs().sqr3().modify(|w| {
+                        w.set_sq(_i - 9, channel.channel());
+                    });
+                }
+                14..=15 => {
+                    T::regs().sqr4().modify(|w| {
+                        w.set_sq(_i - 14, channel.channel());
+                    });
+                }
+                _ => unreachable!(),
+            }
+
+            #[cfg(any(adc_g0, adc_u0))]
+            {
+                channel_mask |= 1 << channel.channel();
+            }
+        }
+
+        // On G0 and U0 enabled channels are sampled from 0 to last channel.
+        // It is possible to add up to 8 sequences if CHSELRMOD = 1.
+        // However for supporting more than 8 channels alternative CHSELRMOD = 0 approach is used.
+        #[cfg(any(adc_g0, adc_u0))]
+        T::regs().chselr().modify(|reg| {
+            reg.set_chsel(channel_mask);
+        });
+    }
+
     fn configure_channel(channel: &mut impl AdcChannel<T>, sample_time: SampleTime) {
         // RM0492, RM0481, etc.
         // "This option bit must be set to 1 when ADCx_INP0 or ADCx_INN1 channel is selected."
@@ -454,19 +467,62 @@ impl<'d, T: Instance> Adc<'d, T> {
         val
     }
 
-    #[cfg(any(adc_g0, adc_u0))]
+    /// Set oversampling shift, the accumulated result is shifted right by `shift` bits (0 to 8).
     pub fn set_oversampling_shift(&mut self, shift: u8) {
+        assert!(shift <= 8, "Oversampling shift cannot be more than 8 bits");
         T::regs().cfgr2().modify(|reg| reg.set_ovss(shift));
     }
 
-    #[cfg(any(adc_g0, adc_u0))]
+    /// Set oversampling ratio, `ratio` accumulates 2^(ratio + 1) conversions (2 to 256).
     pub fn set_oversampling_ratio(&mut self, ratio: u8) {
+        assert!(ratio <= 7, "Oversampling ratio cannot be more than 256 conversions");
         T::regs().cfgr2().modify(|reg| reg.set_ovsr(ratio));
     }
 
-    #[cfg(any(adc_g0, adc_u0))]
+    /// Enable oversampling of regular conversions.
+    ///
+    /// With a ratio of 2^n and a shift below n bits, results have more than 12 bits.
     pub fn oversampling_enable(&mut self, enable: bool) {
+        Self::cancel_conversions();
+        #[cfg(any(adc_g0, adc_u0))]
         T::regs().cfgr2().modify(|reg| reg.set_ovse(enable));
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        T::regs().cfgr2().modify(|reg| reg.set_rovse(enable));
+    }
+
+    /// Configure `channel` as the positive input of a differential pair, or back to single-ended.
+    ///
+    /// In differential mode, the negative input is the positive input of the next channel, which
+    /// must not be converted anymore. Results are offset by half of the full scale, equal
+    /// inputs convert to mid-code.
+    #[cfg(not(any(adc_g0, adc_u0)))]
+    pub fn set_differential(&mut self, channel: &mut impl AdcChannel<T>, enable: bool) {
+        Self::cancel_conversions();
+
+        // Input mode can only be changed while the ADC is disabled
+        if T::regs().cr().read().aden() {
+            T::regs().cr().modify(|reg| reg.set_addis(true));
+            while T::regs().cr().read().aden() {}
+        }
+
+        T::regs().difsel().modify(|reg| {
+            reg.set_difsel(
+                channel.channel() as usize,
+                if enable {
+                    Difsel::DIFFERENTIAL
+                } else {
+                    Difsel::SINGLEENDED
+                },
+            )
+        });
+
+        if enable {
+            // Differential conversions use their own calibration factor
+            T::regs().cr().modify(|reg| reg.set_adcaldif(Adcaldif::DIFFERENTIAL));
+            T::regs().cr().modify(|reg| reg.set_adcal(true));
+            while T::regs().cr().read().adcal() {}
+            T::regs().cr().modify(|reg| reg.set_adcaldif(Adcaldif::SINGLEENDED));
+        }
     }
 
     fn set_channel_sample_time(_ch: u8, sample_time: SampleTime) {
@@ -181,7 +181,7 @@ impl<'d, T: Instance> Adc<'d, T> {
         s.power_up();
         s.configure_differential_inputs();
 
-        s.calibrate();
+        s.calibrate(Adcaldif::SINGLEENDED);
         blocking_delay_us(1);
 
         s.enable();
@@ -207,9 +207,9 @@ impl<'d, T: Instance> Adc<'d, T> {
         });
     }
 
-    fn calibrate(&mut self) {
+    fn calibrate(&mut self, mode: Adcaldif) {
         T::regs().cr().modify(|w| {
-            w.set_adcaldif(Adcaldif::SINGLEENDED);
+            w.set_adcaldif(mode);
             w.set_adcallin(true);
         });
 
@@ -298,6 +298,58 @@ impl<'d, T: Instance> Adc<'d, T> {
         })
     }
 
+    /// Set hardware oversampling, accumulating `ratio` conversions (1 to 1024) shifted right by
+    /// `shift` bits (0 to 11).
+    ///
+    /// Unlike [`set_averaging`](Self::set_averaging), a shift smaller than log2(`ratio`) keeps
+    /// the extra resolution, up to 16 bits. A ratio of 1 disables oversampling.
+    pub fn set_oversampling(&mut self, ratio: u16, shift: u8) {
+        assert!(
+            (1..=1024).contains(&ratio),
+            "Oversampling ratio must be between 1 and 1024"
+        );
+        assert!(shift <= 11, "Oversampling shift cannot be more than 11 bits");
+
+        Self::cancel_conversions();
+
+        T::regs().cfgr2().modify(|reg| {
+            reg.set_rovse(ratio > 1);
+            reg.set_osvr(ratio - 1);
+            reg.set_ovss(shift);
+        })
+    }
+
+    /// Configure `channel` as the positive input of a differential pair, or back to single-ended.
+    ///
+    /// In differential mode, the negative input is the positive input of the next channel, which
+    /// must not be converted anymore. Results are offset by half of the full scale, equal
+    /// inputs convert to mid-code.
+    pub fn set_differential(&mut self, channel: &mut impl AdcChannel<T>, enable: bool) {
+        Self::cancel_conversions();
+
+        // Input mode can only be changed while the ADC is disabled
+        T::regs().cr().modify(|w| w.set_addis(true));
+        while T::regs().cr().read().aden() {}
+
+        T::regs().difsel().modify(|w| {
+            w.set_difsel(
+                channel.channel() as usize,
+                if enable {
+                    Difsel::DIFFERENTIAL
+                } else {
+                    Difsel::SINGLEENDED
+                },
+            );
+        });
+
+        if enable {
+            // Differential conversions use their own calibration factor
+            self.calibrate(Adcaldif::DIFFERENTIAL);
+        }
+
+        self.enable();
+    }
+
     /// Perform a single conversion.
     fn convert(&mut self) -> u16 {
         T::regs().isr().modify(|reg| {
@@ -2,7 +2,7 @@
 
 use core::marker::PhantomData;
 
-use super::{ExtendedId, Fifo, Id, StandardId};
+use super::{ExtendedId, Fifo, FilterOwner, Id, StandardId};
 
 const F32_RTR: u32 = 0b010; // set the RTR bit to match remote frames
 const F32_IDE: u32 = 0b100; // set the IDE bit to match extended identifiers
@@ -24,7 +24,7 @@ pub struct ListEntry16(u16);
 pub struct ListEntry32(u32);
 
 /// A 16-bit identifier mask.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Mask16 {
     id: u16,
@@ -32,7 +32,7 @@ pub struct Mask16 {
 }
 
 /// A 32-bit identifier mask.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Mask32 {
     id: u32,
@@ -168,7 +168,7 @@ impl Mask32 {
 }
 
 /// The configuration of a filter bank.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BankConfig {
     /// Specify up to 4 exact standard CAN ID's.
@@ -209,6 +209,70 @@ impl From<Mask32> for BankConfig {
     }
 }
 
+impl BankConfig {
+    /// Returns the values of the FxR1 and FxR2 registers for this configuration.
+    fn to_registers(self) -> (u32, u32) {
+        match self {
+            BankConfig::List16([a, b, c, d]) => (
+                (u32::from(b.0) << 16) | u32::from(a.0),
+                (u32::from(d.0) << 16) | u32::from(c.0),
+            ),
+            BankConfig::List32([a, b]) => (a.0, b.0),
+            BankConfig::Mask16([a, b]) => (
+                (u32::from(a.mask) << 16) | u32::from(a.id),
+                (u32::from(b.mask) << 16) | u32::from(b.id),
+            ),
+            BankConfig::Mask32(a) => (a.id, a.mask),
+        }
+    }
+
+    /// Reconstructs a configuration from the mode and scale bits and the FxR1 and FxR2 registers.
+    fn from_registers(list_mode: bool, single_scale: bool, fxr1: u32, fxr2: u32) -> Self {
+        let low = |r: u32| r as u16;
+        let high = |r: u32| (r >> 16) as u16;
+        match (list_mode, single_scale) {
+            (true, false) => BankConfig::List16([
+                ListEntry16(low(fxr1)),
+                ListEntry16(high(fxr1)),
+                ListEntry16(low(fxr2)),
+                ListEntry16(high(fxr2)),
+            ]),
+            (true, true) => BankConfig::List32([ListEntry32(fxr1), ListEntry32(fxr2)]),
+            (false, false) => BankConfig::Mask16([
+                Mask16 {
+                    id: low(fxr1),
+                    mask: high(fxr1),
+                },
+                Mask16 {
+                    id: low(fxr2),
+                    mask: high(fxr2),
+                },
+            ]),
+            (false, true) => BankConfig::Mask32(Mask32 { id: fxr1, mask: fxr2 }),
+        }
+    }
+}
+
+/// Returns `INDEX`, failing to compile if the instance `T` has no filter bank with that index.
+///
+/// This only checks against the total number of filter banks of the instance. On chips with
+/// splittable filter banks, the index must additionally be below (master) or at or above (slave)
+/// the split index at runtime.
+///
+/// ```rust,ignore
+/// const BANK: u8 = filter::checked_bank_index::<peripherals::CAN1, 20>();
+/// can.modify_filters().enable_bank(BANK, Fifo::Fifo0, Mask32::accept_all());
+/// ```
+pub const fn checked_bank_index<T: FilterOwner, const INDEX: u8>() -> u8 {
+    const {
+        assert!(
+            INDEX < T::NUM_FILTER_BANKS,
+            "filter bank index out of range for this CAN instance"
+        );
+    }
+    INDEX
+}
+
 /// Interface to the filter banks of a CAN peripheral.
 pub struct MasterFilters<'a> {
     /// Number of assigned filter banks.
@@ -289,6 +353,13 @@ impl MasterFilters<'_> {
         self.banks_imm().enable(index, fifo, config.into());
         self
     }
+
+    /// Returns the FIFO assignment and configuration of a filter bank, or `None` if it is disabled.
+    ///
+    /// If `index` is out of bounds, this will panic.
+    pub fn bank(&self, index: u8) -> Option<(Fifo, BankConfig)> {
+        self.banks_imm().get(index)
+    }
 }
 
 impl MasterFilters<'_> {
@@ -372,6 +443,13 @@ impl SlaveFilters<'_> {
         self.banks_imm().enable(index, fifo, config.into());
         self
     }
+
+    /// Returns the FIFO assignment and configuration of a filter bank, or `None` if it is disabled.
+    ///
+    /// If `index` is out of bounds, this will panic.
+    pub fn bank(&self, index: u8) -> Option<(Fifo, BankConfig)> {
+        self.banks_imm().get(index)
+    }
 }
 
 struct FilterBanks {
@@ -418,25 +496,7 @@ impl FilterBanks {
         self.info.regs.0.fs1r().modify(|reg| reg.set_fsc(index as usize, scale));
 
         // Configure filter register.
-        let (fxr1, fxr2);
-        match config {
-            BankConfig::List16([a, b, c, d]) => {
-                fxr1 = (u32::from(b.0) << 16) | u32::from(a.0);
-                fxr2 = (u32::from(d.0) << 16) | u32::from(c.0);
-            }
-            BankConfig::List32([a, b]) => {
-                fxr1 = a.0;
-                fxr2 = b.0;
-            }
-            BankConfig::Mask16([a, b]) => {
-                fxr1 = (u32::from(a.mask) << 16) | u32::from(a.id);
-                fxr2 = (u32::from(b.mask) << 16) | u32::from(b.id);
-            }
-            BankConfig::Mask32(a) => {
-                fxr1 = a.id;
-                fxr2 = a.mask;
-            }
-        };
+        let (fxr1, fxr2) = config.to_registers();
         let bank = self.info.regs.0.fb(index as usize);
         bank.fr1().write(|w| w.0 = fxr1);
         bank.fr2().write(|w| w.0 = fxr2);
@@ -455,6 +515,29 @@ impl FilterBanks {
         // Set active.
         self.info.regs.0.fa1r().modify(|reg| reg.set_fact(index as usize, true))
     }
+
+    fn get(&self, index: u8) -> Option<(Fifo, BankConfig)> {
+        self.assert_bank_index(index);
+
+        let regs = &self.info.regs.0;
+        let index = index as usize;
+        if !regs.fa1r().read().fact(index) {
+            return None;
+        }
+
+        let fifo = match regs.ffa1r().read().ffa(index) {
+            false => Fifo::Fifo0,
+            true => Fifo::Fifo1,
+        };
+        let bank = regs.fb(index);
+        let config = BankConfig::from_registers(
+            regs.fm1r().read().fbm(index),
+            regs.fs1r().read().fsc(index),
+            bank.fr1().read().0,
+            bank.fr2().read().0,
+        );
+        Some((fifo, config))
+    }
 }
 
 /// Computes a bitmask for per-filter-bank registers that only includes filters in the given range.
@@ -476,4 +559,35 @@ mod tests {
         assert_eq!(filter_bitmask(8, 1), 0x100);
         assert_eq!(filter_bitmask(8, 4), 0xf00);
     }
+
+    #[test]
+    fn test_bank_config_registers() {
+        let configs = [
+            BankConfig::List16([
+                ListEntry16::data_frames_with_id(StandardId::new(0x123).unwrap()),
+                ListEntry16::remote_frames_with_id(StandardId::new(0x456).unwrap()),
+                ListEntry16::data_frames_with_id(StandardId::ZERO),
+                ListEntry16::data_frames_with_id(StandardId::MAX),
+            ]),
+            BankConfig::List32([
+                ListEntry32::data_frames_with_id(ExtendedId::new(0x1234567).unwrap()),
+                ListEntry32::remote_frames_with_id(StandardId::new(0x7ff).unwrap()),
+            ]),
+            BankConfig::Mask16([
+                Mask16::frames_with_std_id(StandardId::new(0x100).unwrap(), StandardId::new(0x700).unwrap()),
+                Mask16::accept_all(),
+            ]),
+            BankConfig::Mask32(Mask32::frames_with_ext_id(
+                ExtendedId::new(0x18ff0000).unwrap(),
+                ExtendedId::new(0x1fff0000).unwrap(),
+            )),
+        ];
+
+        for config in configs {
+            let list_mode = matches!(config, BankConfig::List16(_) | BankConfig::List32(_));
+            let single_scale = matches!(config, BankConfig::List32(_) | BankConfig::Mask32(_));
+            let (fxr1, fxr2) = config.to_registers();
+            assert_eq!(BankConfig::from_registers(list_mode, single_scale, fxr1, fxr2), config);
+        }
+    }
 }
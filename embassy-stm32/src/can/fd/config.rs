@@ -356,6 +356,15 @@ pub struct FdCanConfig {
     pub global_filter: GlobalFilter,
     /// TX buffer mode (FIFO or priority queue)
     pub tx_buffer_mode: TxBufferMode,
+    /// Enables or disables automatic bus-off recovery
+    ///
+    /// If this is enabled, the driver restarts the peripheral as soon as it enters the bus-off
+    /// state, and the peripheral rejoins the bus after 128 occurrences of 11 recessive bits.
+    /// Otherwise, it stays in bus-off until recovery is started with
+    /// [`Properties::recover_from_bus_off`](crate::can::Properties::recover_from_bus_off).
+    ///
+    /// Automatic bus-off recovery is enabled by default.
+    pub automatic_bus_off_recovery: bool,
 }
 
 impl FdCanConfig {
@@ -452,6 +461,15 @@ impl FdCanConfig {
         self.tx_buffer_mode = txbm;
         self
     }
+
+    /// Enables or disables automatic bus-off recovery
+    ///
+    /// Automatic bus-off recovery is enabled by default.
+    #[inline]
+    pub const fn set_automatic_bus_off_recovery(mut self, enabled: bool) -> Self {
+        self.automatic_bus_off_recovery = enabled;
+        self
+    }
 }
 
 impl Default for FdCanConfig {
@@ -470,6 +488,7 @@ impl Default for FdCanConfig {
             timestamp_source: TimestampSource::None,
             global_filter: GlobalFilter::default(),
             tx_buffer_mode: TxBufferMode::Priority,
+            automatic_bus_off_recovery: true,
         }
     }
 }
@@ -71,10 +71,10 @@ impl Registers {
         }
     }
 
-    pub fn put_tx_frame(&self, bufidx: usize, header: &Header, buffer: &[u8]) {
+    pub fn put_tx_frame(&self, bufidx: usize, header: &Header, buffer: &[u8], event: Event) {
         let mailbox = self.tx_buffer_element(bufidx);
         mailbox.reset();
-        put_tx_header(mailbox, header);
+        put_tx_header(mailbox, header, event);
         put_tx_data(mailbox, &buffer[..header.len() as usize]);
 
         // Set <idx as Mailbox> as ready to transmit
@@ -95,6 +95,51 @@ impl Registers {
         }
     }
 
+    /// Returns the last error codes of the arbitration/classic phase and of the data phase.
+    ///
+    /// Reading clears both codes.
+    pub fn last_errors(&self) -> (Option<BusError>, Option<BusError>) {
+        let psr = self.regs.psr().read();
+        cfg_if! {
+            if #[cfg(can_fdcan_h7)] {
+                let (lec, dlec) = (psr.lec(), psr.dlec());
+            } else {
+                let (lec, dlec) = (psr.lec().to_bits(), psr.dlec().to_bits());
+            }
+        }
+        (Self::reg_to_error(lec), Self::reg_to_error(dlec))
+    }
+
+    /// Reads the oldest entry of the TX event FIFO.
+    ///
+    /// Returns the identifier, the message marker and the raw timestamp of the transmitted frame.
+    pub fn read_tx_event(&self) -> Option<(embedded_can::Id, u8, u16)> {
+        let status = self.regs.txefs().read();
+        if status.effl() < 1 {
+            return None;
+        }
+
+        let idx = status.efgi();
+        let event = self.msg_ram_mut().transmit.efsa[idx as usize].read();
+        let id = make_id(event.id().bits(), event.xtd().bits());
+        let marker = event.mm().bits();
+        let ts = event.txts().bits();
+
+        // Acknowledge the element, which frees it and increments the get index
+        self.regs.txefa().modify(|w| w.set_efai(idx));
+
+        Some((id, marker, ts))
+    }
+
+    /// Restarts the peripheral after it went bus-off.
+    ///
+    /// The peripheral rejoins the bus after 128 occurrences of 11 consecutive recessive bits.
+    pub fn recover_from_bus_off(&self) {
+        if self.regs.psr().read().bo() {
+            self.regs.cccr().modify(|w| w.set_init(false));
+        }
+    }
+
     pub fn curr_error(&self) -> Option<BusError> {
         let err = { self.regs.psr().read() };
         if err.bo() {
@@ -212,6 +257,16 @@ impl Registers {
     }
 
     pub fn write<F: embedded_can::Frame + CanHeader>(&self, frame: &F) -> nb::Result<Option<F>, Infallible> {
+        self.write_with_event(frame, Event::NoEvent)
+    }
+
+    /// Same as [`write`](Self::write), storing a TX event with the given marker once the frame
+    /// was transmitted if `event` is [`Event::Event`].
+    pub fn write_with_event<F: embedded_can::Frame + CanHeader>(
+        &self,
+        frame: &F,
+        event: Event,
+    ) -> nb::Result<Option<F>, Infallible> {
         let (idx, pending_frame) = if self.tx_queue_is_full() {
             if self.tx_queue_mode() == TxBufferMode::Fifo {
                 // Does not make sense to cancel a pending frame when using FIFO
@@ -238,7 +293,7 @@ impl Registers {
             (idx, None)
         };
 
-        self.put_tx_frame(idx as usize, frame.header(), frame.data());
+        self.put_tx_frame(idx as usize, frame.header(), frame.data(), event);
 
         Ok(pending_frame)
     }
@@ -368,6 +423,7 @@ impl Registers {
             w.set_rfne(0, true); // Rx Fifo 0 New Msg
             w.set_rfne(1, true); // Rx Fifo 1 New Msg
             w.set_tce(true); //  Tx Complete
+            w.set_tefne(true); // Tx Event FIFO New Entry
             w.set_boe(true); // Bus-Off Status Changed
         });
         self.regs.ile().modify(|w| {
@@ -650,7 +706,7 @@ fn make_id(id: u32, extended: bool) -> embedded_can::Id {
     }
 }
 
-fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header) {
+fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header, event: Event) {
     let (id, id_type) = match header.id() {
         // A standard identifier has to be written to ID[28:18].
         embedded_can::Id::Standard(id) => ((id.as_raw() as u32) << 18, IdType::StandardId),
@@ -672,7 +728,7 @@ fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header) {
             .xtd()
             .set_id_type(id_type)
             .set_len(DataLength::new(header.len(), frame_format))
-            .set_event(Event::NoEvent)
+            .set_event(event)
             .fdf()
             .set_format(frame_format)
             .brs()
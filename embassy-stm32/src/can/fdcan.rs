@@ -9,6 +9,7 @@ use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::channel::{Channel, DynamicReceiver, DynamicSender};
 use embassy_sync::waitqueue::AtomicWaker;
 
+use crate::can::fd::message_ram::enums::Event;
 use crate::can::fd::peripheral::Registers;
 use crate::gpio::{AfType, OutputType, Pull, Speed};
 use crate::interrupt::typelevel::Interrupt;
@@ -33,6 +34,18 @@ pub type Timestamp = embassy_time::Instant;
 #[cfg(not(feature = "time"))]
 pub type Timestamp = u16;
 
+/// Transmit event, stored once a frame written with a message marker went out on the bus.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxEvent {
+    /// Identifier of the transmitted frame
+    pub id: embedded_can::Id,
+    /// Message marker the frame was written with
+    pub marker: u8,
+    /// Time at which the frame was transmitted
+    pub ts: Timestamp,
+}
+
 /// Interrupt handler channel 0.
 pub struct IT0InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -50,6 +63,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::IT0Interrupt> for IT0Interrup
         }
         if ir.tefn() {
             regs.ir().write(|w| w.set_tefn(true));
+            T::state().tx_event_waker.wake();
         }
 
         match &T::state().tx_mode {
@@ -86,8 +100,13 @@ impl<T: Instance> interrupt::typelevel::Handler<T::IT0Interrupt> for IT0Interrup
         if ir.bo() {
             regs.ir().write(|w| w.set_bo(true));
             if regs.psr().read().bo() {
-                // Initiate bus-off recovery sequence by resetting CCCR.INIT
-                regs.cccr().modify(|w| w.set_init(false));
+                if T::state().automatic_bus_off_recovery {
+                    // Initiate bus-off recovery sequence by resetting CCCR.INIT
+                    regs.cccr().modify(|w| w.set_init(false));
+                } else {
+                    // Let readers observe the bus-off state, recovery is up to the application
+                    T::state().err_waker.wake();
+                }
             }
         }
     }
@@ -264,6 +283,7 @@ impl<'d> CanConfigurator<'d> {
             unsafe {
                 let mut_state = state as *mut State;
                 (*mut_state).ns_per_timer_tick = ns_per_timer_tick;
+                (*mut_state).automatic_bus_off_recovery = self.config.automatic_bus_off_recovery;
             }
         });
         self.info.regs.into_mode(self.config, mode);
@@ -335,6 +355,12 @@ impl<'d> Can<'d> {
         self.state.tx_mode.write(self.info, frame).await
     }
 
+    /// Same as [`write`](Self::write), but requests a [`TxEvent`] carrying `marker` to be
+    /// stored once the frame was transmitted.
+    pub async fn write_with_marker(&mut self, frame: &Frame, marker: u8) -> Option<Frame> {
+        self.state.tx_mode.write_with_marker(self.info, frame, marker).await
+    }
+
     /// Returns the next received message frame
     pub async fn read(&mut self) -> Result<Envelope, BusError> {
         self.state.rx_mode.read_classic(self.info, self.state).await
@@ -348,11 +374,27 @@ impl<'d> Can<'d> {
         self.state.tx_mode.write_fd(self.info, frame).await
     }
 
+    /// Same as [`write_fd`](Self::write_fd), but requests a [`TxEvent`] carrying `marker` to be
+    /// stored once the frame was transmitted.
+    pub async fn write_fd_with_marker(&mut self, frame: &FdFrame, marker: u8) -> Option<FdFrame> {
+        self.state.tx_mode.write_with_marker(self.info, frame, marker).await
+    }
+
     /// Returns the next received message frame
     pub async fn read_fd(&mut self) -> Result<FdEnvelope, BusError> {
         self.state.rx_mode.read_fd(self.info, self.state).await
     }
 
+    /// Waits for the next entry of the TX event FIFO.
+    pub async fn read_tx_event(&mut self) -> TxEvent {
+        read_tx_event(self.info, self.state).await
+    }
+
+    /// Returns the next entry of the TX event FIFO, if any.
+    pub fn try_read_tx_event(&mut self) -> Option<TxEvent> {
+        try_read_tx_event(self.info, self.state)
+    }
+
     /// Split instance into separate portions: Tx(write), Rx(read), common properties
     pub fn split(self) -> (CanTx<'d>, CanRx<'d>, Properties) {
         (
@@ -678,6 +720,47 @@ impl<'c, 'd> CanTx<'d> {
     pub async fn write_fd(&mut self, frame: &FdFrame) -> Option<FdFrame> {
         self.state.tx_mode.write_fd(self.info, frame).await
     }
+
+    /// Same as [`write`](Self::write), but requests a [`TxEvent`] carrying `marker` to be
+    /// stored once the frame was transmitted.
+    pub async fn write_with_marker(&mut self, frame: &Frame, marker: u8) -> Option<Frame> {
+        self.state.tx_mode.write_with_marker(self.info, frame, marker).await
+    }
+
+    /// Same as [`write_fd`](Self::write_fd), but requests a [`TxEvent`] carrying `marker` to be
+    /// stored once the frame was transmitted.
+    pub async fn write_fd_with_marker(&mut self, frame: &FdFrame, marker: u8) -> Option<FdFrame> {
+        self.state.tx_mode.write_with_marker(self.info, frame, marker).await
+    }
+
+    /// Waits for the next entry of the TX event FIFO.
+    pub async fn read_tx_event(&mut self) -> TxEvent {
+        read_tx_event(self.info, self.state).await
+    }
+
+    /// Returns the next entry of the TX event FIFO, if any.
+    pub fn try_read_tx_event(&mut self) -> Option<TxEvent> {
+        try_read_tx_event(self.info, self.state)
+    }
+}
+
+fn try_read_tx_event(info: &'static Info, state: &'static State) -> Option<TxEvent> {
+    info.regs.read_tx_event().map(|(id, marker, ts)| TxEvent {
+        id,
+        marker,
+        ts: info.calc_timestamp(state.ns_per_timer_tick, ts),
+    })
+}
+
+async fn read_tx_event(info: &'static Info, state: &'static State) -> TxEvent {
+    poll_fn(|cx| {
+        state.tx_event_waker.register(cx.waker());
+        match try_read_tx_event(info, state) {
+            Some(event) => Poll::Ready(event),
+            None => Poll::Pending,
+        }
+    })
+    .await
 }
 
 enum RxMode {
@@ -819,11 +902,16 @@ impl TxMode {
     /// frame is dropped from the mailbox, it is returned.  If no lower-priority frames
     /// can be replaced, this call asynchronously waits for a frame to be successfully
     /// transmitted, then tries again.
-    async fn write_generic<F: embedded_can::Frame + CanHeader>(&self, info: &'static Info, frame: &F) -> Option<F> {
+    async fn write_generic<F: embedded_can::Frame + CanHeader>(
+        &self,
+        info: &'static Info,
+        frame: &F,
+        event: Event,
+    ) -> Option<F> {
         poll_fn(|cx| {
             self.register(cx.waker());
 
-            if let Ok(dropped) = info.regs.write(frame) {
+            if let Ok(dropped) = info.regs.write_with_event(frame, event) {
                 return Poll::Ready(dropped);
             }
 
@@ -839,7 +927,7 @@ impl TxMode {
     /// can be replaced, this call asynchronously waits for a frame to be successfully
     /// transmitted, then tries again.
     async fn write(&self, info: &'static Info, frame: &Frame) -> Option<Frame> {
-        self.write_generic::<_>(info, frame).await
+        self.write_generic::<_>(info, frame, Event::NoEvent).await
     }
 
     /// Queues the message to be sent but exerts backpressure.  If a lower-priority
@@ -847,7 +935,16 @@ impl TxMode {
     /// can be replaced, this call asynchronously waits for a frame to be successfully
     /// transmitted, then tries again.
     async fn write_fd(&self, info: &'static Info, frame: &FdFrame) -> Option<FdFrame> {
-        self.write_generic::<_>(info, frame).await
+        self.write_generic::<_>(info, frame, Event::NoEvent).await
+    }
+
+    async fn write_with_marker<F: embedded_can::Frame + CanHeader>(
+        &self,
+        info: &'static Info,
+        frame: &F,
+        marker: u8,
+    ) -> Option<F> {
+        self.write_generic::<_>(info, frame, Event::Event(marker)).await
     }
 }
 
@@ -916,6 +1013,34 @@ impl Properties {
             (true, _) => BusErrorMode::BusOff,
         }
     }
+
+    /// Get the CAN error logging counter
+    ///
+    /// Counts protocol errors since it was last read, saturating at 255. Reading clears it.
+    pub fn error_logging_count(&self) -> u8 {
+        self.info.regs.regs.ecr().read().cel()
+    }
+
+    /// Returns true if the RX error counter reached the error passive level
+    pub fn rx_error_passive(&self) -> bool {
+        self.info.regs.regs.ecr().read().rp()
+    }
+
+    /// Get the last protocol error codes
+    ///
+    /// Returns the last error of the arbitration phase (or of a classic frame) and the last
+    /// error of the data phase of an FD frame with bit rate switching. Reading clears both codes.
+    pub fn last_errors(&self) -> (Option<BusError>, Option<BusError>) {
+        self.info.regs.last_errors()
+    }
+
+    /// Starts the bus-off recovery sequence.
+    ///
+    /// Only needed if automatic bus-off recovery was disabled in the config. Has no effect
+    /// unless the peripheral is in the bus-off state.
+    pub fn recover_from_bus_off(&self) {
+        self.info.regs.recover_from_bus_off()
+    }
 }
 
 struct State {
@@ -924,6 +1049,8 @@ struct State {
     pub ns_per_timer_tick: u64,
 
     pub err_waker: AtomicWaker,
+    pub tx_event_waker: AtomicWaker,
+    pub automatic_bus_off_recovery: bool,
 }
 
 impl State {
@@ -933,6 +1060,8 @@ impl State {
             tx_mode: TxMode::NonBuffered(AtomicWaker::new()),
             ns_per_timer_tick: 0,
             err_waker: AtomicWaker::new(),
+            tx_event_waker: AtomicWaker::new(),
+            automatic_bus_off_recovery: true,
         }
     }
 }
@@ -347,6 +347,41 @@ macro_rules! impl_dma_methods {
                     w.set_dmaen(Self::IDX, false);
                 });
             }
+
+            /// Stream 12 bit right-aligned samples to this channel through a circular DMA buffer.
+            ///
+            /// One sample is output per event of `trigger`, typically the TRGO output of a timer
+            /// which sets the sample rate. Prefill the buffer with
+            /// [`RingBufferedDac::write_immediate`], then [`start`](RingBufferedDac::start)
+            /// playback and keep it fed with [`write`](RingBufferedDac::write).
+            #[cfg(not(gpdma))]
+            pub fn ring_buffered<'a>(
+                &'a mut self,
+                dma_buf: &'a mut [u16],
+                trigger: TriggerSel,
+            ) -> RingBufferedDac<'a, T, $n> {
+                assert!(!dma_buf.is_empty() && dma_buf.len() <= 0xFFFF);
+
+                self.set_trigger(trigger);
+                self.set_triggering(true);
+
+                let request = self.dma.request();
+                let opts = crate::dma::TransferOptions::default();
+                let ring_buf = unsafe {
+                    crate::dma::WritableRingBuffer::new(
+                        self.dma.reborrow(),
+                        request,
+                        T::regs().dhr12r(Self::IDX).as_ptr() as *mut u16,
+                        dma_buf,
+                        opts,
+                    )
+                };
+
+                RingBufferedDac {
+                    phantom: PhantomData,
+                    ring_buf,
+                }
+            }
         }
     };
 }
@@ -354,6 +389,73 @@ macro_rules! impl_dma_methods {
 impl_dma_methods!(1, DacDma1);
 impl_dma_methods!(2, DacDma2);
 
+/// The DMA caught up with samples that were not written in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnderrunError;
+
+/// DAC channel playing samples from a circular DMA buffer.
+///
+/// Created with [`DacChannel::ring_buffered`].
+#[cfg(not(gpdma))]
+pub struct RingBufferedDac<'a, T: Instance, const N: u8> {
+    phantom: PhantomData<T>,
+    ring_buf: crate::dma::WritableRingBuffer<'a, u16>,
+}
+
+#[cfg(not(gpdma))]
+impl<'a, T: Instance, const N: u8> RingBufferedDac<'a, T, N> {
+    const IDX: usize = (N - 1) as usize;
+
+    /// Write samples directly to the buffer, to fill it before starting playback.
+    pub fn write_immediate(&mut self, samples: &[u16]) -> Result<usize, UnderrunError> {
+        self.ring_buf
+            .write_immediate(samples)
+            .map(|(len, _)| len)
+            .map_err(|_| UnderrunError)
+    }
+
+    /// Start playback, one sample being output per trigger event.
+    pub fn start(&mut self) {
+        T::regs().cr().modify(|w| {
+            w.set_en(Self::IDX, true);
+            w.set_dmaen(Self::IDX, true);
+        });
+        self.ring_buf.start();
+    }
+
+    /// Write all of `samples`, waiting for the DMA to free up space in the buffer.
+    ///
+    /// Writing half of the buffer per call swaps buffers: the samples are copied into the half
+    /// that has just been played while the DMA plays the other one.
+    pub async fn write(&mut self, samples: &[u16]) -> Result<usize, UnderrunError> {
+        self.ring_buf.write_exact(samples).await.map_err(|_| UnderrunError)
+    }
+
+    /// Number of samples that can currently be written without waiting.
+    pub fn free(&mut self) -> Result<usize, UnderrunError> {
+        self.ring_buf.len().map_err(|_| UnderrunError)
+    }
+
+    /// Capacity of the buffer, in samples.
+    pub const fn capacity(&self) -> usize {
+        self.ring_buf.capacity()
+    }
+
+    /// Stop playback once all written samples have been output.
+    pub async fn stop(&mut self) {
+        self.ring_buf.stop().await;
+        T::regs().cr().modify(|w| w.set_dmaen(Self::IDX, false));
+    }
+}
+
+#[cfg(not(gpdma))]
+impl<'a, T: Instance, const N: u8> Drop for RingBufferedDac<'a, T, N> {
+    fn drop(&mut self) {
+        T::regs().cr().modify(|w| w.set_dmaen(Self::IDX, false));
+    }
+}
+
 impl<'d, T: Instance, const N: u8, DMA> Drop for DacChannel<'d, T, N, DMA> {
     fn drop(&mut self) {
         rcc::disable::<T>();
@@ -1,4 +1,7 @@
 //! Generic SMI Ethernet PHY
+//!
+//! Drives any IEEE 802.3 clause 22 compliant PHY. Vendor specific setup and status registers
+//! can be plugged in through [`PhyVendor`].
 
 use core::task::Context;
 
@@ -7,7 +10,7 @@ use embassy_time::{Duration, Timer};
 #[cfg(feature = "time")]
 use futures_util::FutureExt;
 
-use super::{StationManagement, PHY};
+use super::{Duplex, LinkMode, LinkSpeed, StationManagement, PHY};
 
 #[allow(dead_code)]
 mod phy_consts {
@@ -23,6 +26,16 @@ mod phy_consts {
     pub const PHY_REG_CTL: u8 = 0x0D; // Ethernet PHY Register Control
     pub const PHY_REG_ADDAR: u8 = 0x0E; // Ethernet PHY Address or Data
 
+    // LAN8742A PHY Special Control/Status Register
+    pub const LAN8742A_REG_PSCSR: u8 = 0x1F;
+    pub const LAN8742A_PSCSR_AUTODONE: u16 = 1 << 12;
+    pub const LAN8742A_PSCSR_SPEED_MASK: u16 = 0b111 << 2;
+    pub const LAN8742A_PSCSR_10HD: u16 = 0b001 << 2;
+    pub const LAN8742A_PSCSR_10FD: u16 = 0b101 << 2;
+    pub const LAN8742A_PSCSR_100HD: u16 = 0b010 << 2;
+    pub const LAN8742A_PSCSR_100FD: u16 = 0b110 << 2;
+
+    pub const PHY_MMD_PCS: u8 = 0x03;
     pub const PHY_REG_WUCSR: u16 = 0x8010;
 
     pub const PHY_REG_BCR_COLTEST: u16 = 1 << 7;
@@ -39,12 +52,82 @@ mod phy_consts {
     pub const PHY_REG_BSR_UP: u16 = 1 << 2;
     pub const PHY_REG_BSR_FAULT: u16 = 1 << 4;
     pub const PHY_REG_BSR_ANDONE: u16 = 1 << 5;
+
+    // Technology ability field of the auto-negotiation advertisement registers
+    pub const PHY_REG_AN_10HD: u16 = 1 << 5;
+    pub const PHY_REG_AN_10FD: u16 = 1 << 6;
+    pub const PHY_REG_AN_100HD: u16 = 1 << 7;
+    pub const PHY_REG_AN_100FD: u16 = 1 << 8;
+    pub const PHY_REG_AN_ABILITY: u16 = PHY_REG_AN_10HD | PHY_REG_AN_10FD | PHY_REG_AN_100HD | PHY_REG_AN_100FD;
 }
 use self::phy_consts::*;
 
+/// Vendor specific extensions of the generic PHY driver.
+pub trait PhyVendor {
+    /// Called after the PHY was reset, before auto-negotiation is started.
+    fn init<S: StationManagement>(&mut self, phy_addr: u8, sm: &mut S) {
+        let _ = (phy_addr, sm);
+    }
+
+    /// Reads the speed and duplex mode of the link from vendor specific registers.
+    ///
+    /// Returning `None` resolves them from the clause 22 auto-negotiation registers.
+    fn link_mode<S: StationManagement>(&mut self, phy_addr: u8, sm: &mut S) -> Option<LinkMode> {
+        let _ = (phy_addr, sm);
+        None
+    }
+}
+
+/// PHY without vendor specific behaviour.
+pub struct NoVendor;
+
+impl PhyVendor for NoVendor {}
+
+/// Hooks used by [`GenericSMI::new`].
+///
+/// Clears the LAN8742A wake-up control and status register, as the driver always did, and
+/// resolves the link mode from the clause 22 registers like [`NoVendor`].
+pub struct DefaultVendor;
+
+impl PhyVendor for DefaultVendor {
+    fn init<S: StationManagement>(&mut self, phy_addr: u8, sm: &mut S) {
+        // Clear WU CSR
+        sm.mmd_write(phy_addr, PHY_MMD_PCS, PHY_REG_WUCSR, 0);
+    }
+}
+
+/// Microchip LAN8742A, also reading the link mode from its special control/status register.
+pub struct Lan8742a;
+
+impl PhyVendor for Lan8742a {
+    fn init<S: StationManagement>(&mut self, phy_addr: u8, sm: &mut S) {
+        DefaultVendor.init(phy_addr, sm);
+    }
+
+    fn link_mode<S: StationManagement>(&mut self, phy_addr: u8, sm: &mut S) -> Option<LinkMode> {
+        // Speed indication of the PHY Special Control/Status Register
+        let pscsr = sm.smi_read(phy_addr, LAN8742A_REG_PSCSR);
+        let (speed, duplex) = match pscsr & LAN8742A_PSCSR_SPEED_MASK {
+            LAN8742A_PSCSR_10HD => (LinkSpeed::Mbps10, Duplex::Half),
+            LAN8742A_PSCSR_10FD => (LinkSpeed::Mbps10, Duplex::Full),
+            LAN8742A_PSCSR_100HD => (LinkSpeed::Mbps100, Duplex::Half),
+            LAN8742A_PSCSR_100FD => (LinkSpeed::Mbps100, Duplex::Full),
+            _ => return None,
+        };
+        if sm.smi_read(phy_addr, PHY_REG_BCR) & PHY_REG_BCR_AN != 0 && pscsr & LAN8742A_PSCSR_AUTODONE == 0 {
+            return None;
+        }
+        Some(LinkMode { speed, duplex })
+    }
+}
+
 /// Generic SMI Ethernet PHY implementation
-pub struct GenericSMI {
+///
+/// Defaults to the [`DefaultVendor`] hooks, use [`GenericSMI::with_vendor`] to pick others, like
+/// [`Lan8742a`].
+pub struct GenericSMI<V: PhyVendor = DefaultVendor> {
     phy_addr: u8,
+    vendor: V,
     #[cfg(feature = "time")]
     poll_interval: Duration,
 }
@@ -52,23 +135,31 @@ pub struct GenericSMI {
 impl GenericSMI {
     /// Construct the PHY. It assumes the address `phy_addr` in the SMI communication
     pub fn new(phy_addr: u8) -> Self {
+        Self::with_vendor(phy_addr, DefaultVendor)
+    }
+}
+
+impl<V: PhyVendor> GenericSMI<V> {
+    /// Construct the PHY with vendor specific hooks. It assumes the address `phy_addr` in the
+    /// SMI communication
+    pub fn with_vendor(phy_addr: u8, vendor: V) -> Self {
         Self {
             phy_addr,
+            vendor,
             #[cfg(feature = "time")]
             poll_interval: Duration::from_millis(500),
         }
     }
 }
 
-unsafe impl PHY for GenericSMI {
+unsafe impl<V: PhyVendor> PHY for GenericSMI<V> {
     fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
         sm.smi_write(self.phy_addr, PHY_REG_BCR, PHY_REG_BCR_RESET);
         while sm.smi_read(self.phy_addr, PHY_REG_BCR) & PHY_REG_BCR_RESET == PHY_REG_BCR_RESET {}
     }
 
     fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
-        // Clear WU CSR
-        self.smi_write_ext(sm, PHY_REG_WUCSR, 0);
+        self.vendor.init(self.phy_addr, sm);
 
         // Enable auto-negotiation
         sm.smi_write(
@@ -99,21 +190,68 @@ unsafe impl PHY for GenericSMI {
         // Got link
         true
     }
+
+    fn link_mode<S: StationManagement>(&mut self, sm: &mut S) -> Option<LinkMode> {
+        if let Some(mode) = self.vendor.link_mode(self.phy_addr, sm) {
+            return Some(mode);
+        }
+
+        let bcr = sm.smi_read(self.phy_addr, PHY_REG_BCR);
+        if bcr & PHY_REG_BCR_AN == 0 {
+            // Forced mode
+            return Some(LinkMode {
+                speed: match bcr & PHY_REG_BCR_100M {
+                    0 => LinkSpeed::Mbps10,
+                    _ => LinkSpeed::Mbps100,
+                },
+                duplex: match bcr & PHY_REG_BCR_FD {
+                    0 => Duplex::Half,
+                    _ => Duplex::Full,
+                },
+            });
+        }
+
+        let partner = sm.smi_read(self.phy_addr, PHY_REG_ANRX);
+        if partner & PHY_REG_AN_ABILITY == 0 {
+            // The link partner doesn't auto-negotiate, so the link was set up by parallel
+            // detection, which is half duplex. Clause 22 has no register for the detected speed,
+            // most PHYs reflect it in BCR, others need `PhyVendor::link_mode`.
+            return Some(LinkMode {
+                speed: match bcr & PHY_REG_BCR_100M {
+                    0 => LinkSpeed::Mbps10,
+                    _ => LinkSpeed::Mbps100,
+                },
+                duplex: Duplex::Half,
+            });
+        }
+
+        // Highest common denominator of our and the link partner's abilities
+        let common = sm.smi_read(self.phy_addr, PHY_REG_ANTX) & partner;
+        let (speed, duplex) = if common & PHY_REG_AN_100FD != 0 {
+            (LinkSpeed::Mbps100, Duplex::Full)
+        } else if common & PHY_REG_AN_100HD != 0 {
+            (LinkSpeed::Mbps100, Duplex::Half)
+        } else if common & PHY_REG_AN_10FD != 0 {
+            (LinkSpeed::Mbps10, Duplex::Full)
+        } else if common & PHY_REG_AN_10HD != 0 {
+            (LinkSpeed::Mbps10, Duplex::Half)
+        } else {
+            return None;
+        };
+        Some(LinkMode { speed, duplex })
+    }
 }
 
 /// Public functions for the PHY
-impl GenericSMI {
+impl<V: PhyVendor> GenericSMI<V> {
     /// Set the SMI polling interval.
     #[cfg(feature = "time")]
     pub fn set_poll_interval(&mut self, poll_interval: Duration) {
         self.poll_interval = poll_interval
     }
 
-    // Writes a value to an extended PHY register in MMD address space
-    fn smi_write_ext<S: StationManagement>(&mut self, sm: &mut S, reg_addr: u16, reg_data: u16) {
-        sm.smi_write(self.phy_addr, PHY_REG_CTL, 0x0003); // set address
-        sm.smi_write(self.phy_addr, PHY_REG_ADDAR, reg_addr);
-        sm.smi_write(self.phy_addr, PHY_REG_CTL, 0x4003); // set data
-        sm.smi_write(self.phy_addr, PHY_REG_ADDAR, reg_data);
+    /// Access the vendor specific hooks.
+    pub fn vendor(&mut self) -> &mut V {
+        &mut self.vendor
     }
 }
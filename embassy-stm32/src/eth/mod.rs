@@ -107,10 +107,21 @@ impl<'d, T: Instance, P: PHY> embassy_net_driver::Driver for Ethernet<'d, T, P>
     }
 
     fn link_state(&mut self, cx: &mut Context) -> LinkState {
-        if self.phy.poll_link(&mut self.station_management, cx) {
-            LinkState::Up
-        } else {
-            LinkState::Down
+        let up = self.phy.poll_link(&mut self.station_management, cx);
+        if up != self.link_up {
+            debug!("eth: link {}", if up { "up" } else { "down" });
+        }
+        if up && !self.link_up {
+            // The link may have been negotiated with another partner, or another mode
+            if let Some(mode) = self.phy.link_mode(&mut self.station_management) {
+                self.set_link_mode(mode);
+            }
+        }
+        self.link_up = up;
+
+        match up {
+            true => LinkState::Up,
+            false => LinkState::Down,
         }
     }
 
@@ -165,6 +176,63 @@ pub unsafe trait StationManagement {
     fn smi_read(&mut self, phy_addr: u8, reg: u8) -> u16;
     /// Write a register over SMI.
     fn smi_write(&mut self, phy_addr: u8, reg: u8, val: u16);
+
+    /// Read a register of an MDIO manageable device (MMD), through the clause 22 indirect
+    /// access registers.
+    fn mmd_read(&mut self, phy_addr: u8, mmd: u8, reg: u16) -> u16 {
+        mmd_select(self, phy_addr, mmd, reg);
+        self.smi_read(phy_addr, MMD_REG_ADDAR)
+    }
+
+    /// Write a register of an MDIO manageable device (MMD), through the clause 22 indirect
+    /// access registers.
+    fn mmd_write(&mut self, phy_addr: u8, mmd: u8, reg: u16, val: u16) {
+        mmd_select(self, phy_addr, mmd, reg);
+        self.smi_write(phy_addr, MMD_REG_ADDAR, val);
+    }
+}
+
+/// MMD access control register
+const MMD_REG_CTL: u8 = 0x0D;
+/// MMD access address/data register
+const MMD_REG_ADDAR: u8 = 0x0E;
+
+fn mmd_select<S: StationManagement + ?Sized>(sm: &mut S, phy_addr: u8, mmd: u8, reg: u16) {
+    let mmd = u16::from(mmd & 0x1f);
+    // Set the register address, then switch to data access without post increment
+    sm.smi_write(phy_addr, MMD_REG_CTL, mmd);
+    sm.smi_write(phy_addr, MMD_REG_ADDAR, reg);
+    sm.smi_write(phy_addr, MMD_REG_CTL, 0x4000 | mmd);
+}
+
+/// Speed of an Ethernet link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkSpeed {
+    /// 10 Mbit/s
+    Mbps10,
+    /// 100 Mbit/s
+    Mbps100,
+}
+
+/// Duplex mode of an Ethernet link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Duplex {
+    /// Half duplex
+    Half,
+    /// Full duplex
+    Full,
+}
+
+/// Speed and duplex mode of an Ethernet link, as negotiated by the PHY.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkMode {
+    /// Link speed
+    pub speed: LinkSpeed,
+    /// Duplex mode
+    pub duplex: Duplex,
 }
 
 /// Traits for an Ethernet PHY
@@ -177,8 +245,16 @@ pub unsafe trait PHY {
     fn phy_reset<S: StationManagement>(&mut self, sm: &mut S);
     /// PHY initialisation.
     fn phy_init<S: StationManagement>(&mut self, sm: &mut S);
-    /// Poll link to see if it is up and FD with 100Mbps
+    /// Poll link to see if it is up
     fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> bool;
+    /// Speed and duplex mode of the link, called when `poll_link` reports it coming up.
+    ///
+    /// The MAC is reconfigured whenever this changes. Returning `None` leaves the MAC
+    /// configuration untouched, which is 100Mbps full duplex after initialisation.
+    fn link_mode<S: StationManagement>(&mut self, sm: &mut S) -> Option<LinkMode> {
+        let _ = sm;
+        None
+    }
 }
 
 impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
@@ -193,6 +269,13 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
     pub unsafe fn station_management(&mut self) -> &mut impl StationManagement {
         &mut self.station_management
     }
+
+    /// Speed and duplex mode the MAC is configured for, `None` while the link is down.
+    ///
+    /// It is read from the PHY each time the link comes up.
+    pub fn link_mode(&self) -> Option<LinkMode> {
+        self.link_up.then_some(self.link_mode)
+    }
 }
 
 trait SealedInstance {
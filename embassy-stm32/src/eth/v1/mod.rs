@@ -55,6 +55,8 @@ pub struct Ethernet<'d, T: Instance, P: PHY> {
     pub(crate) phy: P,
     pub(crate) station_management: EthernetStationManagement<T>,
     pub(crate) mac_addr: [u8; 6],
+    pub(crate) link_mode: LinkMode,
+    pub(crate) link_up: bool,
 }
 
 #[cfg(eth_v1a)]
@@ -228,6 +230,11 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 clock_range: clock_range,
             },
             mac_addr,
+            link_mode: LinkMode {
+                speed: LinkSpeed::Mbps100,
+                duplex: Duplex::Full,
+            },
+            link_up: false,
             tx: TDesRing::new(&mut queue.tx_desc, &mut queue.tx_buf),
             rx: RDesRing::new(&mut queue.rx_desc, &mut queue.rx_buf),
         };
@@ -264,6 +271,26 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
 
         this
     }
+
+    /// Reconfigure the MAC for the speed and duplex mode of the link.
+    pub(crate) fn set_link_mode(&mut self, mode: LinkMode) {
+        if mode == self.link_mode {
+            return;
+        }
+        debug!("eth: link mode changed to {:?}", mode);
+
+        T::regs().ethernet_mac().maccr().modify(|w| {
+            w.set_fes(match mode.speed {
+                LinkSpeed::Mbps10 => Fes::FES10,
+                LinkSpeed::Mbps100 => Fes::FES100,
+            });
+            w.set_dm(match mode.duplex {
+                Duplex::Half => Dm::HALFDUPLEX,
+                Duplex::Full => Dm::FULLDUPLEX,
+            });
+        });
+        self.link_mode = mode;
+    }
 }
 
 /// Ethernet station management interface.
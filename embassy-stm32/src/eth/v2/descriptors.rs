@@ -2,6 +2,7 @@ use core::sync::atomic::{fence, Ordering};
 
 use vcell::VolatileCell;
 
+use super::ptp::{self, PtpMessageId, PtpTimestamp};
 use crate::eth::{Packet, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
 use crate::pac::ETH;
 
@@ -16,12 +17,15 @@ mod emac_consts {
     pub const EMAC_DES0_BUF1AP: u32 = 0xFFFF_FFFF;
 
     pub const EMAC_TDES2_IOC: u32 = 0x8000_0000;
+    pub const EMAC_TDES2_TTSE: u32 = 0x4000_0000;
     pub const EMAC_TDES2_B1L: u32 = 0x0000_3FFF;
+    pub const EMAC_TDES3_TTSS: u32 = 0x0002_0000;
 
     pub const EMAC_RDES3_IOC: u32 = 0x4000_0000;
     pub const EMAC_RDES3_PL: u32 = 0x0000_7FFF;
     pub const EMAC_RDES3_BUF1V: u32 = 0x0100_0000;
     pub const EMAC_RDES3_PKTLEN: u32 = 0x0000_7FFF;
+    pub const EMAC_RDES1_TSA: u32 = 0x0000_4000;
 }
 use emac_consts::*;
 
@@ -50,9 +54,21 @@ impl TDes {
     }
 
     /// Return true if this TDes is not currently owned by the DMA
-    fn available(&self) -> bool {
+    pub(crate) fn available(&self) -> bool {
         self.tdes3.get() & EMAC_DES3_OWN == 0
     }
+
+    /// Return the transmit timestamp written back by the DMA, if any
+    pub(crate) fn timestamp(&self) -> Option<PtpTimestamp> {
+        if self.tdes3.get() & EMAC_DES3_CTXT == 0 && self.tdes3.get() & EMAC_TDES3_TTSS != 0 {
+            Some(PtpTimestamp {
+                seconds: self.tdes1.get(),
+                nanoseconds: self.tdes0.get(),
+            })
+        } else {
+            None
+        }
+    }
 }
 
 pub(crate) struct TDesRing<'a> {
@@ -105,15 +121,26 @@ impl<'a> TDesRing<'a> {
         assert!(td.available());
         assert!(len as u32 <= EMAC_TDES2_B1L);
 
+        // Capture a timestamp for PTP event messages
+        let ptp_id = match ptp::enabled() {
+            true => PtpMessageId::from_frame(&self.buffers[self.index].0[..len]),
+            false => None,
+        };
+        let ttse = if ptp_id.is_some() { EMAC_TDES2_TTSE } else { 0 };
+
         // Read format
         td.tdes0.set(self.buffers[self.index].0.as_ptr() as u32);
-        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC);
+        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC | ttse);
 
         // FD: Contains first buffer of packet
         // LD: Contains last buffer of packet
         // Give the DMA engine ownership
         td.tdes3.set(EMAC_DES3_FD | EMAC_DES3_LD | EMAC_DES3_OWN);
 
+        if let Some(id) = ptp_id {
+            ptp::on_transmit(td, id);
+        }
+
         // Ensure changes to the descriptor are committed before DMA engine sees tail pointer store.
         // This will generate an DMB instruction.
         // "Preceding reads and writes cannot be moved past subsequent writes."
@@ -168,6 +195,27 @@ impl RDes {
         self.rdes3.get() & EMAC_DES3_OWN == 0 // Owned by us
     }
 
+    /// Return true if this RDes is a context descriptor, written back with a timestamp
+    #[inline(always)]
+    fn is_context(&self) -> bool {
+        self.rdes3.get() & (EMAC_DES3_OWN | EMAC_DES3_CTXT) == EMAC_DES3_CTXT
+    }
+
+    /// Return true if a context descriptor with the timestamp of this packet follows
+    #[inline(always)]
+    fn has_timestamp(&self) -> bool {
+        self.rdes1.get() & EMAC_RDES1_TSA != 0
+    }
+
+    /// Timestamp stored in a context descriptor
+    #[inline(always)]
+    fn timestamp(&self) -> PtpTimestamp {
+        PtpTimestamp {
+            seconds: self.rdes1.get(),
+            nanoseconds: self.rdes0.get(),
+        }
+    }
+
     #[inline(always)]
     fn set_ready(&mut self, buf: *mut u8) {
         self.rdes0.set(buf as u32);
@@ -218,6 +266,12 @@ impl<'a> RDesRing<'a> {
                 return None;
             }
 
+            // Timestamps are consumed with their packet, drop any stray context descriptor.
+            if descriptor.is_context() {
+                self.pop_descriptor();
+                continue;
+            }
+
             // If packet is invalid, pop it and try again.
             if !descriptor.valid() {
                 warn!("invalid packet: {:08x}", descriptor.rdes0.get());
@@ -225,6 +279,11 @@ impl<'a> RDesRing<'a> {
                 continue;
             }
 
+            // Wait for the context descriptor holding the timestamp to be written back.
+            if descriptor.has_timestamp() && !self.descriptors[self.next_index()].available() {
+                return None;
+            }
+
             break;
         }
 
@@ -235,6 +294,26 @@ impl<'a> RDesRing<'a> {
 
     /// Pop the packet previously returned by `available`.
     pub(crate) fn pop_packet(&mut self) {
+        let rd = &self.descriptors[self.index];
+        assert!(rd.available());
+
+        let ctx = &self.descriptors[self.next_index()];
+        if rd.valid() && rd.has_timestamp() && ctx.is_context() {
+            let len = (rd.rdes3.get() & EMAC_RDES3_PKTLEN) as usize;
+            ptp::on_receive(&self.buffers[self.index].0[..len], ctx.timestamp());
+            self.pop_descriptor();
+            self.pop_descriptor();
+        } else {
+            self.pop_descriptor();
+        }
+    }
+
+    fn next_index(&self) -> usize {
+        (self.index + 1) % self.descriptors.len()
+    }
+
+    /// Hand the current descriptor back to the DMA.
+    fn pop_descriptor(&mut self) {
         let rd = &mut self.descriptors[self.index];
         assert!(rd.available());
 
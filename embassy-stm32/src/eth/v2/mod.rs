@@ -1,4 +1,5 @@
 mod descriptors;
+mod ptp;
 
 use core::marker::PhantomData;
 use core::sync::atomic::{fence, Ordering};
@@ -7,6 +8,7 @@ use embassy_hal_internal::{into_ref, PeripheralRef};
 use stm32_metapac::syscfg::vals::EthSelPhy;
 
 pub(crate) use self::descriptors::{RDes, RDesRing, TDes, TDesRing};
+pub use self::ptp::{PtpClock, PtpMessageId, PtpTimestamp};
 use super::*;
 use crate::gpio::{AfType, AnyPin, OutputType, SealedPin as _, Speed};
 use crate::interrupt::InterruptExt;
@@ -32,6 +34,10 @@ impl interrupt::typelevel::Handler<interrupt::typelevel::ETH> for InterruptHandl
         // Delay two peripheral's clock
         dma.dmacsr().read();
         dma.dmacsr().read();
+
+        if ptp::enabled() {
+            ptp::collect_tx_timestamp();
+        }
     }
 }
 
@@ -44,6 +50,8 @@ pub struct Ethernet<'d, T: Instance, P: PHY> {
     pub(crate) phy: P,
     pub(crate) station_management: EthernetStationManagement<T>,
     pub(crate) mac_addr: [u8; 6],
+    pub(crate) link_mode: LinkMode,
+    pub(crate) link_up: bool,
 }
 
 /// Pins of ethernet driver.
@@ -264,6 +272,11 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
                 clock_range: clock_range,
             },
             mac_addr,
+            link_mode: LinkMode {
+                speed: LinkSpeed::Mbps100,
+                duplex: Duplex::Full,
+            },
+            link_up: false,
         };
 
         fence(Ordering::SeqCst);
@@ -296,6 +309,28 @@ impl<'d, T: Instance, P: PHY> Ethernet<'d, T, P> {
 
         this
     }
+
+    /// Enable PTP (IEEE 1588) timestamping and start the PTP clock at zero.
+    ///
+    /// Once enabled, the transmit and receive timestamps of PTP event messages are captured and
+    /// can be retrieved through the returned [`PtpClock`], which is also used to adjust the clock.
+    pub fn enable_ptp(&mut self) -> PtpClock<'d, T> {
+        PtpClock::init()
+    }
+
+    /// Reconfigure the MAC for the speed and duplex mode of the link.
+    pub(crate) fn set_link_mode(&mut self, mode: LinkMode) {
+        if mode == self.link_mode {
+            return;
+        }
+        debug!("eth: link mode changed to {:?}", mode);
+
+        T::regs().ethernet_mac().maccr().modify(|w| {
+            w.set_fes(mode.speed == LinkSpeed::Mbps100);
+            w.set_dm(mode.duplex == Duplex::Full);
+        });
+        self.link_mode = mode;
+    }
 }
 
 /// Ethernet SMI driver.
@@ -340,6 +375,9 @@ impl<'d, T: Instance, P: PHY> Drop for Ethernet<'d, T, P> {
         let mac = T::regs().ethernet_mac();
         let mtl = T::regs().ethernet_mtl();
 
+        // The packet queue may be gone after the driver, forget its descriptors
+        ptp::disable();
+
         // Disable the TX DMA and wait for any previous transmissions to be completed
         dma.dmactx_cr().modify(|w| w.set_st(false));
         while {
@@ -0,0 +1,546 @@
+//! PTP (IEEE 1588) hardware timestamping.
+
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use critical_section::Mutex;
+
+use super::descriptors::TDes;
+use super::Instance;
+use crate::rcc::SealedRccPeripheral;
+
+/// Number of timestamps kept per direction until they are taken, also the number of transmitted
+/// event messages waiting for their timestamp.
+const TIMESTAMP_LOG_LEN: usize = 4;
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// UDP port of PTP event messages.
+const PTP_EVENT_PORT: u16 = 319;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PENDING_TX: Mutex<RefCell<PendingTx>> = Mutex::new(RefCell::new(PendingTx::new()));
+static TX_TIMESTAMPS: Mutex<RefCell<TimestampLog>> = Mutex::new(RefCell::new(TimestampLog::new()));
+static RX_TIMESTAMPS: Mutex<RefCell<TimestampLog>> = Mutex::new(RefCell::new(TimestampLog::new()));
+
+/// Time of the PTP clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PtpTimestamp {
+    /// Seconds
+    pub seconds: u32,
+    /// Nanoseconds, always below 1_000_000_000
+    pub nanoseconds: u32,
+}
+
+impl PtpTimestamp {
+    /// Total number of nanoseconds.
+    pub const fn as_nanos(&self) -> u64 {
+        self.seconds as u64 * NANOS_PER_SECOND as u64 + self.nanoseconds as u64
+    }
+}
+
+/// Identifies a PTP event message (Sync, Delay_Req, Pdelay_Req or Pdelay_Resp).
+///
+/// Timestamps are only captured for event messages, sent over UDP (IPv4 or IPv6) or directly
+/// over Ethernet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PtpMessageId {
+    /// `messageType` field of the PTP header
+    pub message_type: u8,
+    /// `sequenceId` field of the PTP header
+    pub sequence_id: u16,
+}
+
+impl PtpMessageId {
+    /// Extracts the message id of a PTP event message from an Ethernet frame.
+    pub(crate) fn from_frame(frame: &[u8]) -> Option<Self> {
+        let mut ethertype = u16::from_be_bytes(frame.get(12..14)?.try_into().unwrap());
+        let mut offset = 14;
+        if ethertype == 0x8100 {
+            // 802.1Q VLAN tag
+            ethertype = u16::from_be_bytes(frame.get(16..18)?.try_into().unwrap());
+            offset += 4;
+        }
+
+        let ptp = match ethertype {
+            0x88f7 => frame.get(offset..)?,
+            0x0800 => {
+                let ip = frame.get(offset..)?;
+                let ihl = (*ip.first()? & 0x0f) as usize * 4;
+                if *ip.get(9)? != 17 {
+                    return None;
+                }
+                udp_event_payload(ip.get(ihl..)?)?
+            }
+            0x86dd => {
+                let ip = frame.get(offset..)?;
+                if *ip.get(6)? != 17 {
+                    return None;
+                }
+                udp_event_payload(ip.get(40..)?)?
+            }
+            _ => return None,
+        };
+
+        let message_type = *ptp.first()? & 0x0f;
+        if message_type > 0x7 {
+            // General message, not timestamped
+            return None;
+        }
+        let sequence_id = u16::from_be_bytes(ptp.get(30..32)?.try_into().unwrap());
+
+        Some(Self {
+            message_type,
+            sequence_id,
+        })
+    }
+}
+
+fn udp_event_payload(udp: &[u8]) -> Option<&[u8]> {
+    let dst_port = u16::from_be_bytes(udp.get(2..4)?.try_into().unwrap());
+    if dst_port != PTP_EVENT_PORT {
+        return None;
+    }
+    udp.get(8..)
+}
+
+struct TimestampLog {
+    entries: [Option<(PtpMessageId, PtpTimestamp)>; TIMESTAMP_LOG_LEN],
+    next: usize,
+}
+
+impl TimestampLog {
+    const fn new() -> Self {
+        Self {
+            entries: [None; TIMESTAMP_LOG_LEN],
+            next: 0,
+        }
+    }
+
+    /// Records a timestamp, overwriting the oldest one if the log is full.
+    fn record(&mut self, id: PtpMessageId, ts: PtpTimestamp) {
+        self.entries[self.next] = Some((id, ts));
+        self.next = (self.next + 1) % TIMESTAMP_LOG_LEN;
+    }
+
+    fn take(&mut self, id: PtpMessageId) -> Option<PtpTimestamp> {
+        self.entries.iter_mut().find_map(|entry| match *entry {
+            Some((entry_id, ts)) if entry_id == id => {
+                *entry = None;
+                Some(ts)
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Transmitted event messages whose descriptor the DMA may still own.
+struct PendingTx {
+    /// Address of the descriptor and id of the message
+    entries: [Option<(usize, PtpMessageId)>; TIMESTAMP_LOG_LEN],
+    next: usize,
+}
+
+impl PendingTx {
+    const fn new() -> Self {
+        Self {
+            entries: [None; TIMESTAMP_LOG_LEN],
+            next: 0,
+        }
+    }
+
+    /// Adds a message, replacing the oldest one if all entries are in use.
+    fn push(&mut self, td: usize, id: PtpMessageId) {
+        // The DMA is done with a reused descriptor, so its message is stale.
+        let reused = self
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Some((entry_td, _)) if *entry_td == td));
+        if let Some(index) = reused {
+            self.entries[index] = Some((td, id));
+            return;
+        }
+
+        let index = self.entries.iter().position(Option::is_none).unwrap_or(self.next);
+        self.entries[index] = Some((td, id));
+        self.next = (index + 1) % TIMESTAMP_LOG_LEN;
+    }
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Stops tracking transmitted messages, called when the driver is dropped so no descriptor of
+/// its packet queue is accessed afterwards.
+pub(crate) fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+    critical_section::with(|cs| *PENDING_TX.borrow_ref_mut(cs) = PendingTx::new());
+}
+
+/// Remembers a transmitted PTP event message, its timestamp is collected once the DMA
+/// released the descriptor.
+pub(crate) fn on_transmit(td: &TDes, id: PtpMessageId) {
+    collect_tx_timestamp();
+    critical_section::with(|cs| PENDING_TX.borrow_ref_mut(cs).push(td as *const TDes as usize, id));
+}
+
+/// Records the timestamp of a received PTP event message.
+pub(crate) fn on_receive(frame: &[u8], ts: PtpTimestamp) {
+    if let Some(id) = PtpMessageId::from_frame(frame) {
+        critical_section::with(|cs| RX_TIMESTAMPS.borrow_ref_mut(cs).record(id, ts));
+    }
+}
+
+/// Collects the timestamps of the transmitted PTP event messages the DMA is done with.
+pub(crate) fn collect_tx_timestamp() {
+    critical_section::with(|cs| {
+        let mut pending = PENDING_TX.borrow_ref_mut(cs);
+        for entry in pending.entries.iter_mut() {
+            if let Some((td, id)) = *entry {
+                // SAFETY: entries are cleared when the driver is dropped, and the driver borrows the
+                // packet queue holding the descriptors, so they are still alive.
+                let td = unsafe { &*(td as *const TDes) };
+                if td.available() {
+                    if let Some(ts) = td.timestamp() {
+                        TX_TIMESTAMPS.borrow_ref_mut(cs).record(id, ts);
+                    }
+                    *entry = None;
+                }
+            }
+        }
+    })
+}
+
+/// Handle to the PTP clock of the Ethernet MAC.
+///
+/// Obtained with [`Ethernet::enable_ptp`](super::Ethernet::enable_ptp). The handle doesn't
+/// borrow the driver, so it can be used to discipline the clock while the driver is owned by the
+/// network stack, but it can't outlive the packet queue. No transmit timestamps are collected
+/// once the driver is dropped.
+pub struct PtpClock<'d, T: Instance> {
+    _peri: PhantomData<&'d mut T>,
+    base_addend: u32,
+}
+
+impl<'d, T: Instance> PtpClock<'d, T> {
+    /// Starts the PTP clock at zero and enables timestamping of PTP event messages.
+    pub(crate) fn init() -> Self {
+        let mac = T::regs().ethernet_mac();
+        let hclk = <T as SealedRccPeripheral>::frequency().0;
+
+        // Fine correction: an accumulator overflows at `hclk * addend / 2^32`, incrementing the
+        // sub-seconds by `ssinc` nanoseconds each time. Run it at half of HCLK or below, leaving
+        // room to speed the clock up.
+        let ssinc = (2 * NANOS_PER_SECOND).div_ceil(hclk);
+        assert!(ssinc <= u8::MAX as u32, "HCLK too low for the PTP clock");
+        let ptp_freq = NANOS_PER_SECOND as u64 / ssinc as u64;
+        let base_addend = ((ptp_freq << 32) / hclk as u64) as u32;
+
+        mac.macssir().write(|w| w.set_ssinc(ssinc as u8));
+        mac.mactscr().write(|w| {
+            w.set_tsena(true);
+            w.set_tscfupdt(true);
+            // Sub-seconds roll over at 10^9, so they count nanoseconds
+            w.set_tsctrlssr(true);
+            w.set_tsver2ena(true);
+            w.set_tsipena(true);
+            w.set_tsipv4ena(true);
+            w.set_tsipv6ena(true);
+            w.set_tsevntena(true);
+        });
+
+        let this = Self {
+            _peri: PhantomData,
+            base_addend,
+        };
+        this.set_addend(base_addend);
+        this.set_time(PtpTimestamp {
+            seconds: 0,
+            nanoseconds: 0,
+        });
+
+        ENABLED.store(true, Ordering::Relaxed);
+
+        this
+    }
+
+    /// Returns the current time of the PTP clock.
+    pub fn now(&self) -> PtpTimestamp {
+        let mac = T::regs().ethernet_mac();
+        loop {
+            let seconds = mac.macstsr().read().tss();
+            let nanoseconds = mac.macstnr().read().tsss();
+            // Retry if the seconds rolled over in between
+            if mac.macstsr().read().tss() == seconds {
+                return PtpTimestamp { seconds, nanoseconds };
+            }
+        }
+    }
+
+    /// Sets the time of the PTP clock.
+    pub fn set_time(&self, time: PtpTimestamp) {
+        let mac = T::regs().ethernet_mac();
+        Self::wait_update_done();
+        mac.macstsur().write(|w| w.set_tss(time.seconds));
+        mac.macstnur().write(|w| w.set_tsss(time.nanoseconds));
+        mac.mactscr().modify(|w| w.set_tsinit(true));
+        while mac.mactscr().read().tsinit() {}
+    }
+
+    /// Steps the PTP clock by `offset_ns` nanoseconds, forwards or backwards.
+    pub fn adjust_offset(&self, offset_ns: i64) {
+        let mac = T::regs().ethernet_mac();
+        let subtract = offset_ns < 0;
+        let offset = offset_ns.unsigned_abs();
+        let seconds = (offset / NANOS_PER_SECOND as u64) as u32;
+        let mut nanoseconds = (offset % NANOS_PER_SECOND as u64) as u32;
+        if subtract && nanoseconds != 0 {
+            // With digital rollover, the sub-seconds to subtract are given as 10^9 - value
+            nanoseconds = NANOS_PER_SECOND - nanoseconds;
+        }
+
+        Self::wait_update_done();
+        mac.macstsur().write(|w| w.set_tss(seconds));
+        mac.macstnur().write(|w| {
+            w.set_addsub(subtract);
+            w.set_tsss(nanoseconds);
+        });
+        mac.mactscr().modify(|w| w.set_tsupdt(true));
+        while mac.mactscr().read().tsupdt() {}
+    }
+
+    /// Speeds the PTP clock up or slows it down by `ppb` parts per billion, relative to its
+    /// nominal rate.
+    pub fn adjust_frequency(&self, ppb: i32) {
+        let delta = self.base_addend as i64 * ppb as i64 / NANOS_PER_SECOND as i64;
+        let addend = (self.base_addend as i64 + delta).clamp(0, u32::MAX as i64);
+        self.set_addend(addend as u32);
+    }
+
+    /// Returns the addend of the fine correction accumulator giving the nominal clock rate.
+    pub fn base_addend(&self) -> u32 {
+        self.base_addend
+    }
+
+    /// Returns the current addend of the fine correction accumulator.
+    pub fn addend(&self) -> u32 {
+        T::regs().ethernet_mac().mactsar().read().tsar()
+    }
+
+    /// Sets the addend of the fine correction accumulator.
+    pub fn set_addend(&self, addend: u32) {
+        let mac = T::regs().ethernet_mac();
+        while mac.mactscr().read().tsaddreg() {}
+        mac.mactsar().write(|w| w.set_tsar(addend));
+        mac.mactscr().modify(|w| w.set_tsaddreg(true));
+        while mac.mactscr().read().tsaddreg() {}
+    }
+
+    /// Takes the transmit timestamp of the given PTP event message, once it went out.
+    pub fn tx_timestamp(&self, id: PtpMessageId) -> Option<PtpTimestamp> {
+        collect_tx_timestamp();
+        critical_section::with(|cs| TX_TIMESTAMPS.borrow_ref_mut(cs).take(id))
+    }
+
+    /// Takes the receive timestamp of the given PTP event message.
+    pub fn rx_timestamp(&self, id: PtpMessageId) -> Option<PtpTimestamp> {
+        critical_section::with(|cs| RX_TIMESTAMPS.borrow_ref_mut(cs).take(id))
+    }
+
+    fn wait_update_done() {
+        let mac = T::regs().ethernet_mac();
+        while {
+            let r = mac.mactscr().read();
+            r.tsinit() || r.tsupdt()
+        } {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PendingTx, PtpMessageId, TIMESTAMP_LOG_LEN};
+
+    const DELAY_REQ: u8 = 0x1;
+
+    fn ptp_message(message_type: u8, sequence_id: u16) -> [u8; 44] {
+        let mut msg = [0u8; 44];
+        // transportSpecific 0, messageType
+        msg[0] = message_type;
+        // versionPTP 2
+        msg[1] = 0x02;
+        msg[2..4].copy_from_slice(&44u16.to_be_bytes());
+        msg[30..32].copy_from_slice(&sequence_id.to_be_bytes());
+        msg
+    }
+
+    fn ethernet(ethertype: u16, vlan: bool, payload: &[u8]) -> ([u8; 256], usize) {
+        let mut frame = [0u8; 256];
+        // Destination and source MAC, the PTP multicast address
+        frame[0..6].copy_from_slice(&[0x01, 0x1b, 0x19, 0x00, 0x00, 0x00]);
+        frame[6..12].copy_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        let mut offset = 12;
+        if vlan {
+            frame[12..14].copy_from_slice(&0x8100u16.to_be_bytes());
+            frame[14..16].copy_from_slice(&5u16.to_be_bytes());
+            offset += 4;
+        }
+        frame[offset..offset + 2].copy_from_slice(&ethertype.to_be_bytes());
+        offset += 2;
+        frame[offset..offset + payload.len()].copy_from_slice(payload);
+        (frame, offset + payload.len())
+    }
+
+    fn udp(dst_port: u16, payload: &[u8]) -> ([u8; 128], usize) {
+        let mut udp = [0u8; 128];
+        udp[0..2].copy_from_slice(&dst_port.to_be_bytes());
+        udp[2..4].copy_from_slice(&dst_port.to_be_bytes());
+        udp[4..6].copy_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        udp[8..8 + payload.len()].copy_from_slice(payload);
+        (udp, 8 + payload.len())
+    }
+
+    fn ipv4(udp: &[u8]) -> ([u8; 192], usize) {
+        let mut ip = [0u8; 192];
+        // Version 4, IHL 5
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&(20 + udp.len() as u16).to_be_bytes());
+        ip[8] = 1;
+        ip[9] = 17;
+        ip[12..16].copy_from_slice(&[192, 168, 1, 2]);
+        ip[16..20].copy_from_slice(&[224, 0, 1, 129]);
+        ip[20..20 + udp.len()].copy_from_slice(udp);
+        (ip, 20 + udp.len())
+    }
+
+    fn ipv6(udp: &[u8]) -> ([u8; 192], usize) {
+        let mut ip = [0u8; 192];
+        ip[0] = 0x60;
+        ip[4..6].copy_from_slice(&(udp.len() as u16).to_be_bytes());
+        ip[6] = 17;
+        ip[7] = 1;
+        // ff0e::181
+        ip[24] = 0xff;
+        ip[25] = 0x0e;
+        ip[38] = 0x01;
+        ip[39] = 0x81;
+        ip[40..40 + udp.len()].copy_from_slice(udp);
+        (ip, 40 + udp.len())
+    }
+
+    #[test]
+    fn test_from_frame_l2() {
+        let msg = ptp_message(DELAY_REQ, 0x1234);
+        let (frame, len) = ethernet(0x88f7, false, &msg);
+        assert_eq!(
+            PtpMessageId::from_frame(&frame[..len]),
+            Some(PtpMessageId {
+                message_type: DELAY_REQ,
+                sequence_id: 0x1234
+            })
+        );
+
+        // Follow_Up is a general message
+        let msg = ptp_message(0x8, 0x1234);
+        let (frame, len) = ethernet(0x88f7, false, &msg);
+        assert_eq!(PtpMessageId::from_frame(&frame[..len]), None);
+
+        // Truncated header
+        let (frame, len) = ethernet(0x88f7, false, &msg[..20]);
+        assert_eq!(PtpMessageId::from_frame(&frame[..len]), None);
+    }
+
+    #[test]
+    fn test_from_frame_vlan() {
+        let msg = ptp_message(0x0, 7);
+        let (frame, len) = ethernet(0x88f7, true, &msg);
+        assert_eq!(
+            PtpMessageId::from_frame(&frame[..len]),
+            Some(PtpMessageId {
+                message_type: 0x0,
+                sequence_id: 7
+            })
+        );
+
+        let (udp, udp_len) = udp(319, &msg);
+        let (ip, ip_len) = ipv4(&udp[..udp_len]);
+        let (frame, len) = ethernet(0x0800, true, &ip[..ip_len]);
+        assert_eq!(
+            PtpMessageId::from_frame(&frame[..len]),
+            Some(PtpMessageId {
+                message_type: 0x0,
+                sequence_id: 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_frame_ipv4() {
+        let msg = ptp_message(0x2, 0xBEEF);
+        let (udp_frame, udp_len) = udp(319, &msg);
+        let (ip, ip_len) = ipv4(&udp_frame[..udp_len]);
+        let (frame, len) = ethernet(0x0800, false, &ip[..ip_len]);
+        assert_eq!(
+            PtpMessageId::from_frame(&frame[..len]),
+            Some(PtpMessageId {
+                message_type: 0x2,
+                sequence_id: 0xBEEF
+            })
+        );
+
+        // General messages use port 320
+        let (udp_frame, udp_len) = udp(320, &msg);
+        let (ip, ip_len) = ipv4(&udp_frame[..udp_len]);
+        let (frame, len) = ethernet(0x0800, false, &ip[..ip_len]);
+        assert_eq!(PtpMessageId::from_frame(&frame[..len]), None);
+
+        // TCP
+        let (udp_frame, udp_len) = udp(319, &msg);
+        let (mut ip, ip_len) = ipv4(&udp_frame[..udp_len]);
+        ip[9] = 6;
+        let (frame, len) = ethernet(0x0800, false, &ip[..ip_len]);
+        assert_eq!(PtpMessageId::from_frame(&frame[..len]), None);
+    }
+
+    #[test]
+    fn test_from_frame_ipv6() {
+        let msg = ptp_message(0x3, 42);
+        let (udp_frame, udp_len) = udp(319, &msg);
+        let (ip, ip_len) = ipv6(&udp_frame[..udp_len]);
+        let (frame, len) = ethernet(0x86dd, false, &ip[..ip_len]);
+        assert_eq!(
+            PtpMessageId::from_frame(&frame[..len]),
+            Some(PtpMessageId {
+                message_type: 0x3,
+                sequence_id: 42
+            })
+        );
+    }
+
+    #[test]
+    fn test_pending_tx() {
+        let id = |sequence_id| PtpMessageId {
+            message_type: 0,
+            sequence_id,
+        };
+        let mut pending = PendingTx::new();
+        for i in 0..TIMESTAMP_LOG_LEN {
+            pending.push(0x100 + i * 0x10, id(i as u16));
+        }
+        assert!(pending.entries.iter().all(Option::is_some));
+
+        // A reused descriptor replaces its own entry
+        pending.push(0x110, id(10));
+        assert_eq!(pending.entries[1], Some((0x110, id(10))));
+        assert_eq!(pending.entries[0], Some((0x100, id(0))));
+
+        // A new descriptor replaces the oldest entry
+        pending.push(0x200, id(11));
+        assert_eq!(pending.entries[0], Some((0x200, id(11))));
+        pending.push(0x210, id(12));
+        assert_eq!(pending.entries[1], Some((0x210, id(12))));
+    }
+}
@@ -5,6 +5,9 @@
 #[cfg_attr(any(i2c_v2, i2c_v3), path = "v2.rs")]
 mod _version;
 
+#[cfg(any(i2c_v2, i2c_v3))]
+pub use _version::{Address, TargetCommand, TargetCommandKind, TargetConfig};
+
 use core::future::Future;
 use core::iter;
 use core::marker::PhantomData;
@@ -17,7 +20,7 @@ use embassy_time::{Duration, Instant};
 use crate::dma::ChannelAndRequest;
 #[cfg(gpio_v2)]
 use crate::gpio::Pull;
-use crate::gpio::{AfType, AnyPin, OutputType, SealedPin as _, Speed};
+use crate::gpio::{AfType, AnyPin, Flex, OutputType, SealedPin as _, Speed};
 use crate::interrupt::typelevel::Interrupt;
 use crate::mode::{Async, Blocking, Mode};
 use crate::rcc::{RccInfo, SealedRccPeripheral};
@@ -113,6 +116,8 @@ pub struct I2c<'d, M: Mode> {
     info: &'static Info,
     state: &'static State,
     kernel_clock: Hertz,
+    freq: Hertz,
+    config: Config,
     scl: Option<PeripheralRef<'d, AnyPin>>,
     sda: Option<PeripheralRef<'d, AnyPin>>,
     tx_dma: Option<ChannelAndRequest<'d>>,
@@ -187,6 +192,8 @@ impl<'d, M: Mode> I2c<'d, M> {
             info: T::info(),
             state: T::state(),
             kernel_clock: T::frequency(),
+            freq,
+            config,
             scl,
             sda,
             tx_dma,
@@ -210,6 +217,107 @@ impl<'d, M: Mode> I2c<'d, M> {
             deadline: Instant::now() + self.timeout,
         }
     }
+
+    /// Set the timeout applied to each transaction.
+    ///
+    /// This covers blocking, async and DMA transfers alike, and overrides [`Config::timeout`].
+    #[cfg(feature = "time")]
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Recover a bus that is stuck with SDA held low.
+    ///
+    /// A target can get stuck in the middle of a byte, for example if the controller was reset
+    /// during a transfer. The SCL and SDA pins are temporarily taken over as open-drain GPIOs and
+    /// SCL is clocked up to 9 times until the target releases SDA. A STOP condition is then
+    /// generated, the pins are handed back to the peripheral and the peripheral is reset and
+    /// re-initialized with the original configuration. A target configuration set with
+    /// `set_target_config` has to be applied again.
+    ///
+    /// Returns [`Error::Bus`] if SDA is still held low afterwards.
+    pub fn blocking_recover_bus(&mut self) -> Result<(), Error> {
+        let (Some(scl), Some(sda)) = (self.scl.as_mut(), self.sda.as_mut()) else {
+            return Ok(());
+        };
+
+        let scl_af_num = af_num(scl);
+        let sda_af_num = af_num(sda);
+
+        self.info.regs.cr1().modify(|w| w.set_pe(false));
+
+        let result = {
+            let mut scl = Flex::new(scl.reborrow());
+            let mut sda = Flex::new(sda.reborrow());
+
+            scl.set_high();
+            sda.set_high();
+            scl.set_as_input_output(Speed::Low);
+            sda.set_as_input_output(Speed::Low);
+
+            // Half of a 100 kHz clock period.
+            let half_period_us = 5;
+
+            for _ in 0..9 {
+                if sda.is_high() {
+                    break;
+                }
+                scl.set_low();
+                blocking_delay_us(half_period_us);
+                scl.set_high();
+                blocking_delay_us(half_period_us);
+            }
+
+            // STOP condition: SDA rising while SCL is high.
+            scl.set_low();
+            blocking_delay_us(half_period_us);
+            sda.set_low();
+            blocking_delay_us(half_period_us);
+            scl.set_high();
+            blocking_delay_us(half_period_us);
+            sda.set_high();
+            blocking_delay_us(half_period_us);
+
+            match sda.is_high() && scl.is_high() {
+                true => Ok(()),
+                false => Err(Error::Bus),
+            }
+        };
+
+        scl.set_as_af(scl_af_num, self.config.scl_af());
+        sda.set_as_af(sda_af_num, self.config.sda_af());
+
+        // A reset also clears a BUSY flag left behind by the stuck transfer.
+        self.info.rcc.disable();
+        self.enable_and_init(self.freq, self.config);
+
+        result
+    }
+}
+
+/// Read back the alternate function number currently selected for a pin.
+fn af_num(pin: &AnyPin) -> u8 {
+    #[cfg(gpio_v1)]
+    {
+        let _ = pin;
+        0
+    }
+    #[cfg(gpio_v2)]
+    {
+        let n = pin._pin() as usize;
+        pin.block().afr(n / 8).read().afr(n % 8)
+    }
+}
+
+fn blocking_delay_us(us: u32) {
+    #[cfg(feature = "time")]
+    embassy_time::block_for(embassy_time::Duration::from_micros(us as u64));
+    #[cfg(not(feature = "time"))]
+    {
+        let freq = unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 as u64;
+        let cycles = freq * us as u64 / 1_000_000;
+        cortex_m::asm::delay(cycles as u32);
+    }
 }
 
 impl<'d, M: Mode> Drop for I2c<'d, M> {
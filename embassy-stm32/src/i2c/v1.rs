@@ -501,18 +501,18 @@ impl<'d> I2c<'d, Async> {
 
     /// Write.
     pub async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Error> {
-        self.write_frame(address, write, FrameOptions::FirstAndLastFrame)
-            .await?;
-
-        Ok(())
+        let timeout = self.timeout();
+        timeout
+            .with(self.write_frame(address, write, FrameOptions::FirstAndLastFrame))
+            .await
     }
 
     /// Read.
     pub async fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
-        self.read_frame(address, buffer, FrameOptions::FirstAndLastFrame)
-            .await?;
-
-        Ok(())
+        let timeout = self.timeout();
+        timeout
+            .with(self.read_frame(address, buffer, FrameOptions::FirstAndLastFrame))
+            .await
     }
 
     async fn read_frame(&mut self, address: u8, buffer: &mut [u8], frame: FrameOptions) -> Result<(), Error> {
@@ -679,8 +679,13 @@ impl<'d> I2c<'d, Async> {
             return Err(Error::Overrun);
         }
 
-        self.write_frame(address, write, FrameOptions::FirstFrame).await?;
-        self.read_frame(address, read, FrameOptions::FirstAndLastFrame).await
+        let timeout = self.timeout();
+        timeout
+            .with(async {
+                self.write_frame(address, write, FrameOptions::FirstFrame).await?;
+                self.read_frame(address, read, FrameOptions::FirstAndLastFrame).await
+            })
+            .await
     }
 
     /// Transaction with operations.
@@ -689,14 +694,19 @@ impl<'d> I2c<'d, Async> {
     ///
     /// [transaction contract]: embedded_hal_1::i2c::I2c::transaction
     pub async fn transaction(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
-        for (op, frame) in operation_frames(operations)? {
-            match op {
-                Operation::Read(read) => self.read_frame(addr, read, frame).await?,
-                Operation::Write(write) => self.write_frame(addr, write, frame).await?,
-            }
-        }
+        let timeout = self.timeout();
+        timeout
+            .with(async {
+                for (op, frame) in operation_frames(operations)? {
+                    match op {
+                        Operation::Read(read) => self.read_frame(addr, read, frame).await?,
+                        Operation::Write(write) => self.write_frame(addr, write, frame).await?,
+                    }
+                }
 
-        Ok(())
+                Ok(())
+            })
+            .await
     }
 }
 
@@ -13,16 +13,83 @@ pub(crate) unsafe fn on_interrupt<T: Instance>() {
     let regs = T::info().regs;
     let isr = regs.isr().read();
 
-    if isr.tcr() || isr.tc() {
+    if isr.tcr() || isr.tc() || isr.addr() || isr.rxne() || isr.txis() || isr.nackf() || isr.stopf() {
+        T::state().waker.wake();
+    }
+    if isr.berr() || isr.arlo() || isr.ovr() {
         T::state().waker.wake();
     }
     // The flag can only be cleared by writting to nbytes, we won't do that here, so disable
-    // the interrupt
+    // the interrupt. The target mode flags are handled by the woken task.
     critical_section::with(|_| {
-        regs.cr1().modify(|w| w.set_tcie(false));
+        regs.cr1().modify(|w| {
+            w.set_tcie(false);
+            w.set_addrie(false);
+            w.set_rxie(false);
+            w.set_txie(false);
+            w.set_nackie(false);
+            w.set_stopie(false);
+            w.set_errie(false);
+        });
     });
 }
 
+/// I2C address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Address {
+    /// 7-bit address.
+    SevenBit(u8),
+    /// 10-bit address.
+    TenBit(u16),
+}
+
+/// I2C target (slave) configuration.
+#[non_exhaustive]
+#[derive(Copy, Clone)]
+pub struct TargetConfig {
+    /// Primary own address.
+    pub address: Address,
+    /// Secondary own 7-bit address.
+    pub secondary_address: Option<u8>,
+    /// Number of least significant bits of the secondary address ignored when matching, from 0 to 7.
+    pub secondary_address_mask: u8,
+    /// Respond to the general call address (0x00).
+    pub general_call: bool,
+}
+
+impl TargetConfig {
+    /// Create a configuration responding to `address` only.
+    pub const fn new(address: Address) -> Self {
+        Self {
+            address,
+            secondary_address: None,
+            secondary_address_mask: 0,
+            general_call: false,
+        }
+    }
+}
+
+/// Direction of a transfer addressed to the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TargetCommandKind {
+    /// The controller writes to the target, answer with [`I2c::respond_to_write`].
+    Write,
+    /// The controller reads from the target, answer with [`I2c::respond_to_read`].
+    Read,
+}
+
+/// Transfer addressed to the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TargetCommand {
+    /// Direction of the transfer.
+    pub kind: TargetCommandKind,
+    /// Own address that matched. A general call is reported as `Address::SevenBit(0)`.
+    pub address: Address,
+}
+
 impl<'d, M: Mode> I2c<'d, M> {
     pub(crate) fn init(&mut self, freq: Hertz, _config: Config) {
         self.info.regs.cr1().modify(|reg| {
@@ -45,6 +112,42 @@ impl<'d, M: Mode> I2c<'d, M> {
         });
     }
 
+    /// Configure the addresses the peripheral responds to as a target.
+    ///
+    /// The peripheral keeps working as a controller, transfers addressed to it are
+    /// reported by [`I2c::listen`].
+    pub fn set_target_config(&mut self, config: TargetConfig) {
+        let regs = self.info.regs;
+
+        // The own addresses can only be changed while they are disabled.
+        regs.oar1().write(|w| w.set_oa1en(false));
+        regs.oar2().write(|w| w.set_oa2en(false));
+
+        regs.oar1().write(|w| {
+            match config.address {
+                Address::SevenBit(addr) => {
+                    w.set_oa1((addr as u16) << 1);
+                    w.set_oa1mode(i2c::vals::Addmode::BIT7);
+                }
+                Address::TenBit(addr) => {
+                    w.set_oa1(addr);
+                    w.set_oa1mode(i2c::vals::Addmode::BIT10);
+                }
+            }
+            w.set_oa1en(true);
+        });
+
+        if let Some(addr) = config.secondary_address {
+            regs.oar2().write(|w| {
+                w.set_oa2(addr);
+                w.set_oa2msk(i2c::vals::Oamsk::from_bits(config.secondary_address_mask.min(7)));
+                w.set_oa2en(true);
+            });
+        }
+
+        regs.cr1().modify(|w| w.set_gcen(config.general_call));
+    }
+
     fn master_stop(&mut self) {
         self.info.regs.cr2().write(|w| w.set_stop(true));
     }
@@ -676,6 +779,232 @@ impl<'d> I2c<'d, Async> {
     }
 }
 
+impl<'d> I2c<'d, Async> {
+    // =========================
+    //  Async target API
+
+    /// Wait for a controller to address the target.
+    ///
+    /// The bus is held (clock stretching) until the transfer is answered with
+    /// [`I2c::respond_to_write`] or [`I2c::respond_to_read`], depending on the
+    /// returned [`TargetCommandKind`]. Requires [`I2c::set_target_config`].
+    pub async fn listen(&mut self) -> Result<TargetCommand, Error> {
+        let regs = self.info.regs;
+
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            let isr = regs.isr().read();
+            if !isr.addr() {
+                regs.cr1().modify(|w| w.set_addrie(true));
+                return Poll::Pending;
+            }
+
+            let kind = match isr.dir() {
+                i2c::vals::Dir::READ => TargetCommandKind::Read,
+                i2c::vals::Dir::WRITE => TargetCommandKind::Write,
+            };
+            let oar1 = regs.oar1().read();
+            let address = if oar1.oa1mode() == i2c::vals::Addmode::BIT10 && isr.addcode() >> 2 == 0b11110 {
+                // A 10-bit header only matches the primary address.
+                Address::TenBit(oar1.oa1())
+            } else {
+                Address::SevenBit(isr.addcode())
+            };
+
+            Poll::Ready(Ok(TargetCommand { kind, address }))
+        })
+        .await
+    }
+
+    /// Receive the data written by the controller after [`I2c::listen`] returned
+    /// [`TargetCommandKind::Write`].
+    ///
+    /// Returns the number of bytes received once the controller stops or restarts the transfer.
+    /// Bytes that don't fit in `buffer` are NACKed and reported as [`Error::Overrun`]. If the
+    /// timeout expires, the rest of the transfer is NACKed.
+    pub async fn respond_to_write(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let timeout = self.timeout();
+        timeout.with(self.respond_to_write_inner(buffer)).await
+    }
+
+    async fn respond_to_write_inner(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let regs = self.info.regs;
+        let mut len = 0;
+        let mut overrun = false;
+
+        let on_drop = OnDrop::new(move || {
+            // NACK the rest of the transfer so the controller gives up.
+            regs.cr2().modify(|w| w.set_nack(true));
+            target_abort(regs);
+        });
+
+        // Release the bus.
+        regs.icr().write(|w| w.set_addrcf(true));
+
+        let result = poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            loop {
+                let isr = regs.isr().read();
+                if let Err(e) = check_target_errors(regs, isr) {
+                    return Poll::Ready(Err(e));
+                }
+                if isr.rxne() {
+                    let byte = regs.rxdr().read().rxdata();
+                    match buffer.get_mut(len) {
+                        Some(slot) => {
+                            *slot = byte;
+                            len += 1;
+                        }
+                        None => {
+                            overrun = true;
+                            regs.cr2().modify(|w| w.set_nack(true));
+                        }
+                    }
+                } else if isr.stopf() {
+                    regs.icr().write(|w| w.set_stopcf(true));
+                    return Poll::Ready(Ok(()));
+                } else if isr.addr() {
+                    // Repeated start, leave it to the next `listen`.
+                    return Poll::Ready(Ok(()));
+                } else {
+                    regs.cr1().modify(|w| {
+                        w.set_rxie(true);
+                        w.set_stopie(true);
+                        w.set_addrie(true);
+                        w.set_errie(true);
+                    });
+                    return Poll::Pending;
+                }
+            }
+        })
+        .await;
+
+        on_drop.defuse();
+        result?;
+
+        if overrun {
+            Err(Error::Overrun)
+        } else {
+            Ok(len)
+        }
+    }
+
+    /// Send data to the controller after [`I2c::listen`] returned [`TargetCommandKind::Read`].
+    ///
+    /// Returns the number of bytes of `buffer` sent once the controller stops or restarts the
+    /// transfer. If the controller reads past the end of `buffer`, it receives `0xFF`.
+    pub async fn respond_to_read(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        let timeout = self.timeout();
+        timeout.with(self.respond_to_read_inner(buffer)).await
+    }
+
+    async fn respond_to_read_inner(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        let regs = self.info.regs;
+        let mut written = 0;
+        // Whether the byte waiting in TXDR comes from `buffer`.
+        let mut pending_data = false;
+
+        let on_drop = OnDrop::new(move || {
+            // Drop the byte waiting in TXDR.
+            regs.isr().modify(|w| w.set_txe(true));
+            target_abort(regs);
+        });
+
+        // Drop any stale byte, then release the bus.
+        regs.isr().modify(|w| w.set_txe(true));
+        regs.icr().write(|w| w.set_addrcf(true));
+
+        let result = poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            loop {
+                let isr = regs.isr().read();
+                if let Err(e) = check_target_errors(regs, isr) {
+                    return Poll::Ready(Err(e));
+                }
+                if isr.txis() {
+                    let byte = match buffer.get(written) {
+                        Some(&byte) => {
+                            written += 1;
+                            pending_data = true;
+                            byte
+                        }
+                        None => {
+                            pending_data = false;
+                            0xFF
+                        }
+                    };
+                    regs.txdr().write(|w| w.set_txdata(byte));
+                } else if isr.nackf() {
+                    // The controller doesn't want more data.
+                    regs.icr().write(|w| w.set_nackcf(true));
+                } else if isr.stopf() || isr.addr() {
+                    // A byte left in TXDR was never sent.
+                    if !isr.txe() && pending_data {
+                        written -= 1;
+                    }
+                    regs.isr().modify(|w| w.set_txe(true));
+                    if isr.stopf() {
+                        regs.icr().write(|w| w.set_stopcf(true));
+                    }
+                    return Poll::Ready(Ok(()));
+                } else {
+                    regs.cr1().modify(|w| {
+                        w.set_txie(true);
+                        w.set_nackie(true);
+                        w.set_stopie(true);
+                        w.set_addrie(true);
+                        w.set_errie(true);
+                    });
+                    return Poll::Pending;
+                }
+            }
+        })
+        .await;
+
+        on_drop.defuse();
+        result?;
+
+        Ok(written)
+    }
+}
+
+/// Checks the error flags during a target transfer, clearing the flag of the returned error.
+fn check_target_errors(regs: i2c::I2c, isr: i2c::regs::Isr) -> Result<(), Error> {
+    if isr.berr() {
+        regs.icr().write(|w| w.set_berrcf(true));
+        Err(Error::Bus)
+    } else if isr.arlo() {
+        regs.icr().write(|w| w.set_arlocf(true));
+        Err(Error::Arbitration)
+    } else if isr.ovr() {
+        regs.icr().write(|w| w.set_ovrcf(true));
+        Err(Error::Overrun)
+    } else {
+        Ok(())
+    }
+}
+
+/// Stops a target transfer which was cancelled, leaving the peripheral ready for the next `listen`.
+fn target_abort(regs: i2c::I2c) {
+    regs.cr1().modify(|w| {
+        w.set_rxie(false);
+        w.set_txie(false);
+        w.set_nackie(false);
+        w.set_stopie(false);
+        w.set_errie(false);
+    });
+    regs.icr().write(|w| {
+        w.set_stopcf(true);
+        w.set_nackcf(true);
+        w.set_berrcf(true);
+        w.set_arlocf(true);
+        w.set_ovrcf(true);
+    });
+}
+
 /// I2C Stop Configuration
 ///
 /// Peripheral options for generating the STOP condition
@@ -384,3 +384,45 @@ impl Into<u8> for DummyCycles {
         }
     }
 }
+
+impl TryFrom<u8> for DummyCycles {
+    type Error = ();
+
+    fn try_from(cycles: u8) -> Result<Self, ()> {
+        match cycles {
+            0 => Ok(DummyCycles::_0),
+            1 => Ok(DummyCycles::_1),
+            2 => Ok(DummyCycles::_2),
+            3 => Ok(DummyCycles::_3),
+            4 => Ok(DummyCycles::_4),
+            5 => Ok(DummyCycles::_5),
+            6 => Ok(DummyCycles::_6),
+            7 => Ok(DummyCycles::_7),
+            8 => Ok(DummyCycles::_8),
+            9 => Ok(DummyCycles::_9),
+            10 => Ok(DummyCycles::_10),
+            11 => Ok(DummyCycles::_11),
+            12 => Ok(DummyCycles::_12),
+            13 => Ok(DummyCycles::_13),
+            14 => Ok(DummyCycles::_14),
+            15 => Ok(DummyCycles::_15),
+            16 => Ok(DummyCycles::_16),
+            17 => Ok(DummyCycles::_17),
+            18 => Ok(DummyCycles::_18),
+            19 => Ok(DummyCycles::_19),
+            20 => Ok(DummyCycles::_20),
+            21 => Ok(DummyCycles::_21),
+            22 => Ok(DummyCycles::_22),
+            23 => Ok(DummyCycles::_23),
+            24 => Ok(DummyCycles::_24),
+            25 => Ok(DummyCycles::_25),
+            26 => Ok(DummyCycles::_26),
+            27 => Ok(DummyCycles::_27),
+            28 => Ok(DummyCycles::_28),
+            29 => Ok(DummyCycles::_29),
+            30 => Ok(DummyCycles::_30),
+            31 => Ok(DummyCycles::_31),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,785 @@
+//! Serial NOR flash over OCTOSPI, configured from the device SFDP tables.
+
+use embassy_futures::yield_now;
+use embedded_storage::nor_flash::{ErrorType, NorFlashError, NorFlashErrorKind};
+
+use super::{AddressSize, DummyCycles, Instance, Ospi, OspiError, OspiWidth, TransferConfig};
+use crate::mode::Async;
+
+/// Serial NOR flash instructions
+mod cmd {
+    pub const WRITE_ENABLE: u8 = 0x06;
+    pub const READ_STATUS: u8 = 0x05;
+    pub const READ_STATUS2: u8 = 0x35;
+    pub const READ_STATUS2_BIT7: u8 = 0x3F;
+    pub const WRITE_STATUS: u8 = 0x01;
+    pub const WRITE_STATUS2: u8 = 0x31;
+    pub const WRITE_STATUS2_BIT7: u8 = 0x3E;
+    pub const READ_SFDP: u8 = 0x5A;
+    pub const READ_JEDEC_ID: u8 = 0x9F;
+    pub const FAST_READ: u8 = 0x0B;
+    pub const PAGE_PROGRAM: u8 = 0x02;
+    pub const ENTER_4BYTE_ADDRESS: u8 = 0xB7;
+    pub const RESET_ENABLE: u8 = 0x66;
+    pub const RESET: u8 = 0x99;
+}
+
+/// Write in progress bit of the status register
+const STATUS_WIP: u8 = 1 << 0;
+
+/// "SFDP", little endian
+const SFDP_SIGNATURE: u32 = 0x5044_4653;
+/// Number of Basic Flash Parameter Table DWORDs used, up to JESD216C
+const BFPT_DWORDS: usize = 19;
+
+/// Smallest erase unit, required from every device
+const SECTOR_SIZE: u32 = 4096;
+/// Largest transfer handed to the DMA at once
+const MAX_TRANSFER: usize = 0x8000;
+
+/// External flash error
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlashError {
+    /// Error of the OSPI peripheral
+    Ospi(OspiError),
+    /// The device has no valid SFDP tables
+    NoSfdp,
+    /// The device lacks a feature the driver needs, such as 4 KiB sector erase
+    Unsupported,
+    /// Access outside of the device
+    OutOfBounds,
+    /// Erase range not aligned to sectors
+    NotAligned,
+}
+
+impl From<OspiError> for FlashError {
+    fn from(e: OspiError) -> Self {
+        Self::Ospi(e)
+    }
+}
+
+impl NorFlashError for FlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Self::NotAligned => NorFlashErrorKind::NotAligned,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// Device properties, discovered from its SFDP tables
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FlashInfo {
+    /// JEDEC manufacturer and device id
+    pub jedec_id: [u8; 3],
+    /// Size in bytes
+    pub size: u32,
+    /// Page size in bytes, the most programmed at once
+    pub page_size: u32,
+    /// Number of address bytes, 3 or 4
+    pub address_bytes: u8,
+    /// Number of data lanes used by reads
+    pub read_lanes: u8,
+}
+
+/// Erase instruction and the size it erases
+#[derive(Clone, Copy)]
+struct EraseType {
+    size: u32,
+    instruction: u8,
+}
+
+/// Fast read instruction
+#[derive(Clone, Copy)]
+struct ReadOp {
+    instruction: u8,
+    address_width: OspiWidth,
+    data_width: OspiWidth,
+    /// Mode bits are sent as one alternate byte
+    mode_byte: bool,
+    dummy: u8,
+}
+
+impl ReadOp {
+    /// Decodes a fast read descriptor of the BFPT: wait states in bits 4:0, mode clocks in
+    /// bits 7:5 and the instruction in bits 15:8. Returns `None` if the instruction is missing.
+    fn from_bfpt(params: u16, address_width: OspiWidth, data_width: OspiWidth) -> Option<Self> {
+        let instruction = (params >> 8) as u8;
+        if instruction == 0 {
+            return None;
+        }
+
+        let wait_states = (params & 0x1F) as u8;
+        let mode_clocks = ((params >> 5) & 0x7) as u8;
+
+        // Send the mode bits as an all ones alternate byte, so the device doesn't enter a
+        // continuous read mode. Otherwise clock them as dummy cycles.
+        let mode_byte = mode_clocks * lanes(address_width) == 8;
+        let dummy = match mode_byte {
+            true => wait_states,
+            false => wait_states + mode_clocks,
+        };
+
+        Some(Self {
+            instruction,
+            address_width,
+            data_width,
+            mode_byte,
+            dummy,
+        })
+    }
+}
+
+fn lanes(width: OspiWidth) -> u8 {
+    match width {
+        OspiWidth::NONE => 0,
+        OspiWidth::SING => 1,
+        OspiWidth::DUAL => 2,
+        OspiWidth::QUAD => 4,
+        OspiWidth::OCTO => 8,
+    }
+}
+
+/// Serial NOR flash on an OCTOSPI bus.
+///
+/// The device is discovered from its SFDP tables (JESD216): size, page size, erase
+/// instructions, address length and the fastest read the bus supports. Reads use all the lanes
+/// the [`Ospi`] driver was created with, enabling quad mode in the device when needed.
+/// Devices needing an octal enable bit are read in quad mode. Programming and erasing use a
+/// single lane.
+pub struct OspiFlash<'d, T: Instance> {
+    ospi: Ospi<'d, T, Async>,
+    info: FlashInfo,
+    read_op: ReadOp,
+    /// Erase types, smallest first
+    erase_types: [Option<EraseType>; 5],
+    /// Use the dedicated 4-byte address instructions
+    four_byte_instructions: bool,
+}
+
+impl<'d, T: Instance> OspiFlash<'d, T> {
+    /// Resets the device and configures the driver from its SFDP tables.
+    ///
+    /// The device must answer single lane SPI commands, which is the case after a reset.
+    pub async fn new(ospi: Ospi<'d, T, Async>) -> Result<Self, FlashError> {
+        let mut this = Self {
+            ospi,
+            info: FlashInfo {
+                jedec_id: [0; 3],
+                size: 0,
+                page_size: 256,
+                address_bytes: 3,
+                read_lanes: 1,
+            },
+            read_op: ReadOp {
+                instruction: cmd::FAST_READ,
+                address_width: OspiWidth::SING,
+                data_width: OspiWidth::SING,
+                mode_byte: false,
+                dummy: 8,
+            },
+            erase_types: [None; 5],
+            four_byte_instructions: false,
+        };
+
+        // Leave any continuous read or 4-byte address mode of a previous run
+        this.command(cmd::RESET_ENABLE).await?;
+        this.command(cmd::RESET).await?;
+        this.wait_idle().await?;
+
+        let mut jedec_id = [0; 3];
+        this.read_register(cmd::READ_JEDEC_ID, &mut jedec_id)?;
+        this.info.jedec_id = jedec_id;
+
+        let bfpt = this.read_bfpt()?;
+        this.configure(&bfpt).await?;
+
+        Ok(this)
+    }
+
+    /// Returns the device properties.
+    pub fn info(&self) -> &FlashInfo {
+        &self.info
+    }
+
+    /// Returns the OSPI driver.
+    pub fn release(self) -> Ospi<'d, T, Async> {
+        self.ospi
+    }
+
+    /// Reads `buf.len()` bytes at `offset`.
+    pub async fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+        self.check_bounds(offset, buf.len())?;
+
+        let mut address = offset;
+        for chunk in buf.chunks_mut(MAX_TRANSFER) {
+            let transaction = self.read_config(address);
+            self.ospi.read(chunk, transaction).await?;
+            address += chunk.len() as u32;
+        }
+        Ok(())
+    }
+
+    /// Programs `data` at `offset`, which must have been erased.
+    pub async fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), FlashError> {
+        self.check_bounds(offset, data.len())?;
+
+        let mut address = offset;
+        let mut data = data;
+        while !data.is_empty() {
+            // A program operation wraps around at the end of the page
+            let page_left = (self.info.page_size - address % self.info.page_size) as usize;
+            let (chunk, rest) = data.split_at(page_left.min(data.len()));
+
+            self.command(cmd::WRITE_ENABLE).await?;
+            let transaction = TransferConfig {
+                dwidth: OspiWidth::SING,
+                ..self.address_config(self.instruction(cmd::PAGE_PROGRAM)?, address)
+            };
+            self.ospi.write(chunk, transaction).await?;
+            self.wait_idle().await?;
+
+            address += chunk.len() as u32;
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Erases the sectors from `from` to `to`, which must be aligned to 4 KiB.
+    ///
+    /// The largest erase instructions fitting the range are used.
+    pub async fn erase(&mut self, from: u32, to: u32) -> Result<(), FlashError> {
+        if from > to || to > self.info.size {
+            return Err(FlashError::OutOfBounds);
+        }
+        if from % SECTOR_SIZE != 0 || to % SECTOR_SIZE != 0 {
+            return Err(FlashError::NotAligned);
+        }
+
+        let mut address = from;
+        while address < to {
+            // The sector erase always fits
+            let erase = *unwrap!(self
+                .erase_types
+                .iter()
+                .flatten()
+                .rev()
+                .find(|e| address % e.size == 0 && to - address >= e.size));
+
+            self.command(cmd::WRITE_ENABLE).await?;
+            let transaction = self.address_config(erase.instruction, address);
+            self.ospi.command(&transaction).await?;
+            self.wait_idle().await?;
+
+            address += erase.size;
+        }
+        Ok(())
+    }
+
+    /// Reads the SFDP header and the Basic Flash Parameter Table.
+    fn read_bfpt(&mut self) -> Result<[u32; BFPT_DWORDS], FlashError> {
+        let mut header = [0u8; 16];
+        self.read_sfdp(0, &mut header)?;
+        if u32::from_le_bytes(header[0..4].try_into().unwrap()) != SFDP_SIGNATURE {
+            return Err(FlashError::NoSfdp);
+        }
+
+        // The first parameter header always describes the BFPT
+        let param = &header[8..16];
+        let len = (param[3] as usize).min(BFPT_DWORDS);
+        let pointer = u32::from_le_bytes([param[4], param[5], param[6], 0]);
+        if len < 9 {
+            return Err(FlashError::NoSfdp);
+        }
+
+        let mut raw = [0u8; BFPT_DWORDS * 4];
+        self.read_sfdp(pointer, &mut raw[..len * 4])?;
+
+        let mut bfpt = [0u32; BFPT_DWORDS];
+        for (dword, bytes) in bfpt.iter_mut().zip(raw.chunks_exact(4)) {
+            *dword = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(bfpt)
+    }
+
+    /// Configures the driver and the device from the BFPT, with all DWORDs past the table
+    /// length left to zero.
+    async fn configure(&mut self, bfpt: &[u32; BFPT_DWORDS]) -> Result<(), FlashError> {
+        // DWORDs are numbered from 1 in JESD216
+        let dword = |n: usize| bfpt[n - 1];
+
+        if dword(1) & 0b11 != 0b01 {
+            return Err(FlashError::Unsupported);
+        }
+
+        self.info.size = decode_density(dword(2)).ok_or(FlashError::Unsupported)?;
+
+        let page_exp = (dword(11) >> 4) & 0xF;
+        if page_exp != 0 {
+            self.info.page_size = 1 << page_exp;
+        }
+
+        self.erase_types = erase_types(bfpt);
+        self.configure_address_bytes(bfpt).await?;
+        self.configure_read(bfpt).await
+    }
+
+    async fn configure_address_bytes(&mut self, bfpt: &[u32; BFPT_DWORDS]) -> Result<(), FlashError> {
+        match address_mode(bfpt, self.info.size)? {
+            AddressMode::ThreeBytes => {}
+            AddressMode::FourBytes => self.info.address_bytes = 4,
+            AddressMode::Enter { write_enable } => {
+                if write_enable {
+                    self.command(cmd::WRITE_ENABLE).await?;
+                }
+                self.command(cmd::ENTER_4BYTE_ADDRESS).await?;
+                self.info.address_bytes = 4;
+            }
+            AddressMode::FourByteInstructions => {
+                for erase in self.erase_types.iter_mut().flatten() {
+                    erase.instruction = four_byte_instruction(erase.instruction).ok_or(FlashError::Unsupported)?;
+                }
+                self.four_byte_instructions = true;
+                self.info.address_bytes = 4;
+            }
+        }
+        Ok(())
+    }
+
+    async fn configure_read(&mut self, bfpt: &[u32; BFPT_DWORDS]) -> Result<(), FlashError> {
+        let dword1 = bfpt[0];
+        let bus_lanes = lanes(self.ospi.width);
+
+        // Octal reads (JESD216C, DWORD 17), only for devices without an octal enable bit
+        let octal_enable = (bfpt[18] >> 20) & 0x7;
+        if bus_lanes >= 8 && octal_enable == 0 {
+            let dword17 = bfpt[16];
+            let op = ReadOp::from_bfpt((dword17 >> 16) as u16, OspiWidth::OCTO, OspiWidth::OCTO)
+                .or_else(|| ReadOp::from_bfpt(dword17 as u16, OspiWidth::SING, OspiWidth::OCTO));
+            if let Some(op) = op {
+                return self.set_read_op(op, 8);
+            }
+        }
+
+        if bus_lanes >= 4 {
+            let dword3 = bfpt[2];
+            let op = if dword1 & (1 << 21) != 0 {
+                ReadOp::from_bfpt(dword3 as u16, OspiWidth::QUAD, OspiWidth::QUAD)
+            } else if dword1 & (1 << 22) != 0 {
+                ReadOp::from_bfpt((dword3 >> 16) as u16, OspiWidth::SING, OspiWidth::QUAD)
+            } else {
+                None
+            };
+            if let Some(op) = op {
+                if self.quad_enable((bfpt[14] >> 20) & 0x7).await? {
+                    return self.set_read_op(op, 4);
+                }
+            }
+        }
+
+        if bus_lanes >= 2 {
+            let dword4 = bfpt[3];
+            let op = if dword1 & (1 << 20) != 0 {
+                ReadOp::from_bfpt((dword4 >> 16) as u16, OspiWidth::DUAL, OspiWidth::DUAL)
+            } else if dword1 & (1 << 16) != 0 {
+                ReadOp::from_bfpt(dword4 as u16, OspiWidth::SING, OspiWidth::DUAL)
+            } else {
+                None
+            };
+            if let Some(op) = op {
+                return self.set_read_op(op, 2);
+            }
+        }
+
+        // Keep the single lane fast read
+        let op = self.read_op;
+        self.set_read_op(op, 1)
+    }
+
+    fn set_read_op(&mut self, mut op: ReadOp, lanes: u8) -> Result<(), FlashError> {
+        DummyCycles::try_from(op.dummy).map_err(|_| FlashError::Unsupported)?;
+        op.instruction = self.instruction(op.instruction)?;
+        self.read_op = op;
+        self.info.read_lanes = lanes;
+        Ok(())
+    }
+
+    /// Sets the quad enable bit, as described by the Quad Enable Requirements of DWORD 15.
+    /// Returns `false` if the requirement is unknown.
+    async fn quad_enable(&mut self, requirement: u32) -> Result<bool, FlashError> {
+        match requirement {
+            // No quad enable bit
+            0b000 => return Ok(true),
+            // Bit 1 of status register 2, written along with status register 1. Reading status
+            // register 2 with 35h is only defined for 101b, otherwise the rest of it is cleared.
+            0b001 | 0b100 | 0b101 => {
+                let mut sr1 = [0];
+                self.read_register(cmd::READ_STATUS, &mut sr1)?;
+                let mut sr2 = [0];
+                if requirement == 0b101 {
+                    self.read_register(cmd::READ_STATUS2, &mut sr2)?;
+                }
+                self.command(cmd::WRITE_ENABLE).await?;
+                self.write_register(cmd::WRITE_STATUS, &[sr1[0], sr2[0] | (1 << 1)])?;
+            }
+            // Bit 6 of status register 1
+            0b010 => {
+                let mut sr1 = [0];
+                self.read_register(cmd::READ_STATUS, &mut sr1)?;
+                self.command(cmd::WRITE_ENABLE).await?;
+                self.write_register(cmd::WRITE_STATUS, &[sr1[0] | (1 << 6)])?;
+            }
+            // Bit 7 of status register 2, with dedicated instructions
+            0b011 => {
+                let mut sr2 = [0];
+                self.read_register(cmd::READ_STATUS2_BIT7, &mut sr2)?;
+                self.command(cmd::WRITE_ENABLE).await?;
+                self.write_register(cmd::WRITE_STATUS2_BIT7, &[sr2[0] | (1 << 7)])?;
+            }
+            // Bit 1 of status register 2, written on its own
+            0b110 => {
+                let mut sr2 = [0];
+                self.read_register(cmd::READ_STATUS2, &mut sr2)?;
+                self.command(cmd::WRITE_ENABLE).await?;
+                self.write_register(cmd::WRITE_STATUS2, &[sr2[0] | (1 << 1)])?;
+            }
+            _ => return Ok(false),
+        }
+        self.wait_idle().await?;
+        Ok(true)
+    }
+
+    /// Returns the 4-byte address variant of `instruction` when the device uses them.
+    fn instruction(&self, instruction: u8) -> Result<u8, FlashError> {
+        match self.four_byte_instructions {
+            true => four_byte_instruction(instruction).ok_or(FlashError::Unsupported),
+            false => Ok(instruction),
+        }
+    }
+
+    fn read_config(&self, address: u32) -> TransferConfig {
+        let op = self.read_op;
+        let mut transaction = TransferConfig {
+            adwidth: op.address_width,
+            dwidth: op.data_width,
+            dummy: unwrap!(DummyCycles::try_from(op.dummy)),
+            ..self.address_config(op.instruction, address)
+        };
+        if op.mode_byte {
+            transaction.abwidth = op.address_width;
+            transaction.alternate_bytes = Some(0xFF);
+            transaction.absize = AddressSize::_8Bit;
+        }
+        transaction
+    }
+
+    /// Single lane instruction and address
+    fn address_config(&self, instruction: u8, address: u32) -> TransferConfig {
+        TransferConfig {
+            iwidth: OspiWidth::SING,
+            instruction: Some(instruction as u32),
+            adwidth: OspiWidth::SING,
+            address: Some(address),
+            adsize: match self.info.address_bytes {
+                4 => AddressSize::_32bit,
+                _ => AddressSize::_24bit,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn check_bounds(&self, offset: u32, len: usize) -> Result<(), FlashError> {
+        match (offset as u64) + (len as u64) <= u64::from(self.info.size) {
+            true => Ok(()),
+            false => Err(FlashError::OutOfBounds),
+        }
+    }
+
+    async fn command(&mut self, instruction: u8) -> Result<(), FlashError> {
+        let transaction = TransferConfig {
+            iwidth: OspiWidth::SING,
+            instruction: Some(instruction as u32),
+            ..Default::default()
+        };
+        Ok(self.ospi.command(&transaction).await?)
+    }
+
+    fn read_register(&mut self, instruction: u8, buf: &mut [u8]) -> Result<(), FlashError> {
+        let transaction = TransferConfig {
+            iwidth: OspiWidth::SING,
+            instruction: Some(instruction as u32),
+            dwidth: OspiWidth::SING,
+            ..Default::default()
+        };
+        Ok(self.ospi.blocking_read(buf, transaction)?)
+    }
+
+    fn write_register(&mut self, instruction: u8, data: &[u8]) -> Result<(), FlashError> {
+        let transaction = TransferConfig {
+            iwidth: OspiWidth::SING,
+            instruction: Some(instruction as u32),
+            dwidth: OspiWidth::SING,
+            ..Default::default()
+        };
+        Ok(self.ospi.blocking_write(data, transaction)?)
+    }
+
+    /// SFDP reads always use 3 address bytes and 8 dummy cycles
+    fn read_sfdp(&mut self, address: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+        let transaction = TransferConfig {
+            iwidth: OspiWidth::SING,
+            instruction: Some(cmd::READ_SFDP as u32),
+            adwidth: OspiWidth::SING,
+            address: Some(address),
+            adsize: AddressSize::_24bit,
+            dwidth: OspiWidth::SING,
+            dummy: DummyCycles::_8,
+            ..Default::default()
+        };
+        Ok(self.ospi.blocking_read(buf, transaction)?)
+    }
+
+    /// Waits for the end of a program or erase operation.
+    async fn wait_idle(&mut self) -> Result<(), FlashError> {
+        loop {
+            let mut status = [0];
+            self.read_register(cmd::READ_STATUS, &mut status)?;
+            if status[0] & STATUS_WIP == 0 {
+                return Ok(());
+            }
+            yield_now().await;
+        }
+    }
+}
+
+impl<'d, T: Instance> ErrorType for OspiFlash<'d, T> {
+    type Error = FlashError;
+}
+
+impl<'d, T: Instance> embedded_storage_async::nor_flash::ReadNorFlash for OspiFlash<'d, T> {
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.read(offset, bytes).await
+    }
+
+    fn capacity(&self) -> usize {
+        self.info.size as usize
+    }
+}
+
+impl<'d, T: Instance> embedded_storage_async::nor_flash::NorFlash for OspiFlash<'d, T> {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.erase(from, to).await
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write(offset, bytes).await
+    }
+}
+
+/// Decodes the density of DWORD 2 into a size in bytes.
+fn decode_density(density: u32) -> Option<u32> {
+    let size_bits: u64 = match density & (1 << 31) {
+        0 => u64::from(density) + 1,
+        _ => 1u64.checked_shl(density & 0x7FFF_FFFF).unwrap_or(0),
+    };
+    if size_bits < 8 || size_bits / 8 > u64::from(u32::MAX) {
+        return None;
+    }
+    Some((size_bits / 8) as u32)
+}
+
+/// Returns the erase types of the BFPT, smallest first.
+fn erase_types(bfpt: &[u32; BFPT_DWORDS]) -> [Option<EraseType>; 5] {
+    let mut types = [None; 5];
+
+    // Erase types 1 to 4 in DWORDs 8 and 9: size as a power of two, then instruction
+    let halves = [
+        bfpt[7] as u16,
+        (bfpt[7] >> 16) as u16,
+        bfpt[8] as u16,
+        (bfpt[8] >> 16) as u16,
+    ];
+    for (slot, half) in types.iter_mut().zip(halves) {
+        let exp = half & 0xFF;
+        if exp != 0 && exp < 32 {
+            *slot = Some(EraseType {
+                size: 1 << exp,
+                instruction: (half >> 8) as u8,
+            });
+        }
+    }
+
+    // The 4 KiB erase instruction of DWORD 1, in case the erase types omit it
+    if !types.iter().flatten().any(|e| e.size == SECTOR_SIZE) {
+        types[4] = Some(EraseType {
+            size: SECTOR_SIZE,
+            instruction: (bfpt[0] >> 8) as u8,
+        });
+    }
+
+    types.sort_unstable_by_key(|e| e.map(|e| e.size).unwrap_or(u32::MAX));
+    types
+}
+
+/// How the device is switched to 4-byte addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AddressMode {
+    /// 3-byte addresses cover the whole device
+    ThreeBytes,
+    /// The device only supports 4-byte addresses
+    FourBytes,
+    /// Enter 4-byte address mode with B7h, preceded by a write enable if requested
+    Enter { write_enable: bool },
+    /// Use the dedicated 4-byte address instructions
+    FourByteInstructions,
+}
+
+/// Decodes the address bytes of DWORD 1 and the 4-byte address entry methods of DWORD 16.
+fn address_mode(bfpt: &[u32; BFPT_DWORDS], size: u32) -> Result<AddressMode, FlashError> {
+    match (bfpt[0] >> 17) & 0b11 {
+        0b00 => Ok(AddressMode::ThreeBytes),
+        0b01 if size <= 1 << 24 => Ok(AddressMode::ThreeBytes),
+        0b01 => {
+            // Enter 4-byte address mode, DWORD 16 bits 31:24
+            let enter = (bfpt[15] >> 24) as u8;
+            if enter & 0b10 != 0 {
+                Ok(AddressMode::Enter { write_enable: true })
+            } else if enter & 0b01 != 0 || enter == 0 {
+                // Devices with a JESD216 table predating DWORD 16 use B7h as well
+                Ok(AddressMode::Enter { write_enable: false })
+            } else if enter & (1 << 6) != 0 {
+                Ok(AddressMode::FourByteInstructions)
+            } else {
+                Err(FlashError::Unsupported)
+            }
+        }
+        0b10 => Ok(AddressMode::FourBytes),
+        _ => Err(FlashError::Unsupported),
+    }
+}
+
+/// Returns the 4-byte address variant of a 3-byte address instruction.
+fn four_byte_instruction(instruction: u8) -> Option<u8> {
+    Some(match instruction {
+        0x03 => 0x13,
+        0x0B => 0x0C,
+        0x3B => 0x3C,
+        0xBB => 0xBC,
+        0x6B => 0x6C,
+        0xEB => 0xEC,
+        0x02 => 0x12,
+        0x20 => 0x21,
+        0x52 => 0x5C,
+        0xD8 => 0xDC,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{address_mode, decode_density, erase_types, four_byte_instruction, AddressMode, ReadOp, BFPT_DWORDS};
+    use crate::ospi::OspiWidth;
+
+    #[test]
+    fn test_read_op_from_bfpt() {
+        // 1-4-4 EBh: 4 wait states and 2 mode clocks, one mode byte on four lanes
+        let op = ReadOp::from_bfpt(0xEB44, OspiWidth::QUAD, OspiWidth::QUAD).unwrap();
+        assert_eq!(op.instruction, 0xEB);
+        assert!(op.mode_byte);
+        assert_eq!(op.dummy, 4);
+        assert!(matches!(op.address_width, OspiWidth::QUAD));
+        assert!(matches!(op.data_width, OspiWidth::QUAD));
+
+        // 1-1-4 6Bh: 8 wait states, no mode clocks
+        let op = ReadOp::from_bfpt(0x6B08, OspiWidth::SING, OspiWidth::QUAD).unwrap();
+        assert_eq!(op.instruction, 0x6B);
+        assert!(!op.mode_byte);
+        assert_eq!(op.dummy, 8);
+
+        // 1-2-2 BBh: 2 mode clocks on two lanes are clocked as dummy cycles
+        let op = ReadOp::from_bfpt(0xBB42, OspiWidth::DUAL, OspiWidth::DUAL).unwrap();
+        assert!(!op.mode_byte);
+        assert_eq!(op.dummy, 4);
+
+        assert!(ReadOp::from_bfpt(0x0008, OspiWidth::SING, OspiWidth::QUAD).is_none());
+    }
+
+    #[test]
+    fn test_decode_density() {
+        // 128 Mbit as a bit count minus one
+        assert_eq!(decode_density(0x07FF_FFFF), Some(16 * 1024 * 1024));
+        // 2 Gbit as a power of two
+        assert_eq!(decode_density(0x8000_001F), Some(256 * 1024 * 1024));
+        assert_eq!(decode_density(0x8000_0022), Some(2048 * 1024 * 1024));
+        // Larger than the 32-bit address space
+        assert_eq!(decode_density(0x8000_0023), None);
+        assert_eq!(decode_density(0x8000_0040), None);
+        assert_eq!(decode_density(0x8000_0000), None);
+    }
+
+    #[test]
+    fn test_erase_types() {
+        let mut bfpt = [0u32; BFPT_DWORDS];
+        bfpt[0] = 0x20 << 8;
+        // 64 KiB D8h and 4 KiB 20h, then 32 KiB 52h
+        bfpt[7] = 0x200C_D810;
+        bfpt[8] = 0x0000_520F;
+
+        let types = erase_types(&bfpt);
+        let sizes = types.map(|e| e.map(|e| (e.size, e.instruction)));
+        assert_eq!(
+            sizes,
+            [Some((4096, 0x20)), Some((32768, 0x52)), Some((65536, 0xD8)), None, None]
+        );
+
+        // The 4 KiB erase of DWORD 1 is added when the erase types omit it
+        bfpt[7] = 0x0000_D810;
+        bfpt[8] = 0;
+        let types = erase_types(&bfpt);
+        let sizes = types.map(|e| e.map(|e| (e.size, e.instruction)));
+        assert_eq!(sizes, [Some((4096, 0x20)), Some((65536, 0xD8)), None, None, None]);
+    }
+
+    #[test]
+    fn test_address_mode() {
+        let mut bfpt = [0u32; BFPT_DWORDS];
+        assert_eq!(address_mode(&bfpt, 1 << 24).unwrap(), AddressMode::ThreeBytes);
+
+        bfpt[0] = 0b01 << 17;
+        assert_eq!(address_mode(&bfpt, 1 << 24).unwrap(), AddressMode::ThreeBytes);
+        assert_eq!(
+            address_mode(&bfpt, 1 << 25).unwrap(),
+            AddressMode::Enter { write_enable: false }
+        );
+        bfpt[15] = 0b10 << 24;
+        assert_eq!(
+            address_mode(&bfpt, 1 << 25).unwrap(),
+            AddressMode::Enter { write_enable: true }
+        );
+        bfpt[15] = 1 << 30;
+        assert_eq!(address_mode(&bfpt, 1 << 25).unwrap(), AddressMode::FourByteInstructions);
+        bfpt[15] = 1 << 29;
+        assert!(address_mode(&bfpt, 1 << 25).is_err());
+
+        bfpt[0] = 0b10 << 17;
+        assert_eq!(address_mode(&bfpt, 1 << 25).unwrap(), AddressMode::FourBytes);
+    }
+
+    #[test]
+    fn test_four_byte_instruction() {
+        assert_eq!(four_byte_instruction(0x0B), Some(0x0C));
+        assert_eq!(four_byte_instruction(0xEB), Some(0xEC));
+        assert_eq!(four_byte_instruction(0x02), Some(0x12));
+        assert_eq!(four_byte_instruction(0x20), Some(0x21));
+        assert_eq!(four_byte_instruction(0xD8), Some(0xDC));
+        assert_eq!(four_byte_instruction(0x05), None);
+    }
+}
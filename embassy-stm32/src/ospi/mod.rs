@@ -4,12 +4,14 @@
 #![macro_use]
 
 pub mod enums;
+mod flash;
 
 use core::marker::PhantomData;
 
 use embassy_embedded_hal::{GetConfig, SetConfig};
 use embassy_hal_internal::{into_ref, PeripheralRef};
 pub use enums::*;
+pub use flash::{FlashError, FlashInfo, OspiFlash};
 use stm32_metapac::octospi::vals::{PhaseMode, SizeInBits};
 
 use crate::dma::{word, ChannelAndRequest};
@@ -118,6 +120,10 @@ pub struct TransferConfig {
 
     /// Number of dummy cycles (DCYC)
     pub dummy: DummyCycles,
+
+    /// Data strobe enable (DQSE), for memories driving DQS such as octal DTR flash or HyperBus
+    /// devices. Ignored for the write config of memory mapped mode, which always enables it.
+    pub dqse: bool,
 }
 
 impl Default for TransferConfig {
@@ -142,6 +148,8 @@ impl Default for TransferConfig {
             ddtr: false,
 
             dummy: DummyCycles::_0,
+
+            dqse: false,
         }
     }
 }
@@ -181,6 +189,9 @@ pub struct Ospi<'d, T: Instance, M: PeriMode> {
 impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
     /// Enter memory mode.
     /// The Input `read_config` is used to configure the read operation in memory mode
+    ///
+    /// The address of both configs is ignored, it is taken from the accessed location. DQS is
+    /// always enabled for writes, so memories like HyperRAM can use it as a write mask.
     pub fn enable_memory_mapped_mode(
         &mut self,
         read_config: TransferConfig,
@@ -193,7 +204,7 @@ impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
         while reg.sr().read().busy() {}
 
         reg.ccr().modify(|r| {
-            r.set_dqse(false);
+            r.set_dqse(read_config.dqse);
             r.set_sioo(true);
         });
 
@@ -204,16 +215,23 @@ impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
             w.set_isize(SizeInBits::from_bits(write_config.isize.into()));
 
             w.set_admode(PhaseMode::from_bits(write_config.adwidth.into()));
-            w.set_addtr(write_config.idtr);
+            w.set_addtr(write_config.addtr);
             w.set_adsize(SizeInBits::from_bits(write_config.adsize.into()));
 
             w.set_dmode(PhaseMode::from_bits(write_config.dwidth.into()));
             w.set_ddtr(write_config.ddtr);
 
             w.set_abmode(PhaseMode::from_bits(write_config.abwidth.into()));
+            w.set_abdtr(write_config.abdtr);
+            w.set_absize(SizeInBits::from_bits(write_config.absize.into()));
+
             w.set_dqse(true);
         });
 
+        if let Some(ab) = write_config.alternate_bytes {
+            reg.wabr().write(|v| v.set_alternate(ab));
+        }
+
         reg.wtcr().modify(|w| w.set_dcyc(write_config.dummy.into()));
 
         // Enable memory mapped mode
@@ -244,6 +262,11 @@ impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
         });
     }
 
+    /// Size in bytes of the external memory, as set by [`Config::device_size`].
+    pub fn memory_size(&self) -> u64 {
+        1 << (T::REGS.dcr1().read().devsize() + 1)
+    }
+
     fn new_inner(
         peri: impl Peripheral<P = T> + 'd,
         d0: Option<PeripheralRef<'d, AnyPin>>,
@@ -449,6 +472,9 @@ impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
                 w.set_abdtr(command.abdtr);
                 w.set_absize(SizeInBits::from_bits(command.absize.into()));
             })
+        } else {
+            // Don't leave the alternate bytes of a previous command enabled
+            T::REGS.ccr().modify(|w| w.set_abmode(PhaseMode::NONE));
         }
 
         // Configure dummy cycles
@@ -474,11 +500,13 @@ impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
             w.set_isize(SizeInBits::from_bits(command.isize.into()));
 
             w.set_admode(PhaseMode::from_bits(command.adwidth.into()));
-            w.set_addtr(command.idtr);
+            w.set_addtr(command.addtr);
             w.set_adsize(SizeInBits::from_bits(command.adsize.into()));
 
             w.set_dmode(PhaseMode::from_bits(command.dwidth.into()));
             w.set_ddtr(command.ddtr);
+
+            w.set_dqse(command.dqse);
         });
 
         // Set informationrequired to initiate transaction
@@ -680,6 +708,30 @@ impl<'d, T: Instance, M: PeriMode> Ospi<'d, T, M> {
     }
 }
 
+impl<T: Instance, M: PeriMode> Ospi<'static, T, M> {
+    /// Enter memory mapped mode for good, and return the external memory as a slice.
+    ///
+    /// The driver is leaked, so the peripheral keeps serving reads of the memory mapped region
+    /// for the rest of the program. Code can be executed in place from it, for instance
+    /// functions placed there by the linker script. See
+    /// [`enable_memory_mapped_mode`](Self::enable_memory_mapped_mode) for the configs.
+    ///
+    /// The slice is limited to the 256 MiB memory mapped region. Returns
+    /// [`OspiError::InvalidConfiguration`] if [`Config::device_size`] was left unset.
+    pub fn into_memory_mapped(
+        mut self,
+        read_config: TransferConfig,
+        write_config: TransferConfig,
+    ) -> Result<&'static [u8], OspiError> {
+        let size = mapped_size(T::REGS.dcr1().read().devsize()).ok_or(OspiError::InvalidConfiguration)?;
+        self.enable_memory_mapped_mode(read_config, write_config)?;
+        core::mem::forget(self);
+
+        // SAFETY: the region stays mapped since the driver is never dropped
+        Ok(unsafe { core::slice::from_raw_parts(T::MEMORY_BASE as *const u8, size) })
+    }
+}
+
 impl<'d, T: Instance> Ospi<'d, T, Blocking> {
     /// Create new blocking OSPI driver for a single spi external chip
     pub fn new_blocking_singlespi(
@@ -1210,6 +1262,8 @@ pub(crate) trait SealedOctospimInstance {
 /// OctoSPI instance trait.
 pub(crate) trait SealedInstance {
     const REGS: Regs;
+    /// Start of the memory mapped region
+    const MEMORY_BASE: usize;
 }
 
 /// OSPI instance trait.
@@ -1249,11 +1303,34 @@ impl SealedOctospimInstance for peripherals::OCTOSPI2 {
     const OCTOSPI_IDX: u8 = 2;
 }
 
+/// Size of the memory mapped region of an instance
+const MEMORY_WINDOW: u64 = 256 * 1024 * 1024;
+
+/// Returns the size of the memory mapped slice for a DEVSIZE value, `None` if it is unset.
+fn mapped_size(devsize: u8) -> Option<usize> {
+    match devsize {
+        // MemorySize::Other(0), a 2 byte memory
+        0 => None,
+        _ => Some((1u64 << (devsize + 1)).min(MEMORY_WINDOW) as usize),
+    }
+}
+
+// OCTOSPI2 is mapped below OCTOSPI1 on all parts having both
+macro_rules! memory_base {
+    (OCTOSPI2) => {
+        0x7000_0000
+    };
+    ($inst:ident) => {
+        0x9000_0000
+    };
+}
+
 #[cfg(octospim_v1)]
 foreach_peripheral!(
     (octospi, $inst:ident) => {
         impl SealedInstance for peripherals::$inst {
             const REGS: Regs = crate::pac::$inst;
+            const MEMORY_BASE: usize = memory_base!($inst);
         }
 
         impl Instance for peripherals::$inst {}
@@ -1265,6 +1342,7 @@ foreach_peripheral!(
     (octospi, $inst:ident) => {
         impl SealedInstance for peripherals::$inst {
             const REGS: Regs = crate::pac::$inst;
+            const MEMORY_BASE: usize = memory_base!($inst);
         }
 
         impl Instance for peripherals::$inst {}
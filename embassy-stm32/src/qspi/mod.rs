@@ -201,6 +201,60 @@ impl<'d, T: Instance, M: PeriMode> Qspi<'d, T, M> {
         T::REGS.fcr().modify(|v| v.set_ctcf(true));
     }
 
+    /// Enter memory mapped mode.
+    ///
+    /// `read_config` describes the read command issued for accesses to the memory mapped
+    /// region, its address is ignored. The memory is read only in this mode.
+    pub fn enable_memory_mapped_mode(&mut self, read_config: TransferConfig) {
+        if let QspiWidth::NONE = read_config.awidth {
+            panic!("QSPI memory mapped reads need an address width");
+        }
+        if let QspiWidth::NONE = read_config.dwidth {
+            panic!("QSPI memory mapped reads need a data width");
+        }
+
+        #[cfg(not(stm32h7))]
+        T::REGS.cr().modify(|v| v.set_dmaen(false));
+
+        T::REGS.fcr().modify(|v| {
+            v.set_csmf(true);
+            v.set_ctcf(true);
+            v.set_ctef(true);
+            v.set_ctof(true);
+        });
+
+        while T::REGS.sr().read().busy() {}
+
+        // Keep the chip selected after a read, so sequential accesses are prefetched
+        T::REGS.cr().modify(|v| v.set_tcen(false));
+
+        T::REGS.ccr().write(|v| {
+            v.set_fmode(QspiMode::MemoryMapped.into());
+            v.set_imode(read_config.iwidth.into());
+            v.set_instruction(read_config.instruction);
+            v.set_admode(read_config.awidth.into());
+            v.set_adsize(self.config.address_size.into());
+            v.set_dmode(read_config.dwidth.into());
+            v.set_abmode(QspiWidth::NONE.into());
+            v.set_dcyc(read_config.dummy.into());
+        });
+    }
+
+    /// Quit memory mapped mode.
+    pub fn disable_memory_mapped_mode(&mut self) {
+        T::REGS.cr().modify(|v| v.set_abort(true));
+        while T::REGS.cr().read().abort() {}
+        while T::REGS.sr().read().busy() {}
+
+        T::REGS.ccr().modify(|v| v.set_fmode(QspiMode::IndirectWrite.into()));
+        T::REGS.fcr().modify(|v| v.set_ctcf(true));
+    }
+
+    /// Size in bytes of the external memory, as set by [`Config::memory_size`].
+    pub fn memory_size(&self) -> u64 {
+        1 << (T::REGS.dcr().read().fsize() + 1)
+    }
+
     fn setup_transaction(&mut self, fmode: QspiMode, transaction: &TransferConfig, data_len: Option<usize>) {
         match (transaction.address, transaction.awidth) {
             (Some(_), QspiWidth::NONE) => panic!("QSPI address can't be sent with an address width of NONE"),
@@ -249,6 +303,27 @@ impl<'d, T: Instance, M: PeriMode> Qspi<'d, T, M> {
     }
 }
 
+impl<T: Instance, M: PeriMode> Qspi<'static, T, M> {
+    /// Enter memory mapped mode for good, and return the external memory as a slice.
+    ///
+    /// The driver is leaked, so the peripheral keeps serving reads of the memory mapped region
+    /// for the rest of the program. Code can be executed in place from it, for instance
+    /// functions placed there by the linker script.
+    ///
+    /// The slice is limited to the 256 MiB memory mapped region. Panics if
+    /// [`Config::memory_size`] was left unset.
+    pub fn into_memory_mapped(mut self, read_config: TransferConfig) -> &'static [u8] {
+        let Some(size) = mapped_size(T::REGS.dcr().read().fsize()) else {
+            panic!("QSPI memory size must be set for memory mapped mode");
+        };
+        self.enable_memory_mapped_mode(read_config);
+        core::mem::forget(self);
+
+        // SAFETY: the region stays mapped since the driver is never dropped
+        unsafe { core::slice::from_raw_parts(MEMORY_BASE as *const u8, size) }
+    }
+}
+
 impl<'d, T: Instance> Qspi<'d, T, Blocking> {
     /// Create a new QSPI driver for bank 1, in blocking mode.
     pub fn new_blocking_bank1(
@@ -439,6 +514,21 @@ impl<'d, T: Instance> Qspi<'d, T, Async> {
     }
 }
 
+/// Start of the memory mapped region
+const MEMORY_BASE: usize = 0x9000_0000;
+
+/// Size of the memory mapped region
+const MEMORY_WINDOW: u64 = 256 * 1024 * 1024;
+
+/// Returns the size of the memory mapped slice for a FSIZE value, `None` if it is unset.
+fn mapped_size(fsize: u8) -> Option<usize> {
+    match fsize {
+        // MemorySize::Other(0), a 2 byte memory
+        0 => None,
+        _ => Some((1u64 << (fsize + 1)).min(MEMORY_WINDOW) as usize),
+    }
+}
+
 trait SealedInstance {
     const REGS: Regs;
 }
@@ -0,0 +1,550 @@
+//! eMMC device support.
+
+use embassy_hal_internal::drop::OnDrop;
+use sdio_host::BusWidth;
+
+use super::{
+    clk_div, Cmd, DataBlock, Error, Instance, InterruptHandler, PowerCtrl, Response, Sdmmc, SdmmcDma, SD_INIT_FREQ,
+};
+use crate::time::Hertz;
+
+/// Number of CMD1 / CMD13 polls before giving up on a device becoming ready.
+const READY_RETRIES: u32 = 100_000;
+
+/// Relative address assigned to the device. Unlike SD cards, the host picks it.
+const EMMC_RCA: u16 = 1;
+
+/// CMD1 argument: 2.7-3.6 V and 1.70-1.95 V, sector addressing supported.
+const OCR_VOLTAGE_WINDOW: u32 = 0x00FF_8080;
+const OCR_SECTOR_MODE: u32 = 1 << 30;
+const OCR_POWER_UP_DONE: u32 = 1 << 31;
+
+/// Highest clock of the legacy timing.
+const LEGACY_MAX_FREQ: u32 = 26_000_000;
+/// Highest clock of the high speed timing. sdmmc_v1 tops out at 50 MHz.
+#[cfg(sdmmc_v1)]
+const HS_MAX_FREQ: u32 = 50_000_000;
+#[cfg(sdmmc_v2)]
+const HS_MAX_FREQ: u32 = 52_000_000;
+/// Highest clock of the HS200 timing.
+#[cfg(sdmmc_v2)]
+const HS200_MAX_FREQ: u32 = 200_000_000;
+
+/// Extended CSD register indices and values
+#[allow(dead_code)]
+mod ext_csd {
+    pub const RPMB_SIZE_MULT: usize = 168;
+    pub const PARTITION_CONFIG: u8 = 179;
+    pub const BUS_WIDTH: u8 = 183;
+    pub const HS_TIMING: u8 = 185;
+    pub const REVISION: usize = 192;
+    pub const DEVICE_TYPE: usize = 196;
+    pub const SEC_COUNT: usize = 212;
+    pub const BOOT_SIZE_MULT: usize = 226;
+
+    pub const TIMING_HS: u8 = 1;
+    pub const TIMING_HS200: u8 = 2;
+
+    pub const DEVICE_TYPE_HS_52: u8 = 1 << 1;
+    pub const DEVICE_TYPE_HS200_18V: u8 = 1 << 4;
+    pub const DEVICE_TYPE_HS200_12V: u8 = 1 << 5;
+
+    pub const PARTITION_ACCESS_MASK: u8 = 0b111;
+    pub const BOOT_PARTITION_ENABLE_MASK: u8 = 0b111 << 3;
+    pub const BOOT_ACK: u8 = 1 << 6;
+}
+
+/// Bits of the R1 device status
+mod r1 {
+    pub const SWITCH_ERROR: u32 = 1 << 7;
+    pub const READY_FOR_DATA: u32 = 1 << 8;
+    pub const STATE_TRANSFER: u32 = 4;
+}
+
+/// Tuning block pattern sent by CMD21 on a 4 bit bus.
+#[cfg(sdmmc_v2)]
+const TUNING_BLOCK_4BIT: [u8; 64] = [
+    0xff, 0x0f, 0xff, 0x00, 0xff, 0xcc, 0xc3, 0xcc, 0xc3, 0x3c, 0xcc, 0xff, 0xfe, 0xff, 0xfe, 0xef, //
+    0xff, 0xdf, 0xff, 0xdd, 0xff, 0xfb, 0xff, 0xfb, 0xbf, 0xff, 0x7f, 0xff, 0x77, 0xf7, 0xbd, 0xef, //
+    0xff, 0xf0, 0xff, 0xf0, 0x0f, 0xfc, 0xcc, 0x3c, 0xcc, 0x33, 0xcc, 0xcf, 0xff, 0xef, 0xff, 0xee, //
+    0xff, 0xfd, 0xff, 0xfd, 0xdf, 0xff, 0xbf, 0xff, 0xbb, 0xff, 0xf7, 0xff, 0xf7, 0x7f, 0x7b, 0xde, //
+];
+
+/// Tuning block pattern sent by CMD21 on an 8 bit bus.
+#[cfg(sdmmc_v2)]
+const TUNING_BLOCK_8BIT: [u8; 128] = [
+    0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff, 0xcc, 0xcc, 0xcc, 0x33, 0xcc, 0xcc, //
+    0xcc, 0x33, 0x33, 0xcc, 0xcc, 0xcc, 0xff, 0xff, 0xff, 0xee, 0xff, 0xff, 0xff, 0xee, 0xee, 0xff, //
+    0xff, 0xff, 0xdd, 0xff, 0xff, 0xff, 0xdd, 0xdd, 0xff, 0xff, 0xff, 0xbb, 0xff, 0xff, 0xff, 0xbb, //
+    0xbb, 0xff, 0xff, 0xff, 0x77, 0xff, 0xff, 0xff, 0x77, 0x77, 0xff, 0x77, 0xbb, 0xdd, 0xee, 0xff, //
+    0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff, 0xcc, 0xcc, 0xcc, 0x33, 0xcc, //
+    0xcc, 0xcc, 0x33, 0x33, 0xcc, 0xcc, 0xcc, 0xff, 0xff, 0xff, 0xee, 0xff, 0xff, 0xff, 0xee, 0xee, //
+    0xff, 0xff, 0xff, 0xdd, 0xff, 0xff, 0xff, 0xdd, 0xdd, 0xff, 0xff, 0xff, 0xbb, 0xff, 0xff, 0xff, //
+    0xbb, 0xbb, 0xff, 0xff, 0xff, 0x77, 0xff, 0xff, 0xff, 0x77, 0x77, 0xff, 0x77, 0xbb, 0xdd, 0xee, //
+];
+
+/// Fields of the Extended CSD register
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ExtCsd {
+    /// Extended CSD revision (EXT_CSD_REV)
+    pub revision: u8,
+    /// Number of 512 byte sectors of the user partition, 0 for devices up to 2 GB (SEC_COUNT)
+    pub sector_count: u32,
+    /// Supported bus timings (DEVICE_TYPE)
+    pub device_type: u8,
+    /// Size of each boot partition in bytes
+    pub boot_partition_size: u32,
+    /// Size of the RPMB partition in bytes
+    pub rpmb_partition_size: u32,
+    /// Boot configuration and accessed partition (PARTITION_CONFIG)
+    pub partition_config: u8,
+}
+
+impl ExtCsd {
+    fn parse(bytes: &[u8; 512]) -> Self {
+        Self {
+            revision: bytes[ext_csd::REVISION],
+            sector_count: u32::from_le_bytes(bytes[ext_csd::SEC_COUNT..ext_csd::SEC_COUNT + 4].try_into().unwrap()),
+            device_type: bytes[ext_csd::DEVICE_TYPE],
+            boot_partition_size: u32::from(bytes[ext_csd::BOOT_SIZE_MULT]) * 128 * 1024,
+            rpmb_partition_size: u32::from(bytes[ext_csd::RPMB_SIZE_MULT]) * 128 * 1024,
+            partition_config: bytes[ext_csd::PARTITION_CONFIG as usize],
+        }
+    }
+
+    /// The device supports the high speed timing at 52 MHz.
+    pub fn supports_high_speed(&self) -> bool {
+        self.device_type & ext_csd::DEVICE_TYPE_HS_52 != 0
+    }
+
+    /// The device supports the HS200 timing.
+    pub fn supports_hs200(&self) -> bool {
+        self.device_type & (ext_csd::DEVICE_TYPE_HS200_18V | ext_csd::DEVICE_TYPE_HS200_12V) != 0
+    }
+}
+
+/// eMMC bus timing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EmmcTiming {
+    /// Backwards compatible timing, up to 26 MHz
+    #[default]
+    Legacy,
+    /// High speed SDR, up to 52 MHz
+    HighSpeed,
+    /// HS200, up to 200 MHz. Needs 1.8 V or 1.2 V I/O signalling.
+    Hs200,
+}
+
+/// eMMC hardware partitions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EmmcPartition {
+    /// User data area
+    #[default]
+    User,
+    /// Boot partition 1
+    Boot1,
+    /// Boot partition 2
+    Boot2,
+    /// Replay protected memory block
+    Rpmb,
+    /// General purpose partition 1 to 4
+    General(u8),
+}
+
+impl EmmcPartition {
+    /// Value of the PARTITION_ACCESS field
+    fn access(self) -> Result<u8, Error> {
+        match self {
+            Self::User => Ok(0),
+            Self::Boot1 => Ok(1),
+            Self::Boot2 => Ok(2),
+            Self::Rpmb => Ok(3),
+            Self::General(n @ 1..=4) => Ok(3 + n),
+            Self::General(_) => Err(Error::InvalidPartition),
+        }
+    }
+
+    fn from_access(access: u8) -> Self {
+        match access & ext_csd::PARTITION_ACCESS_MASK {
+            0 => Self::User,
+            1 => Self::Boot1,
+            2 => Self::Boot2,
+            3 => Self::Rpmb,
+            n => Self::General(n - 3),
+        }
+    }
+}
+
+/// eMMC device
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Emmc {
+    /// Relative Card Address
+    pub rca: u16,
+    /// Operation Conditions Register, as returned by CMD1
+    pub ocr: u32,
+    /// Card ID
+    pub cid: u128,
+    /// Card Specific Data
+    pub csd: u128,
+    /// Extended Card Specific Data
+    pub ext_csd: ExtCsd,
+    /// Number of data lanes in use
+    pub bus_width: u8,
+    /// Bus timing in use
+    pub timing: EmmcTiming,
+    /// Currently accessed partition
+    pub partition: EmmcPartition,
+}
+
+impl Emmc {
+    /// Size of the user partition in bytes
+    pub fn size(&self) -> u64 {
+        if self.ext_csd.sector_count != 0 {
+            return u64::from(self.ext_csd.sector_count) * 512;
+        }
+
+        // Devices up to 2 GB report their size in the CSD
+        let read_bl_len = (self.csd >> 80) & 0xF;
+        let c_size = (self.csd >> 62) & 0xFFF;
+        let c_size_mult = (self.csd >> 47) & 0x7;
+        ((c_size + 1) << (c_size_mult + 2 + read_bl_len)) as u64
+    }
+
+    /// Size of a partition in bytes, `None` for general purpose partitions.
+    pub fn partition_size(&self, partition: EmmcPartition) -> Option<u64> {
+        match partition {
+            EmmcPartition::User => Some(self.size()),
+            EmmcPartition::Boot1 | EmmcPartition::Boot2 => Some(u64::from(self.ext_csd.boot_partition_size)),
+            EmmcPartition::Rpmb => Some(u64::from(self.ext_csd.rpmb_partition_size)),
+            EmmcPartition::General(_) => None,
+        }
+    }
+
+    /// Devices above 2 GB are sector addressed, smaller ones are byte addressed.
+    pub(super) fn sector_addressed(&self) -> bool {
+        self.ocr & OCR_SECTOR_MODE != 0
+    }
+}
+
+/// eMMC Commands
+impl Cmd {
+    /// CMD1: Send Operation Conditions
+    const fn send_op_cond(ocr: u32) -> Cmd {
+        Cmd::new(1, ocr, Response::Short)
+    }
+
+    /// CMD3: Set Relative Address
+    const fn set_rel_addr(rca: u32) -> Cmd {
+        Cmd::new(3, rca, Response::Short)
+    }
+
+    /// CMD21: Send Tuning Block
+    #[cfg(sdmmc_v2)]
+    const fn send_tuning_block() -> Cmd {
+        Cmd::new(21, 0, Response::Short)
+    }
+}
+
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
+    /// Initializes an eMMC device (if present) and sets the bus at the specified frequency.
+    ///
+    /// The bus is switched to the widest width the driver was created with. Above 26 MHz the
+    /// high speed timing is selected if the device supports it. Above 52 MHz on `sdmmc_v2`, with
+    /// a 4 or 8 bit bus, HS200 is tried first: the I/O lines must already run at 1.8 V. The
+    /// sampling point is not tuned, the tuning block only validates the link, and high speed is
+    /// used if it fails.
+    ///
+    /// The user partition is selected, see [`select_partition`](Self::select_partition).
+    pub async fn init_emmc(&mut self, freq: Hertz) -> Result<(), Error> {
+        let regs = T::regs();
+        let ker_ck = T::frequency();
+
+        let bus_width = if self.d7.is_some() {
+            BusWidth::Eight
+        } else if self.d3.is_some() {
+            BusWidth::Four
+        } else {
+            BusWidth::One
+        };
+
+        self.card = None;
+        self.sdio = None;
+        self.emmc = None;
+
+        // While the device is in identification mode, the SDMMC_CK frequency must be no more than 400 kHz.
+        let (_bypass, clkdiv, init_clock) = unwrap!(clk_div(ker_ck, SD_INIT_FREQ.0));
+        self.clock = init_clock;
+
+        // CPSMACT and DPSMACT must be 0 to set WIDBUS
+        Self::wait_idle();
+
+        regs.clkcr().modify(|w| {
+            w.set_widbus(0);
+            w.set_clkdiv(clkdiv);
+            #[cfg(sdmmc_v1)]
+            w.set_bypass(_bypass);
+            #[cfg(sdmmc_v2)]
+            w.set_busspeed(false);
+        });
+
+        regs.power().modify(|w| w.set_pwrctrl(PowerCtrl::On as u8));
+        Self::cmd(Cmd::idle(), false)?;
+
+        let mut retries = READY_RETRIES;
+        let ocr = loop {
+            let ocr = Self::send_op_cond(OCR_VOLTAGE_WINDOW | OCR_SECTOR_MODE)?;
+            if ocr & OCR_POWER_UP_DONE != 0 {
+                break ocr;
+            }
+            retries -= 1;
+            if retries == 0 {
+                return Err(Error::SoftwareTimeout);
+            }
+        };
+
+        Self::cmd(Cmd::all_send_cid(), false)?; // CMD2
+        let cid = Self::long_response();
+
+        let rca = EMMC_RCA;
+        Self::cmd(Cmd::set_rel_addr(u32::from(rca) << 16), false)?; // CMD3
+
+        Self::cmd(Cmd::send_csd(u32::from(rca) << 16), false)?; // CMD9
+        let csd = Self::long_response();
+
+        Self::cmd(Cmd::sel_desel_card(u32::from(rca) << 16), false)?; // CMD7
+        Self::wait_ready(rca)?;
+
+        let mut block = DataBlock([0; 512]);
+        self.read_ext_csd(&mut block).await?;
+        let ext_csd = ExtCsd::parse(&block.0);
+
+        let mut emmc = Emmc {
+            rca,
+            ocr,
+            cid,
+            csd,
+            ext_csd,
+            bus_width: 1,
+            timing: EmmcTiming::Legacy,
+            partition: EmmcPartition::from_access(ext_csd.partition_config),
+        };
+
+        // Set bus width
+        let (lanes, widbus) = match bus_width {
+            BusWidth::Eight => (8, 2),
+            BusWidth::Four => (4, 1),
+            _ => (1, 0),
+        };
+        if lanes > 1 {
+            Self::switch(ext_csd::BUS_WIDTH, widbus)?;
+            Self::wait_ready(rca)?;
+        }
+
+        // CPSMACT and DPSMACT must be 0 to set WIDBUS
+        Self::wait_idle();
+
+        regs.clkcr().modify(|w| w.set_widbus(widbus));
+        emmc.bus_width = lanes;
+
+        // Select the bus timing
+        #[cfg(sdmmc_v2)]
+        if freq.0 > HS_MAX_FREQ && bus_width != BusWidth::One && ext_csd.supports_hs200() {
+            match self.select_hs200(rca, freq, bus_width).await {
+                Ok(()) => emmc.timing = EmmcTiming::Hs200,
+                Err(_) => {
+                    // Go back to a clock the device handles in any timing, high speed is tried below
+                    self.clkcr_set_clkdiv(LEGACY_MAX_FREQ, bus_width)?;
+                    regs.clkcr().modify(|w| w.set_busspeed(false));
+                }
+            }
+        }
+
+        if emmc.timing == EmmcTiming::Legacy {
+            if freq.0 > LEGACY_MAX_FREQ && ext_csd.supports_high_speed() {
+                Self::switch(ext_csd::HS_TIMING, ext_csd::TIMING_HS)?;
+                self.clkcr_set_clkdiv(freq.0.min(HS_MAX_FREQ), bus_width)?;
+                Self::wait_ready(rca)?;
+                emmc.timing = EmmcTiming::HighSpeed;
+            } else {
+                self.clkcr_set_clkdiv(freq.0.min(LEGACY_MAX_FREQ), bus_width)?;
+            }
+        }
+
+        self.emmc = Some(emmc);
+
+        if emmc.partition != EmmcPartition::User {
+            self.select_partition(EmmcPartition::User)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a reference to the initialized eMMC device
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::NoCard if [`init_emmc`](#method.init_emmc)
+    /// has not previously succeeded
+    pub fn emmc(&self) -> Result<&Emmc, Error> {
+        self.emmc.as_ref().ok_or(Error::NoCard)
+    }
+
+    /// Reads the raw Extended CSD register (CMD8).
+    ///
+    /// The device must be selected, which is the case after [`init_emmc`](Self::init_emmc).
+    pub async fn read_ext_csd(&mut self, buffer: &mut DataBlock) -> Result<(), Error> {
+        // NOTE(unsafe) DataBlock uses align 4
+        let buffer = unsafe { &mut *((&mut buffer.0) as *mut [u8; 512] as *mut [u32; 128]) };
+        self.read_data(Cmd::hs_send_ext_csd(0), buffer, 512, 9).await
+    }
+
+    /// Selects the partition accessed by block reads and writes.
+    ///
+    /// RPMB accesses must follow the authenticated frame protocol, which is not handled here.
+    pub fn select_partition(&mut self, partition: EmmcPartition) -> Result<(), Error> {
+        let emmc = self.emmc.as_mut().ok_or(Error::NoCard)?;
+
+        let config = (emmc.ext_csd.partition_config & !ext_csd::PARTITION_ACCESS_MASK) | partition.access()?;
+        Self::switch(ext_csd::PARTITION_CONFIG, config)?;
+        Self::wait_ready(emmc.rca)?;
+
+        emmc.ext_csd.partition_config = config;
+        emmc.partition = partition;
+        Ok(())
+    }
+
+    /// Selects the partition the device boots from, `None` disables booting.
+    ///
+    /// Only [`EmmcPartition::Boot1`], [`EmmcPartition::Boot2`] and [`EmmcPartition::User`] can
+    /// be booted from, other partitions return [`Error::InvalidPartition`]. `ack` makes the
+    /// device send a boot acknowledge pattern.
+    pub fn set_boot_partition(&mut self, partition: Option<EmmcPartition>, ack: bool) -> Result<(), Error> {
+        let emmc = self.emmc.as_mut().ok_or(Error::NoCard)?;
+
+        let enable = match partition {
+            None => 0,
+            Some(EmmcPartition::Boot1) => 1,
+            Some(EmmcPartition::Boot2) => 2,
+            Some(EmmcPartition::User) => 7,
+            Some(_) => return Err(Error::InvalidPartition),
+        };
+        let mut config = emmc.ext_csd.partition_config & ext_csd::PARTITION_ACCESS_MASK;
+        config |= enable << 3;
+        if ack {
+            config |= ext_csd::BOOT_ACK;
+        }
+        Self::switch(ext_csd::PARTITION_CONFIG, config)?;
+        Self::wait_ready(emmc.rca)?;
+
+        emmc.ext_csd.partition_config = config;
+        Ok(())
+    }
+
+    /// Returns the partition the device boots from, `None` if booting is disabled.
+    pub fn boot_partition(&self) -> Result<Option<EmmcPartition>, Error> {
+        let emmc = self.emmc()?;
+        Ok(
+            match (emmc.ext_csd.partition_config & ext_csd::BOOT_PARTITION_ENABLE_MASK) >> 3 {
+                1 => Some(EmmcPartition::Boot1),
+                2 => Some(EmmcPartition::Boot2),
+                7 => Some(EmmcPartition::User),
+                _ => None,
+            },
+        )
+    }
+
+    /// Switches to HS200 and validates the link by reading the tuning block.
+    #[cfg(sdmmc_v2)]
+    async fn select_hs200(&mut self, rca: u16, freq: Hertz, bus_width: BusWidth) -> Result<(), Error> {
+        Self::switch(ext_csd::HS_TIMING, ext_csd::TIMING_HS200)?;
+        self.clkcr_set_clkdiv(freq.0.min(HS200_MAX_FREQ), bus_width)?;
+        T::regs().clkcr().modify(|w| w.set_busspeed(true));
+        Self::wait_ready(rca)?;
+
+        let pattern: &[u8] = match bus_width {
+            BusWidth::Eight => &TUNING_BLOCK_8BIT,
+            _ => &TUNING_BLOCK_4BIT,
+        };
+        let len = pattern.len();
+
+        let mut block = DataBlock([0; 512]);
+        // NOTE(unsafe) DataBlock uses align 4
+        let buffer = unsafe { &mut *((&mut block.0) as *mut [u8; 512] as *mut [u32; 128]) };
+        self.read_data(
+            Cmd::send_tuning_block(),
+            &mut buffer[..len / 4],
+            len as u32,
+            len.trailing_zeros() as u8,
+        )
+        .await?;
+
+        if block.0[..len] != *pattern {
+            return Err(Error::Crc);
+        }
+        Ok(())
+    }
+
+    /// Waits until the device is back in transfer state and ready for data (CMD13).
+    pub(super) fn wait_ready(rca: u16) -> Result<(), Error> {
+        for _ in 0..READY_RETRIES {
+            Self::cmd(Cmd::card_status(u32::from(rca) << 16), false)?; // CMD13
+            let status = T::regs().respr(0).read().cardstatus();
+
+            if status & r1::SWITCH_ERROR != 0 {
+                return Err(Error::SwitchError);
+            }
+            if (status >> 9) & 0xF == r1::STATE_TRANSFER && status & r1::READY_FOR_DATA != 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::SoftwareTimeout)
+    }
+
+    /// CMD6, writes a byte of the Extended CSD
+    fn switch(index: u8, value: u8) -> Result<(), Error> {
+        // Access mode 0b11: write byte
+        let arg = (0b11 << 24) | (u32::from(index) << 16) | (u32::from(value) << 8);
+        Self::cmd(Cmd::cmd6(arg), false)
+    }
+
+    /// CMD1, returns the OCR
+    fn send_op_cond(ocr: u32) -> Result<u32, Error> {
+        match Self::cmd(Cmd::send_op_cond(ocr), false) {
+            // R3 has no CRC
+            Ok(_) | Err(Error::Crc) => Ok(T::regs().respr(0).read().cardstatus()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns a 136 bit response (R2)
+    fn long_response() -> u128 {
+        let regs = T::regs();
+        let r0 = regs.respr(0).read().cardstatus() as u128;
+        let r1 = regs.respr(1).read().cardstatus() as u128;
+        let r2 = regs.respr(2).read().cardstatus() as u128;
+        let r3 = regs.respr(3).read().cardstatus() as u128;
+        (r0 << 96) | (r1 << 64) | (r2 << 32) | r3
+    }
+
+    async fn read_data(&mut self, cmd: Cmd, buffer: &mut [u32], len: u32, block_size: u8) -> Result<(), Error> {
+        let on_drop = OnDrop::new(|| Self::on_drop());
+
+        let transfer = Self::prepare_datapath_read(&self.config, &mut self.dma, buffer, len, block_size);
+        InterruptHandler::<T>::data_interrupts(true);
+        Self::cmd(cmd, true)?;
+
+        let res = Self::wait_data_end().await;
+        Self::clear_interrupt_flags();
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+            drop(transfer);
+        }
+        res
+    }
+}
@@ -0,0 +1,557 @@
+//! SDIO card support.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use sdio_host::BusWidth;
+
+use super::{clk_div, Cmd, Error, Instance, InterruptHandler, PowerCtrl, Response, Sdmmc, SdmmcDma, SD_INIT_FREQ};
+use crate::time::Hertz;
+
+/// Number of CMD5 / CMD52 polls before giving up on a card becoming ready.
+const READY_RETRIES: u32 = 10_000;
+
+/// Card Common Control Registers (function 0)
+#[allow(dead_code)]
+mod cccr {
+    pub const REVISION: u32 = 0x00;
+    pub const IO_ENABLE: u32 = 0x02;
+    pub const IO_READY: u32 = 0x03;
+    pub const INT_ENABLE: u32 = 0x04;
+    pub const INT_PENDING: u32 = 0x05;
+    pub const IO_ABORT: u32 = 0x06;
+    pub const BUS_IF_CONTROL: u32 = 0x07;
+    pub const CARD_CAPABILITY: u32 = 0x08;
+    pub const BUS_SPEED_SELECT: u32 = 0x13;
+
+    pub const BUS_WIDTH_4BIT: u8 = 0b10;
+    pub const BUS_SPEED_SHS: u8 = 1 << 0;
+    pub const BUS_SPEED_EHS: u8 = 1 << 1;
+}
+
+/// Function Basic Registers, at `0x100 * function`
+#[allow(dead_code)]
+mod fbr {
+    pub const INTERFACE_CODE: u32 = 0x00;
+    pub const BLOCK_SIZE: u32 = 0x10;
+}
+
+/// Error flags of the R5 response
+const R5_ERROR_FLAGS: u8 = 0xCB;
+
+/// SDIO card
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SdioCard {
+    /// Relative Card Address
+    pub rca: u16,
+    /// I/O Operation Conditions Register, as returned by CMD5
+    pub ocr: u32,
+    /// Number of I/O functions, excluding function 0
+    pub num_functions: u8,
+    /// The card also contains a memory card (combo card)
+    pub memory_present: bool,
+    /// CCCR and SDIO specification revision (CCCR register 0x00)
+    pub revision: u8,
+}
+
+/// Standard SDIO function interface codes (FBR register 0x00).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SdioInterface {
+    /// No standard interface, vendor specific
+    None,
+    /// SDIO standard UART
+    Uart,
+    /// Bluetooth type-A
+    BluetoothA,
+    /// Bluetooth type-B
+    BluetoothB,
+    /// GPS
+    Gps,
+    /// Camera
+    Camera,
+    /// PHS
+    Phs,
+    /// WLAN
+    Wlan,
+    /// Embedded SDIO-ATA
+    Ata,
+    /// Other standard interface code
+    Other(u8),
+}
+
+impl From<u8> for SdioInterface {
+    fn from(code: u8) -> Self {
+        match code {
+            0x0 => Self::None,
+            0x1 => Self::Uart,
+            0x2 => Self::BluetoothA,
+            0x3 => Self::BluetoothB,
+            0x4 => Self::Gps,
+            0x5 => Self::Camera,
+            0x6 => Self::Phs,
+            0x7 => Self::Wlan,
+            0x8 => Self::Ata,
+            x => Self::Other(x),
+        }
+    }
+}
+
+/// SDIO Commands
+impl Cmd {
+    /// CMD5: I/O Send Operation Conditions
+    const fn io_send_op_cond(ocr: u32) -> Cmd {
+        Cmd::new(5, ocr, Response::Short)
+    }
+
+    /// CMD52: I/O Read/Write Direct
+    const fn io_rw_direct(arg: u32) -> Cmd {
+        Cmd::new(52, arg, Response::Short)
+    }
+
+    /// CMD53: I/O Read/Write Extended
+    const fn io_rw_extended(arg: u32) -> Cmd {
+        Cmd::new(53, arg, Response::Short)
+    }
+}
+
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
+    /// Initializes an SDIO card (if present) and sets the bus at the specified frequency.
+    ///
+    /// The bus is switched to 4 bits if the driver was created with 4 data lanes, and to high
+    /// speed if `freq` is above 25 MHz and the card supports it. I/O functions are left disabled,
+    /// see [`enable_function`](Self::enable_function).
+    pub async fn init_sdio(&mut self, freq: Hertz) -> Result<(), Error> {
+        let regs = T::regs();
+        let ker_ck = T::frequency();
+
+        let bus_width = match self.d3.is_some() {
+            true => BusWidth::Four,
+            false => BusWidth::One,
+        };
+
+        self.card = None;
+        self.sdio = None;
+        self.emmc = None;
+
+        // While the card is in identification mode, the SDMMC_CK frequency must be no more than 400 kHz.
+        let (_bypass, clkdiv, init_clock) = unwrap!(clk_div(ker_ck, SD_INIT_FREQ.0));
+        self.clock = init_clock;
+
+        // CPSMACT and DPSMACT must be 0 to set WIDBUS
+        Self::wait_idle();
+
+        regs.clkcr().modify(|w| {
+            w.set_widbus(0);
+            w.set_clkdiv(clkdiv);
+            #[cfg(sdmmc_v1)]
+            w.set_bypass(_bypass);
+        });
+
+        regs.power().modify(|w| w.set_pwrctrl(PowerCtrl::On as u8));
+        Self::cmd(Cmd::idle(), false)?;
+
+        // Probe the supported voltage window. R4 has no CRC.
+        let ocr = Self::io_send_op_cond(0)?;
+        if (ocr >> 28) & 0x7 == 0 {
+            return Err(Error::UnsupportedCardType);
+        }
+
+        // Initialize the card with the voltages it supports
+        let mut retries = READY_RETRIES;
+        let ocr = loop {
+            let ocr = Self::io_send_op_cond(ocr & 0x00FF_FF00)?;
+            if ocr & 0x8000_0000 != 0 {
+                break ocr;
+            }
+            retries -= 1;
+            if retries == 0 {
+                return Err(Error::SoftwareTimeout);
+            }
+        };
+
+        let mut card = SdioCard {
+            ocr,
+            num_functions: ((ocr >> 28) & 0x7) as u8,
+            memory_present: ocr & (1 << 27) != 0,
+            ..Default::default()
+        };
+
+        Self::cmd(Cmd::send_rel_addr(), false)?; // CMD3
+        card.rca = (regs.respr(0).read().cardstatus() >> 16) as u16;
+
+        Self::cmd(Cmd::sel_desel_card(u32::from(card.rca) << 16), false)?; // CMD7
+
+        card.revision = self.read_byte(0, cccr::REVISION)?;
+
+        // Set bus width
+        if bus_width == BusWidth::Four {
+            let bus_if = self.read_byte(0, cccr::BUS_IF_CONTROL)?;
+            self.write_byte(0, cccr::BUS_IF_CONTROL, (bus_if & !0b11) | cccr::BUS_WIDTH_4BIT)?;
+        }
+
+        // CPSMACT and DPSMACT must be 0 to set WIDBUS
+        Self::wait_idle();
+
+        regs.clkcr().modify(|w| {
+            w.set_widbus(match bus_width {
+                BusWidth::Four => 1,
+                _ => 0,
+            })
+        });
+
+        // Switch to high speed if needed and supported
+        let mut max_freq = 25_000_000;
+        if freq.0 > 25_000_000 {
+            let speed = self.read_byte(0, cccr::BUS_SPEED_SELECT)?;
+            if speed & cccr::BUS_SPEED_SHS != 0 {
+                self.write_byte(0, cccr::BUS_SPEED_SELECT, speed | cccr::BUS_SPEED_EHS)?;
+                max_freq = 50_000_000;
+            }
+        }
+        self.clkcr_set_clkdiv(freq.0.min(max_freq), bus_width)?;
+
+        // Enable SDIO specific operations, needed to detect card interrupts
+        regs.dctrl().modify(|w| w.set_sdioen(true));
+
+        self.sdio = Some(card);
+        Ok(())
+    }
+
+    /// Get a reference to the initialized SDIO card
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::NoCard if [`init_sdio`](#method.init_sdio)
+    /// has not previously succeeded
+    pub fn sdio_card(&self) -> Result<&SdioCard, Error> {
+        self.sdio.as_ref().ok_or(Error::NoCard)
+    }
+
+    /// Read a register of an I/O function (CMD52).
+    pub fn read_byte(&mut self, function: u8, address: u32) -> Result<u8, Error> {
+        Self::io_rw_direct(function, address, None)
+    }
+
+    /// Write a register of an I/O function (CMD52).
+    pub fn write_byte(&mut self, function: u8, address: u32, value: u8) -> Result<(), Error> {
+        Self::io_rw_direct(function, address, Some(value)).map(|_| ())
+    }
+
+    /// Returns the standard interface code of an I/O function.
+    pub fn function_interface(&mut self, function: u8) -> Result<SdioInterface, Error> {
+        assert!((1..=7).contains(&function));
+        let code = self.read_byte(0, 0x100 * u32::from(function) + fbr::INTERFACE_CODE)? & 0x0F;
+        Ok(code.into())
+    }
+
+    /// Enables an I/O function and waits until it reports ready.
+    pub fn enable_function(&mut self, function: u8) -> Result<(), Error> {
+        assert!((1..=7).contains(&function));
+        let mask = 1 << function;
+
+        let enabled = self.read_byte(0, cccr::IO_ENABLE)?;
+        self.write_byte(0, cccr::IO_ENABLE, enabled | mask)?;
+
+        for _ in 0..READY_RETRIES {
+            if self.read_byte(0, cccr::IO_READY)? & mask != 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::SoftwareTimeout)
+    }
+
+    /// Disables an I/O function.
+    pub fn disable_function(&mut self, function: u8) -> Result<(), Error> {
+        assert!((1..=7).contains(&function));
+        let enabled = self.read_byte(0, cccr::IO_ENABLE)?;
+        self.write_byte(0, cccr::IO_ENABLE, enabled & !(1 << function))
+    }
+
+    /// Sets the block size used by block mode transfers of an I/O function.
+    ///
+    /// Only powers of two are supported by the SDMMC peripheral.
+    pub fn set_block_size(&mut self, function: u8, block_size: u16) -> Result<(), Error> {
+        assert!(function <= 7);
+        assert!(block_size.is_power_of_two() && block_size <= 2048);
+        let base = 0x100 * u32::from(function) + fbr::BLOCK_SIZE;
+        let [lo, hi] = block_size.to_le_bytes();
+        self.write_byte(0, base, lo)?;
+        self.write_byte(0, base + 1, hi)
+    }
+
+    /// Returns the block size used by block mode transfers of an I/O function.
+    pub fn block_size(&mut self, function: u8) -> Result<u16, Error> {
+        assert!(function <= 7);
+        let base = 0x100 * u32::from(function) + fbr::BLOCK_SIZE;
+        let lo = self.read_byte(0, base)?;
+        let hi = self.read_byte(0, base + 1)?;
+        Ok(u16::from_le_bytes([lo, hi]))
+    }
+
+    /// Enables the interrupt of an I/O function, along with the master interrupt enable.
+    pub fn enable_function_interrupt(&mut self, function: u8) -> Result<(), Error> {
+        assert!((1..=7).contains(&function));
+        let enabled = self.read_byte(0, cccr::INT_ENABLE)?;
+        self.write_byte(0, cccr::INT_ENABLE, enabled | (1 << function) | 1)
+    }
+
+    /// Returns the I/O functions with a pending interrupt, as a bitmask (bit 1 for function 1).
+    pub fn pending_interrupts(&mut self) -> Result<u8, Error> {
+        self.read_byte(0, cccr::INT_PENDING)
+    }
+
+    /// Waits for the card to signal an interrupt on the DAT1 line.
+    ///
+    /// The interrupt stays asserted until it is cleared in the function that raised it, see
+    /// [`pending_interrupts`](Self::pending_interrupts).
+    pub async fn wait_for_interrupt(&mut self) {
+        let regs = T::regs();
+        let _on_drop = OnDrop::new(|| regs.maskr().modify(|w| w.set_sdioitie(false)));
+
+        poll_fn(|cx| {
+            T::state().register(cx.waker());
+            if regs.star().read().sdioit() {
+                regs.icr().write(|w| w.set_sdioitc(true));
+                return Poll::Ready(());
+            }
+            // The interrupt handler masks all interrupts, so arm it again on every poll
+            regs.maskr().modify(|w| w.set_sdioitie(true));
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Read from an I/O function in byte mode (CMD53).
+    ///
+    /// Reads `buffer.len() * 4` bytes, at most 512. On `sdmmc_v1` the length must be a power of two.
+    pub async fn read_bytes(
+        &mut self,
+        function: u8,
+        address: u32,
+        incrementing: bool,
+        buffer: &mut [u32],
+    ) -> Result<(), Error> {
+        let len = buffer.len() * 4;
+        assert!(len > 0 && len <= 512);
+        let arg = io_rw_extended_arg(false, function, false, incrementing, address, (len % 512) as u16);
+        self.io_read_extended(arg, buffer, len as u32, byte_mode_block_size(len))
+            .await
+    }
+
+    /// Write to an I/O function in byte mode (CMD53).
+    ///
+    /// Writes `buffer.len() * 4` bytes, at most 512. On `sdmmc_v1` the length must be a power of two.
+    pub async fn write_bytes(
+        &mut self,
+        function: u8,
+        address: u32,
+        incrementing: bool,
+        buffer: &[u32],
+    ) -> Result<(), Error> {
+        let len = buffer.len() * 4;
+        assert!(len > 0 && len <= 512);
+        let arg = io_rw_extended_arg(true, function, false, incrementing, address, (len % 512) as u16);
+        self.io_write_extended(arg, buffer, len as u32, byte_mode_block_size(len))
+            .await
+    }
+
+    /// Read from an I/O function in block mode (CMD53).
+    ///
+    /// The buffer length must be a multiple of the function block size, set with
+    /// [`set_block_size`](Self::set_block_size).
+    pub async fn read_blocks(
+        &mut self,
+        function: u8,
+        address: u32,
+        incrementing: bool,
+        block_size: u16,
+        buffer: &mut [u32],
+    ) -> Result<(), Error> {
+        let (len, blocks) = block_mode_len(block_size, buffer.len());
+        let arg = io_rw_extended_arg(false, function, true, incrementing, address, blocks);
+        self.io_read_extended(arg, buffer, len, block_size.trailing_zeros() as u8)
+            .await
+    }
+
+    /// Write to an I/O function in block mode (CMD53).
+    ///
+    /// The buffer length must be a multiple of the function block size, set with
+    /// [`set_block_size`](Self::set_block_size).
+    pub async fn write_blocks(
+        &mut self,
+        function: u8,
+        address: u32,
+        incrementing: bool,
+        block_size: u16,
+        buffer: &[u32],
+    ) -> Result<(), Error> {
+        let (len, blocks) = block_mode_len(block_size, buffer.len());
+        let arg = io_rw_extended_arg(true, function, true, incrementing, address, blocks);
+        self.io_write_extended(arg, buffer, len, block_size.trailing_zeros() as u8)
+            .await
+    }
+
+    /// CMD5, returns the I/O OCR
+    fn io_send_op_cond(ocr: u32) -> Result<u32, Error> {
+        match Self::cmd(Cmd::io_send_op_cond(ocr), false) {
+            // R4 has no CRC
+            Ok(_) | Err(Error::Crc) => Ok(T::regs().respr(0).read().cardstatus()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// CMD52, returns the data byte of the R5 response
+    fn io_rw_direct(function: u8, address: u32, write: Option<u8>) -> Result<u8, Error> {
+        assert!(function <= 7);
+        assert!(address <= 0x1_FFFF);
+
+        let mut arg = (u32::from(function) << 28) | (address << 9);
+        if let Some(value) = write {
+            arg |= (1 << 31) | u32::from(value);
+        }
+
+        Self::cmd(Cmd::io_rw_direct(arg), false)?;
+        Self::check_r5()
+    }
+
+    /// Checks the flags of an R5 response, returns its data byte
+    fn check_r5() -> Result<u8, Error> {
+        let r5 = T::regs().respr(0).read().cardstatus();
+        let flags = (r5 >> 8) as u8;
+        if flags & R5_ERROR_FLAGS != 0 {
+            return Err(Error::SdioResponse(flags));
+        }
+        Ok(r5 as u8)
+    }
+
+    async fn io_read_extended(&mut self, arg: u32, buffer: &mut [u32], len: u32, block_size: u8) -> Result<(), Error> {
+        let on_drop = OnDrop::new(|| Self::on_drop());
+
+        let transfer = Self::prepare_datapath_read(&self.config, &mut self.dma, buffer, len, block_size);
+        #[cfg(sdmmc_v2)]
+        set_sdio_multibyte::<T>(arg);
+        InterruptHandler::<T>::data_interrupts(true);
+        Self::cmd(Cmd::io_rw_extended(arg), true)?;
+        Self::check_r5()?;
+
+        let res = Self::wait_data_end().await;
+        Self::clear_interrupt_flags();
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+            drop(transfer);
+        }
+        res
+    }
+
+    async fn io_write_extended(&mut self, arg: u32, buffer: &[u32], len: u32, block_size: u8) -> Result<(), Error> {
+        let on_drop = OnDrop::new(|| Self::on_drop());
+
+        // sdmmc_v1 uses different cmd/dma order than v2, but only for writes
+        #[cfg(sdmmc_v1)]
+        {
+            Self::cmd(Cmd::io_rw_extended(arg), true)?;
+            Self::check_r5()?;
+        }
+
+        let transfer = self.prepare_datapath_write(buffer, len, block_size);
+        #[cfg(sdmmc_v2)]
+        set_sdio_multibyte::<T>(arg);
+        InterruptHandler::<T>::data_interrupts(true);
+
+        #[cfg(sdmmc_v2)]
+        {
+            Self::cmd(Cmd::io_rw_extended(arg), true)?;
+            Self::check_r5()?;
+        }
+
+        let res = Self::wait_data_end().await;
+        Self::clear_interrupt_flags();
+
+        if res.is_ok() {
+            on_drop.defuse();
+            Self::stop_datapath();
+            drop(transfer);
+        }
+        res
+    }
+
+    pub(super) async fn wait_data_end() -> Result<(), Error> {
+        let regs = T::regs();
+        poll_fn(|cx| {
+            T::state().register(cx.waker());
+            let status = regs.star().read();
+
+            if status.dcrcfail() {
+                return Poll::Ready(Err(Error::Crc));
+            }
+            if status.dtimeout() {
+                return Poll::Ready(Err(Error::Timeout));
+            }
+            #[cfg(sdmmc_v1)]
+            if status.stbiterr() {
+                return Poll::Ready(Err(Error::StBitErr));
+            }
+            if status.dataend() {
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// Builds the argument of CMD53. A count of 0 means 512 bytes in byte mode.
+fn io_rw_extended_arg(
+    write: bool,
+    function: u8,
+    block_mode: bool,
+    incrementing: bool,
+    address: u32,
+    count: u16,
+) -> u32 {
+    assert!(function <= 7);
+    assert!(address <= 0x1_FFFF);
+    assert!(count <= 0x1FF);
+
+    (u32::from(write) << 31)
+        | (u32::from(function) << 28)
+        | (u32::from(block_mode) << 27)
+        | (u32::from(incrementing) << 26)
+        | (address << 9)
+        | u32::from(count)
+}
+
+/// Data path block size (as a power of two) of a byte mode transfer.
+fn byte_mode_block_size(len: usize) -> u8 {
+    // sdmmc_v2 transfers any length in SDIO multibyte mode, sdmmc_v1 sends it as a single block
+    #[cfg(sdmmc_v1)]
+    assert!(len.is_power_of_two(), "Byte mode length must be a power of two");
+    len.next_power_of_two().trailing_zeros() as u8
+}
+
+/// Returns the length in bytes and the block count of a block mode transfer.
+fn block_mode_len(block_size: u16, words: usize) -> (u32, u16) {
+    assert!(block_size.is_power_of_two() && block_size >= 4);
+    let len = words * 4;
+    assert!(
+        len % block_size as usize == 0,
+        "Length must be a multiple of the block size"
+    );
+    let blocks = len / block_size as usize;
+    assert!(blocks > 0 && blocks <= 0x1FF);
+    (len as u32, blocks as u16)
+}
+
+/// Switches the data path to SDIO multibyte mode for byte mode CMD53 transfers.
+#[cfg(sdmmc_v2)]
+fn set_sdio_multibyte<T: Instance>(arg: u32) {
+    let block_mode = arg & (1 << 27) != 0;
+    T::regs()
+        .dctrl()
+        .modify(|w| w.set_dtmode(if block_mode { 0b00 } else { 0b01 }));
+}
@@ -0,0 +1,428 @@
+//! SPI slave (peripheral) mode.
+
+use core::future::poll_fn;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::Poll;
+
+use embassy_futures::select::{select, Either};
+use embassy_hal_internal::{into_ref, PeripheralRef};
+
+use super::{
+    check_error_flags, set_rxdmaen, set_txdmaen, BitOrder, CsPin, Error, Info, Instance, MisoPin, Mode, MosiPin,
+    RegsExt, RxDma, SckPin, SealedWord, TxDma, MODE_0,
+};
+use crate::dma::{ChannelAndRequest, ReadableRingBuffer};
+use crate::exti::{Channel as _, ExtiInputFuture};
+use crate::gpio::{AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
+use crate::pac::spi::vals;
+use crate::Peripheral;
+
+/// SPI slave configuration.
+#[non_exhaustive]
+#[derive(Copy, Clone)]
+pub struct SlaveConfig {
+    /// SPI mode.
+    pub mode: Mode,
+    /// Bit order.
+    pub bit_order: BitOrder,
+    /// Enable internal pullup on NSS, so the slave stays deselected when no controller is connected.
+    pub nss_pull: Pull,
+}
+
+impl Default for SlaveConfig {
+    fn default() -> Self {
+        Self {
+            mode: MODE_0,
+            bit_order: BitOrder::MsbFirst,
+            nss_pull: Pull::Up,
+        }
+    }
+}
+
+/// SPI slave driver.
+///
+/// Data from the controller is received continuously into a ring buffer using circular DMA,
+/// so no bytes are lost while the application is busy. Frames are delimited by the hardware NSS
+/// (chip select) signal, whose edges are detected through EXTI.
+///
+/// Data sent to the controller is fed with DMA as the controller clocks it out, so writes apply
+/// backpressure: they only complete once the controller has fetched the data, or the frame ends.
+pub struct SpiSlave<'d> {
+    info: &'static Info,
+    sck: Option<PeripheralRef<'d, AnyPin>>,
+    mosi: Option<PeripheralRef<'d, AnyPin>>,
+    miso: Option<PeripheralRef<'d, AnyPin>>,
+    nss: Option<PeripheralRef<'d, AnyPin>>,
+    tx_dma: ChannelAndRequest<'d>,
+    ring_buf: ReadableRingBuffer<'d, u8>,
+    config: SlaveConfig,
+}
+
+impl<'d> SpiSlave<'d> {
+    /// Create a new SPI slave driver.
+    ///
+    /// `rx_buf` is the ring buffer registered with the receive DMA. It must be large enough to hold
+    /// the data received while the application isn't reading, otherwise [`Error::Overrun`] is returned.
+    pub fn new<T: Instance, N: CsPin<T>>(
+        _peri: impl Peripheral<P = T> + 'd,
+        sck: impl Peripheral<P = impl SckPin<T>> + 'd,
+        mosi: impl Peripheral<P = impl MosiPin<T>> + 'd,
+        miso: impl Peripheral<P = impl MisoPin<T>> + 'd,
+        nss: impl Peripheral<P = N> + 'd,
+        nss_exti: impl Peripheral<P = N::ExtiChannel> + 'd,
+        tx_dma: impl Peripheral<P = impl TxDma<T>> + 'd,
+        rx_dma: impl Peripheral<P = impl RxDma<T>> + 'd,
+        rx_buf: &'d mut [u8],
+        config: SlaveConfig,
+    ) -> Self {
+        assert!(!rx_buf.is_empty() && rx_buf.len() <= 0xFFFF);
+        into_ref!(nss_exti, rx_dma);
+
+        let nss = new_pin!(nss, AfType::input(config.nss_pull));
+        // Needed if using AnyPin+AnyChannel.
+        assert_eq!(nss.as_ref().unwrap()._pin(), nss_exti.number());
+
+        let info = T::info();
+        let request = rx_dma.request();
+        let ring_buf =
+            unsafe { ReadableRingBuffer::new(rx_dma, request, info.regs.rx_ptr(), rx_buf, Default::default()) };
+
+        let mut this = Self {
+            info,
+            sck: new_pin!(sck, AfType::input(Pull::None)),
+            mosi: new_pin!(mosi, AfType::input(Pull::None)),
+            miso: new_pin!(miso, AfType::output(OutputType::PushPull, Speed::VeryHigh)),
+            nss,
+            tx_dma: new_dma!(tx_dma).unwrap(),
+            ring_buf,
+            config,
+        };
+        this.enable_and_init(config);
+        this
+    }
+
+    fn enable_and_init(&mut self, config: SlaveConfig) {
+        let cpha = match config.mode.phase {
+            super::Phase::CaptureOnSecondTransition => vals::Cpha::SECONDEDGE,
+            super::Phase::CaptureOnFirstTransition => vals::Cpha::FIRSTEDGE,
+        };
+        let cpol = match config.mode.polarity {
+            super::Polarity::IdleHigh => vals::Cpol::IDLEHIGH,
+            super::Polarity::IdleLow => vals::Cpol::IDLELOW,
+        };
+        let lsbfirst = match config.bit_order {
+            BitOrder::LsbFirst => vals::Lsbfirst::LSBFIRST,
+            BitOrder::MsbFirst => vals::Lsbfirst::MSBFIRST,
+        };
+
+        self.info.rcc.enable_and_reset();
+
+        let regs = self.info.regs;
+        #[cfg(any(spi_v1, spi_f1, spi_v2))]
+        {
+            regs.cr2().modify(|w| {
+                #[cfg(spi_v2)]
+                {
+                    let (ds, frxth) = <u8 as SealedWord>::CONFIG;
+                    w.set_frxth(frxth);
+                    w.set_ds(ds);
+                }
+                w.set_ssoe(false);
+            });
+            regs.cr1().modify(|w| {
+                w.set_cpha(cpha);
+                w.set_cpol(cpol);
+
+                w.set_mstr(vals::Mstr::SLAVE);
+                w.set_lsbfirst(lsbfirst);
+                // NSS is driven by the controller.
+                w.set_ssm(false);
+                w.set_crcen(false);
+                w.set_bidimode(vals::Bidimode::UNIDIRECTIONAL);
+                w.set_rxonly(vals::Rxonly::FULLDUPLEX);
+                #[cfg(any(spi_v1, spi_f1))]
+                w.set_dff(<u8 as SealedWord>::CONFIG);
+            });
+        }
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        {
+            regs.ifcr().write(|w| w.0 = 0xffff_ffff);
+            regs.cfg2().modify(|w| {
+                w.set_ssoe(false);
+                w.set_cpha(cpha);
+                w.set_cpol(cpol);
+                w.set_lsbfirst(lsbfirst);
+                // NSS is driven by the controller.
+                w.set_ssm(false);
+                w.set_ssiop(vals::Ssiop::ACTIVELOW);
+                w.set_master(vals::Master::SLAVE);
+                w.set_comm(vals::Comm::FULLDUPLEX);
+                w.set_afcntr(true);
+            });
+            regs.cfg1().modify(|w| {
+                w.set_crcen(false);
+                w.set_dsize(<u8 as SealedWord>::CONFIG);
+                w.set_fthlv(vals::Fthlv::ONEFRAME);
+            });
+            regs.cr2().modify(|w| {
+                w.set_tsize(0);
+            });
+        }
+
+        self.start();
+    }
+
+    /// Start the background reception.
+    ///
+    /// Note: This is done automatically on creation and after an overrun.
+    fn start(&mut self) {
+        compiler_fence(Ordering::SeqCst);
+        self.ring_buf.clear();
+        self.ring_buf.start();
+
+        set_rxdmaen(self.info.regs, true);
+        self.info.regs.cr1().modify(|w| {
+            w.set_spe(true);
+        });
+    }
+
+    fn stop(&mut self) {
+        self.ring_buf.request_pause();
+        while self.ring_buf.is_running() {}
+
+        self.info.regs.cr1().modify(|w| {
+            w.set_spe(false);
+        });
+        set_rxdmaen(self.info.regs, false);
+
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn nss(&self) -> &AnyPin {
+        self.nss.as_ref().unwrap()
+    }
+
+    /// Get whether the slave is currently selected by the controller.
+    pub fn is_selected(&self) -> bool {
+        nss_is_low(self.nss())
+    }
+
+    /// Wait until the controller selects the slave by pulling NSS low.
+    ///
+    /// Returns immediately if the slave is already selected.
+    pub async fn wait_for_select(&mut self) {
+        wait_for_nss(self.nss(), false).await
+    }
+
+    /// Wait until the controller ends the current frame by releasing NSS.
+    ///
+    /// Returns immediately if the slave is not selected.
+    pub async fn wait_for_deselect(&mut self) {
+        wait_for_nss(self.nss(), true).await
+    }
+
+    /// Read bytes that are readily available in the ring buffer.
+    ///
+    /// If no bytes are available, this waits until the DMA buffer is half or completely full, or
+    /// until the controller ends the frame. Unlike [`read_frame`](Self::read_frame), this doesn't
+    /// stop at frame boundaries.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        loop {
+            let len = self.read_available(buf)?;
+            if len > 0 || buf.is_empty() {
+                return Ok(len);
+            }
+
+            self.wait_for_data_or_deselect().await;
+        }
+    }
+
+    /// Read a complete frame, from NSS falling to NSS rising.
+    ///
+    /// Waits for the controller to select the slave if it isn't already selected. Data left over in
+    /// the ring buffer from earlier frames is discarded in that case.
+    ///
+    /// Returns the number of bytes received in the frame. If the frame doesn't fit into `buf`, the
+    /// rest of the frame is discarded and [`Error::Overrun`] is returned.
+    pub async fn read_frame(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if !self.is_selected() {
+            self.ring_buf.clear();
+            self.wait_for_select().await;
+        }
+
+        let mut len = 0;
+        let mut truncated = false;
+        loop {
+            let deselected = !self.is_selected();
+            if deselected {
+                // Let the DMA pick up the last received byte.
+                wait_rx_drained(self.info);
+            }
+
+            loop {
+                let n = if len < buf.len() {
+                    self.read_available(&mut buf[len..])?
+                } else {
+                    let mut discard = [0u8; 16];
+                    let n = self.read_available(&mut discard)?;
+                    truncated |= n > 0;
+                    n
+                };
+                if n == 0 {
+                    break;
+                }
+                if !truncated {
+                    len += n;
+                }
+            }
+
+            if deselected {
+                return match truncated {
+                    true => Err(Error::Overrun),
+                    false => Ok(len),
+                };
+            }
+
+            self.wait_for_data_or_deselect().await;
+        }
+    }
+
+    /// Write bytes to be clocked out by the controller.
+    ///
+    /// The bytes are fed to the peripheral as the controller fetches them. This waits for the
+    /// controller to select the slave if needed, and completes when all bytes have been handed to
+    /// the peripheral or when the controller ends the frame early, whichever happens first.
+    ///
+    /// Since the peripheral buffers outgoing data, short writes complete right away and are clocked
+    /// out in the next frame.
+    ///
+    /// Returns the number of bytes handed to the peripheral. Bytes not fetched when the frame ends
+    /// are dropped, so the next write starts fresh at the next frame. On SPI v1 and v2, this resets
+    /// the peripheral, which also drops the received bytes that were not read yet.
+    pub async fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        let regs = self.info.regs;
+        let nss = unsafe { self.nss().clone_unchecked() };
+
+        let mut transfer = unsafe { self.tx_dma.write(data, regs.tx_ptr(), Default::default()) };
+        set_txdmaen(regs, true);
+
+        let frame_end = async {
+            wait_for_nss(&nss, false).await;
+            wait_for_nss(&nss, true).await;
+        };
+
+        let sent = match select(&mut transfer, frame_end).await {
+            Either::First(()) => {
+                drop(transfer);
+                set_txdmaen(regs, false);
+                data.len()
+            }
+            Either::Second(()) => {
+                transfer.request_stop();
+                while transfer.is_running() {}
+                let sent = data.len() - transfer.get_remaining_transfers() as usize;
+                drop(transfer);
+                set_txdmaen(regs, false);
+
+                // Flush bytes the controller did not fetch, they would otherwise leak into the next frame.
+                self.flush_tx();
+                sent
+            }
+        };
+
+        Ok(sent)
+    }
+
+    /// Drops the data waiting in the TX FIFO.
+    fn flush_tx(&mut self) {
+        // Disabling the peripheral resets the TX FIFO; the RX ring buffer keeps its contents.
+        #[cfg(any(spi_v3, spi_v4, spi_v5))]
+        {
+            let regs = self.info.regs;
+            regs.cr1().modify(|w| w.set_spe(false));
+            regs.cr1().modify(|w| w.set_spe(true));
+        }
+        // Older peripherals keep the TX buffer while disabled, only a reset clears it.
+        #[cfg(not(any(spi_v3, spi_v4, spi_v5)))]
+        {
+            self.stop();
+            // Release the clock first, `enable_and_init` only resets the peripheral when it enables it.
+            self.info.rcc.disable();
+            self.enable_and_init(self.config);
+        }
+    }
+
+    fn read_available(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let sr = self.info.regs.sr().read();
+        let result = check_error_flags(sr, true).and_then(|_| self.ring_buf.read(buf).map_err(|_| Error::Overrun));
+        match result {
+            Ok((len, _)) => Ok(len),
+            Err(e) => {
+                // Restart reception so that the next call can pick up with fresh data.
+                self.stop();
+                self.start();
+                Err(e)
+            }
+        }
+    }
+
+    async fn wait_for_data_or_deselect(&mut self) {
+        let nss = unsafe { self.nss().clone_unchecked() };
+
+        let mut dma_init = false;
+        // Future which completes when the DMA buffer is half full or full
+        let dma = poll_fn(|cx| {
+            self.ring_buf.set_waker(cx.waker());
+
+            let status = match dma_init {
+                false => Poll::Pending,
+                true => Poll::Ready(()),
+            };
+
+            dma_init = true;
+            status
+        });
+
+        select(dma, wait_for_nss(&nss, true)).await;
+    }
+}
+
+impl Drop for SpiSlave<'_> {
+    fn drop(&mut self) {
+        self.stop();
+
+        self.sck.as_ref().map(|x| x.set_as_disconnected());
+        self.mosi.as_ref().map(|x| x.set_as_disconnected());
+        self.miso.as_ref().map(|x| x.set_as_disconnected());
+        self.nss.as_ref().map(|x| x.set_as_disconnected());
+
+        self.info.rcc.disable();
+    }
+}
+
+fn nss_is_low(nss: &AnyPin) -> bool {
+    nss.block().idr().read().idr(nss._pin() as _) == crate::pac::gpio::vals::Idr::LOW
+}
+
+/// Wait for NSS to reach the given level, using the EXTI line of the pin.
+async fn wait_for_nss(nss: &AnyPin, high: bool) {
+    loop {
+        // Arm the EXTI line before checking the level, so that an edge in between isn't missed.
+        let edge = ExtiInputFuture::new(nss._pin(), nss._port(), high, !high);
+        if nss_is_low(nss) != high {
+            return;
+        }
+        edge.await;
+    }
+}
+
+fn wait_rx_drained(info: &Info) {
+    #[cfg(not(any(spi_v3, spi_v4, spi_v5)))]
+    while info.regs.sr().read().rxne() {}
+    #[cfg(any(spi_v3, spi_v4, spi_v5))]
+    while info.regs.sr().read().rxp() {}
+}
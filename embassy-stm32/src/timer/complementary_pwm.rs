@@ -1,17 +1,21 @@
 //! PWM driver with complementary output support.
 
+use core::future::poll_fn;
 use core::marker::PhantomData;
+use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{into_ref, PeripheralRef};
 use stm32_metapac::timer::vals::Ckd;
 
 use super::low_level::{CountingMode, OutputPolarity, Timer};
 use super::simple_pwm::{Ch1, Ch2, Ch3, Ch4, PwmPin};
 use super::{
-    AdvancedInstance4Channel, Channel, Channel1ComplementaryPin, Channel2ComplementaryPin, Channel3ComplementaryPin,
-    Channel4ComplementaryPin,
+    AdvancedInstance1Channel, AdvancedInstance4Channel, BreakInputInterruptHandler, BreakInputPin, Channel,
+    Channel1ComplementaryPin, Channel2ComplementaryPin, Channel3ComplementaryPin, Channel4ComplementaryPin,
 };
-use crate::gpio::{AnyPin, OutputType};
+use crate::gpio::{AfType, AnyPin, OutputType, Pull};
+use crate::interrupt::typelevel::{Binding, Interrupt};
 use crate::time::Hertz;
 use crate::timer::low_level::OutputCompareMode;
 use crate::Peripheral;
@@ -51,6 +55,56 @@ complementary_channel_impl!(new_ch2, Ch2, Channel2ComplementaryPin);
 complementary_channel_impl!(new_ch3, Ch3, Channel3ComplementaryPin);
 complementary_channel_impl!(new_ch4, Ch4, Channel4ComplementaryPin);
 
+/// Break input pin wrapper.
+pub struct BreakPin<'d, T> {
+    _pin: PeripheralRef<'d, AnyPin>,
+    phantom: PhantomData<T>,
+}
+
+impl<'d, T: AdvancedInstance4Channel> BreakPin<'d, T> {
+    /// Create a new break input pin instance.
+    pub fn new(pin: impl Peripheral<P = impl BreakInputPin<T>> + 'd, pull: Pull) -> Self {
+        into_ref!(pin);
+        critical_section::with(|_| {
+            pin.set_as_af(pin.af_num(), AfType::input(pull));
+        });
+        BreakPin {
+            _pin: pin.map_into(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Active level of the break input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BreakPolarity {
+    /// The break input is active low.
+    ActiveLow,
+    /// The break input is active high.
+    ActiveHigh,
+}
+
+/// Break input configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct BreakConfig {
+    /// Active level of the break input.
+    pub polarity: BreakPolarity,
+    /// Enable the outputs again at the next update event once the break input is inactive,
+    /// instead of waiting for [`ComplementaryPwm::clear_break`].
+    pub automatic_output_enable: bool,
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        Self {
+            polarity: BreakPolarity::ActiveLow,
+            automatic_output_enable: false,
+        }
+    }
+}
+
 /// PWM driver with support for standard and complementary outputs.
 pub struct ComplementaryPwm<'d, T: AdvancedInstance4Channel> {
     inner: Timer<'d, T>,
@@ -147,6 +201,95 @@ impl<'d, T: AdvancedInstance4Channel> ComplementaryPwm<'d, T> {
         self.inner.set_dead_time_clock_division(ckd);
         self.inner.set_dead_time_value(value);
     }
+
+    /// Enable the break input.
+    ///
+    /// When the break input becomes active, the hardware immediately drives all outputs to their
+    /// inactive level, whatever the software is doing. They stay off until
+    /// [`clear_break`](Self::clear_break) is called, or until the next update event after the
+    /// break input is released when `automatic_output_enable` is set.
+    pub fn enable_break(
+        &mut self,
+        _pin: BreakPin<'d, T>,
+        _irq: impl Binding<<T as AdvancedInstance1Channel>::BreakInputInterrupt, BreakInputInterruptHandler<T>> + 'd,
+        config: BreakConfig,
+    ) {
+        let regs = self.inner.regs_1ch_cmp();
+        regs.bdtr().modify(|w| {
+            w.set_bkp(config.polarity == BreakPolarity::ActiveHigh);
+            w.set_aoe(config.automatic_output_enable);
+            w.set_bke(true);
+        });
+        regs.sr().modify(|w| w.set_bif(false));
+
+        <T as AdvancedInstance1Channel>::BreakInputInterrupt::unpend();
+        unsafe { <T as AdvancedInstance1Channel>::BreakInputInterrupt::enable() };
+    }
+
+    /// Disable the break input.
+    pub fn disable_break(&mut self) {
+        let regs = self.inner.regs_1ch_cmp();
+        regs.bdtr().modify(|w| w.set_bke(false));
+        regs.dier().modify(|w| w.set_bie(false));
+        regs.sr().modify(|w| w.set_bif(false));
+    }
+
+    /// Returns whether a break event occurred since the last [`clear_break`](Self::clear_break).
+    pub fn is_break_triggered(&self) -> bool {
+        self.inner.regs_1ch_cmp().sr().read().bif()
+    }
+
+    /// Enable the outputs again after a break event.
+    ///
+    /// The outputs stay off as long as the break input is active.
+    pub fn clear_break(&mut self) {
+        self.inner.regs_1ch_cmp().sr().modify(|w| w.set_bif(false));
+        self.inner.set_moe(true);
+    }
+
+    /// Wait for a break event.
+    ///
+    /// Requires the break input to be enabled with [`enable_break`](Self::enable_break).
+    pub async fn wait_for_break(&mut self) {
+        let regs = self.inner.regs_1ch_cmp();
+
+        let _on_drop = OnDrop::new(|| regs.dier().modify(|w| w.set_bie(false)));
+
+        poll_fn(|cx| {
+            T::state().brk_waker.register(cx.waker());
+
+            if regs.sr().read().bif() {
+                Poll::Ready(())
+            } else {
+                regs.dier().modify(|w| w.set_bie(true));
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Apply several changes at the same update event.
+    ///
+    /// Update events are disabled while `f` runs, so the new duty cycles set by `f` are loaded
+    /// together at the next update event, instead of some of them taking effect one PWM period
+    /// before the others.
+    pub fn update_synchronized(&mut self, f: impl FnOnce(&mut Self)) {
+        self.inner.regs_core().cr1().modify(|w| w.set_udis(true));
+        f(self);
+        self.inner.regs_core().cr1().modify(|w| w.set_udis(false));
+    }
+
+    /// Preload channel enables and output modes until the next [`commutate`](Self::commutate).
+    ///
+    /// This allows switching several outputs at the same instant, as in six-step motor control.
+    pub fn set_commutation_preload(&mut self, enable: bool) {
+        self.inner.regs_advanced().cr2().modify(|w| w.set_ccpc(enable));
+    }
+
+    /// Apply the preloaded channel enables and output modes at once.
+    pub fn commutate(&mut self) {
+        self.inner.regs_advanced().egr().write(|w| w.set_comg(true));
+    }
 }
 
 impl<'d, T: AdvancedInstance4Channel> embedded_hal_02::Pwm for ComplementaryPwm<'d, T> {
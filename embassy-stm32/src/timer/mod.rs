@@ -55,6 +55,8 @@ pub enum TimerBits {
 struct State {
     up_waker: AtomicWaker,
     cc_waker: [AtomicWaker; 4],
+    #[allow(unused)]
+    brk_waker: AtomicWaker,
 }
 
 impl State {
@@ -62,6 +64,7 @@ impl State {
         Self {
             up_waker: AtomicWaker::new(),
             cc_waker: [const { AtomicWaker::new() }; 4],
+            brk_waker: AtomicWaker::new(),
         }
     }
 }
@@ -348,6 +351,31 @@ impl<T: CoreInstance> interrupt::typelevel::Handler<T::UpdateInterrupt> for Upda
     }
 }
 
+/// Break input interrupt handler.
+#[cfg(not(stm32l0))]
+pub struct BreakInputInterruptHandler<T: AdvancedInstance1Channel> {
+    _phantom: PhantomData<T>,
+}
+
+#[cfg(not(stm32l0))]
+impl<T: AdvancedInstance1Channel> interrupt::typelevel::Handler<T::BreakInputInterrupt>
+    for BreakInputInterruptHandler<T>
+{
+    unsafe fn on_interrupt() {
+        #[cfg(feature = "low-power")]
+        crate::low_power::on_wakeup_irq();
+
+        let regs = crate::pac::timer::Tim1chCmp::from_ptr(T::regs());
+
+        if regs.sr().read().bif() {
+            // Mask the break interrupt, the waiting task clears the flag.
+            regs.dier().modify(|w| w.set_bie(false));
+
+            T::state().brk_waker.wake();
+        }
+    }
+}
+
 /// Capture/Compare interrupt handler.
 pub struct CaptureCompareInterruptHandler<T: GeneralInstance1Channel> {
     _phantom: PhantomData<T>,
@@ -1,13 +1,19 @@
 //! Quadrature decoder using a timer.
 
+use core::future::poll_fn;
 use core::marker::PhantomData;
+use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{into_ref, PeripheralRef};
 use stm32_metapac::timer::vals;
 
-use super::low_level::Timer;
-use super::{Channel1Pin, Channel2Pin, GeneralInstance4Channel};
+use super::low_level::{FilterValue, Timer};
+use super::{
+    CaptureCompareInterruptHandler, Channel, Channel1Pin, Channel2Pin, GeneralInstance4Channel, UpdateInterruptHandler,
+};
 use crate::gpio::{AfType, AnyPin, Pull};
+use crate::interrupt::typelevel::{Binding, Interrupt};
 use crate::Peripheral;
 
 /// Counting direction
@@ -18,6 +24,50 @@ pub enum Direction {
     Downcounting,
 }
 
+/// Encoder mode, selecting which input edges are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EncoderMode {
+    /// Count the edges of channel 1 only, 2 counts per cycle.
+    Mode1,
+    /// Count the edges of channel 2 only, 2 counts per cycle.
+    Mode2,
+    /// Count the edges of both channels, 4 counts per cycle.
+    Mode3,
+}
+
+impl From<EncoderMode> for vals::Sms {
+    fn from(mode: EncoderMode) -> Self {
+        match mode {
+            EncoderMode::Mode1 => vals::Sms::ENCODER_MODE_1,
+            EncoderMode::Mode2 => vals::Sms::ENCODER_MODE_2,
+            EncoderMode::Mode3 => vals::Sms::ENCODER_MODE_3,
+        }
+    }
+}
+
+/// Quadrature decoder configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct Config {
+    /// Encoder mode.
+    pub mode: EncoderMode,
+    /// Digital filter applied to both inputs.
+    pub filter: FilterValue,
+    /// Invert channel 1, reversing the counting direction.
+    pub reverse: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mode: EncoderMode::Mode3,
+            filter: FilterValue::NOFILTER,
+            reverse: false,
+        }
+    }
+}
+
 /// Channel 1 marker type.
 pub enum Ch1 {}
 /// Channel 2 marker type.
@@ -52,17 +102,46 @@ channel_impl!(new_ch1, Ch1, Channel1Pin);
 channel_impl!(new_ch2, Ch2, Channel2Pin);
 
 /// Quadrature decoder driver.
+///
+/// The hardware counter is 16 bits wide. [`position`](Self::position) extends it to 64 bits
+/// in software, which requires calling it (or one of the async waits) at least once every
+/// 32767 counts.
 pub struct Qei<'d, T: GeneralInstance4Channel> {
     inner: Timer<'d, T>,
+    last_count: u16,
+    position: i64,
 }
 
 impl<'d, T: GeneralInstance4Channel> Qei<'d, T> {
     /// Create a new quadrature decoder driver.
     pub fn new(tim: impl Peripheral<P = T> + 'd, _ch1: QeiPin<'d, T, Ch1>, _ch2: QeiPin<'d, T, Ch2>) -> Self {
-        Self::new_inner(tim)
+        Self::new_inner(tim, Config::default())
     }
 
-    fn new_inner(tim: impl Peripheral<P = T> + 'd) -> Self {
+    /// Create a new quadrature decoder driver with the given configuration.
+    ///
+    /// The interrupts are used by [`wait_for_position`](Self::wait_for_position) and
+    /// [`wait_for_delta`](Self::wait_for_delta).
+    pub fn new_with_config(
+        tim: impl Peripheral<P = T> + 'd,
+        _ch1: QeiPin<'d, T, Ch1>,
+        _ch2: QeiPin<'d, T, Ch2>,
+        _irq: impl Binding<T::UpdateInterrupt, UpdateInterruptHandler<T>>
+            + Binding<T::CaptureCompareInterrupt, CaptureCompareInterruptHandler<T>>
+            + 'd,
+        config: Config,
+    ) -> Self {
+        let this = Self::new_inner(tim, config);
+
+        T::UpdateInterrupt::unpend();
+        unsafe { T::UpdateInterrupt::enable() };
+        T::CaptureCompareInterrupt::unpend();
+        unsafe { T::CaptureCompareInterrupt::enable() };
+
+        this
+    }
+
+    fn new_inner(tim: impl Peripheral<P = T> + 'd, config: Config) -> Self {
         let inner = Timer::new(tim);
         let r = inner.regs_gp16();
 
@@ -77,18 +156,27 @@ impl<'d, T: GeneralInstance4Channel> Qei<'d, T> {
             w.set_cce(0, true);
             w.set_cce(1, true);
 
-            w.set_ccp(0, false);
+            w.set_ccp(0, config.reverse);
             w.set_ccp(1, false);
         });
 
+        inner.set_input_capture_filter(Channel::Ch1, config.filter);
+        inner.set_input_capture_filter(Channel::Ch2, config.filter);
+
         r.smcr().modify(|w| {
-            w.set_sms(vals::Sms::ENCODER_MODE_3);
+            w.set_sms(config.mode.into());
         });
 
         r.arr().modify(|w| w.set_arr(u16::MAX));
         r.cr1().modify(|w| w.set_cen(true));
 
-        Self { inner }
+        let last_count = r.cnt().read().cnt();
+
+        Self {
+            inner,
+            last_count,
+            position: 0,
+        }
     }
 
     /// Get direction.
@@ -103,4 +191,168 @@ impl<'d, T: GeneralInstance4Channel> Qei<'d, T> {
     pub fn count(&self) -> u16 {
         self.inner.regs_gp16().cnt().read().cnt()
     }
+
+    /// Get the position, extended to 64 bits.
+    pub fn position(&mut self) -> i64 {
+        let count = self.count();
+        self.position = extend_position(self.position, self.last_count, count);
+        self.last_count = count;
+        self.position
+    }
+
+    /// Set the current position.
+    pub fn set_position(&mut self, position: i64) {
+        self.last_count = self.count();
+        self.position = position;
+    }
+
+    /// Wait for the position to reach `target`, and return the current position.
+    ///
+    /// Requires the driver to be created with [`new_with_config`](Self::new_with_config).
+    pub async fn wait_for_position(&mut self, target: i64) -> i64 {
+        self.wait_until(target, target, |prev, pos| {
+            (prev <= target && target <= pos) || (pos <= target && target <= prev)
+        })
+        .await
+    }
+
+    /// Wait for the position to move by at least `delta` counts, in either direction, and return
+    /// the current position.
+    ///
+    /// Requires the driver to be created with [`new_with_config`](Self::new_with_config).
+    pub async fn wait_for_delta(&mut self, delta: u32) -> i64 {
+        let start = self.position();
+        let delta = delta as i64;
+        self.wait_until(start + delta, start - delta, |_, pos| (pos - start).abs() >= delta)
+            .await
+    }
+
+    /// Wait until `done` returns true, checking whenever the position reaches `up` or `down`.
+    ///
+    /// Far away targets are approached through waypoints, so that the counter never moves by more
+    /// than [`MAX_WAYPOINT_DISTANCE`] between two samples of the position.
+    async fn wait_until(&mut self, up: i64, down: i64, mut done: impl FnMut(i64, i64) -> bool) -> i64 {
+        assert!(
+            T::UpdateInterrupt::is_enabled() && T::CaptureCompareInterrupt::is_enabled(),
+            "Timer interrupts not enabled"
+        );
+
+        let r = self.inner.regs_gp16();
+
+        let _on_drop = OnDrop::new(|| {
+            r.dier().modify(|w| {
+                w.set_uie(false);
+                w.set_ccie(2, false);
+                w.set_ccie(3, false);
+            });
+        });
+
+        poll_fn(|cx| {
+            T::state().up_waker.register(cx.waker());
+            T::state().cc_waker[2].register(cx.waker());
+            T::state().cc_waker[3].register(cx.waker());
+
+            // Clear the flags before checking, so that no event is missed
+            r.sr().modify(|w| {
+                w.set_uif(false);
+                w.set_ccif(2, false);
+                w.set_ccif(3, false);
+            });
+
+            let prev = self.position;
+            let pos = self.position();
+            if done(prev, pos) {
+                Poll::Ready(pos)
+            } else {
+                // Channels 3 and 4 are free, use their compare events to wake up at the next
+                // waypoint in each direction.
+                let count = self.last_count;
+                let next_up = count.wrapping_add((waypoint_up(pos, up) - pos) as u16);
+                let next_down = count.wrapping_sub((pos - waypoint_down(pos, down)) as u16);
+                self.inner.set_compare_value(Channel::Ch3, next_up as u32);
+                self.inner.set_compare_value(Channel::Ch4, next_down as u32);
+
+                r.dier().modify(|w| {
+                    w.set_uie(true);
+                    w.set_ccie(2, true);
+                    w.set_ccie(3, true);
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Largest distance between the position and a compare event, well below the half range of the
+/// 16-bit counter which [`extend_position`] can track.
+const MAX_WAYPOINT_DISTANCE: i64 = 16384;
+
+/// Extends a 16-bit counter value to 64 bits, assuming it moved by less than half of its range
+/// since `last_count`.
+fn extend_position(position: i64, last_count: u16, count: u16) -> i64 {
+    position + count.wrapping_sub(last_count) as i16 as i64
+}
+
+/// Next position to wake up at when moving up from `pos` towards `target`.
+fn waypoint_up(pos: i64, target: i64) -> i64 {
+    if target > pos {
+        target.min(pos + MAX_WAYPOINT_DISTANCE)
+    } else {
+        pos + MAX_WAYPOINT_DISTANCE
+    }
+}
+
+/// Next position to wake up at when moving down from `pos` towards `target`.
+fn waypoint_down(pos: i64, target: i64) -> i64 {
+    if target < pos {
+        target.max(pos - MAX_WAYPOINT_DISTANCE)
+    } else {
+        pos - MAX_WAYPOINT_DISTANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extend_position, waypoint_down, waypoint_up, MAX_WAYPOINT_DISTANCE};
+
+    #[test]
+    fn test_extend_position() {
+        assert_eq!(extend_position(0, 0, 100), 100);
+        assert_eq!(extend_position(100, 100, 40), 40);
+        // Wrapping forwards and backwards
+        assert_eq!(extend_position(65530, 65530, 4), 65540);
+        assert_eq!(extend_position(0, 0, 65535), -1);
+        assert_eq!(extend_position(-70000, 61072, 61072u16.wrapping_add(32767)), -70000 + 32767);
+        assert_eq!(
+            extend_position(i64::from(u32::MAX) * 4, 1, 0),
+            i64::from(u32::MAX) * 4 - 1
+        );
+    }
+
+    #[test]
+    fn test_waypoints_stay_trackable() {
+        for (pos, target) in [(0, 100_000), (0, -100_000), (5, 6), (1_000_000, 999_000), (-3, -3)] {
+            let up = waypoint_up(pos, target);
+            let down = waypoint_down(pos, target);
+            assert!(up > pos && up - pos <= MAX_WAYPOINT_DISTANCE);
+            assert!(down < pos && pos - down <= MAX_WAYPOINT_DISTANCE);
+        }
+
+        assert_eq!(waypoint_up(0, 100), 100);
+        assert_eq!(waypoint_down(0, -100), -100);
+        assert_eq!(waypoint_up(0, 100_000), MAX_WAYPOINT_DISTANCE);
+
+        // Following the waypoints, the position sampled at each compare event is exact, even
+        // though the target is far beyond the range of the counter.
+        let target = 200_000;
+        let (mut pos, mut count) = (0i64, 0u16);
+        while pos != target {
+            let next = waypoint_up(pos, target);
+            let next_count = count.wrapping_add((next - pos) as u16);
+            pos = extend_position(pos, count, next_count);
+            count = next_count;
+            assert_eq!(pos, next);
+        }
+    }
 }
@@ -30,6 +30,9 @@ use crate::pac::ucpd::vals::{Anamode, Ccenable, PscUsbpdclk, Txmode};
 pub use crate::pac::ucpd::vals::{Phyccsel as CcSel, Rxordset, TypecVstateCc as CcVState};
 use crate::rcc::{self, RccPeripheral};
 
+#[cfg(feature = "time")]
+pub mod sink;
+
 pub(crate) fn init(
     _cs: critical_section::CriticalSection,
     #[cfg(peri_ucpd1)] ucpd1_db_enable: bool,
@@ -0,0 +1,808 @@
+//! USB Power Delivery sink policy engine.
+//!
+//! [`Sink`] runs the protocol and policy layers of a USB PD sink on top of a [`PdPhy`]: it
+//! acknowledges messages with GoodCRC, evaluates the source capabilities, negotiates a contract
+//! for the requested [`PowerRequest`], keeps Programmable Power Supply (PPS) contracts alive and
+//! reports source alerts.
+//!
+//! GoodCRC messages are sent by software, so the task running the sink should not be delayed by
+//! other work for more than a few hundred microseconds.
+
+use embassy_time::{with_deadline, with_timeout, Duration, Instant, Timer};
+
+use super::{Instance, PdPhy, RxError, TxError};
+
+/// Maximum length of a non-extended message, or of a single extended message chunk.
+const MAX_MESSAGE_LEN: usize = 30;
+/// Maximum number of data objects in a message.
+const MAX_DATA_OBJECTS: usize = 7;
+
+const SPEC_REV_3_0: u8 = 0b10;
+
+/// Number of retransmissions when no GoodCRC is received.
+const N_RETRY_COUNT: usize = 2;
+/// Number of hard resets sent before the source is considered not PD capable.
+const N_HARD_RESET_COUNT: u8 = 2;
+
+const T_RECEIVE: Duration = Duration::from_micros(1100);
+const T_SENDER_RESPONSE: Duration = Duration::from_millis(30);
+const T_PS_TRANSITION: Duration = Duration::from_millis(550);
+const T_SINK_REQUEST: Duration = Duration::from_millis(100);
+// tTypeCSinkWaitCap, plus the time a source needs to restore VBUS after a hard reset.
+const T_SINK_WAIT_CAP: Duration = Duration::from_millis(620 + 1275);
+// PPS sources hard reset if they don't get a request at least every 10 s.
+const T_PPS_REQUEST: Duration = Duration::from_secs(5);
+
+mod control {
+    pub const GOOD_CRC: u8 = 0x01;
+    pub const ACCEPT: u8 = 0x03;
+    pub const REJECT: u8 = 0x04;
+    pub const PING: u8 = 0x05;
+    pub const PS_RDY: u8 = 0x06;
+    pub const WAIT: u8 = 0x0C;
+    pub const SOFT_RESET: u8 = 0x0D;
+    pub const NOT_SUPPORTED: u8 = 0x10;
+    pub const GET_STATUS: u8 = 0x12;
+}
+
+mod data {
+    pub const SOURCE_CAPABILITIES: u8 = 0x01;
+    pub const REQUEST: u8 = 0x02;
+    pub const ALERT: u8 = 0x06;
+}
+
+mod extended {
+    pub const STATUS: u8 = 0x02;
+}
+
+/// Message header.
+#[derive(Clone, Copy)]
+struct Header(u16);
+
+impl Header {
+    fn new(spec_rev: u8, message_type: u8, num_objects: usize, message_id: u8) -> Self {
+        // Port power role and port data role are both 0 for a sink (UFP).
+        Self(
+            (message_type as u16 & 0x1f)
+                | ((spec_rev as u16 & 0b11) << 6)
+                | ((message_id as u16 & 0b111) << 9)
+                | ((num_objects as u16 & 0b111) << 12),
+        )
+    }
+
+    fn message_type(&self) -> u8 {
+        (self.0 & 0x1f) as u8
+    }
+
+    fn spec_rev(&self) -> u8 {
+        ((self.0 >> 6) & 0b11) as u8
+    }
+
+    fn message_id(&self) -> u8 {
+        ((self.0 >> 9) & 0b111) as u8
+    }
+
+    fn num_objects(&self) -> usize {
+        ((self.0 >> 12) & 0b111) as usize
+    }
+
+    fn extended(&self) -> bool {
+        self.0 & (1 << 15) != 0
+    }
+
+    fn is_control(&self, message_type: u8) -> bool {
+        !self.extended() && self.num_objects() == 0 && self.message_type() == message_type
+    }
+
+    fn is_data(&self, message_type: u8) -> bool {
+        !self.extended() && self.num_objects() != 0 && self.message_type() == message_type
+    }
+
+    fn is_extended(&self, message_type: u8) -> bool {
+        self.extended() && self.message_type() == message_type
+    }
+}
+
+/// Power Data Object (PDO) advertised by a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerDataObject {
+    /// Fixed voltage supply.
+    Fixed {
+        /// Voltage in mV.
+        voltage_mv: u32,
+        /// Maximum current in mA.
+        max_current_ma: u32,
+    },
+    /// Battery supply.
+    Battery {
+        /// Minimum voltage in mV.
+        min_voltage_mv: u32,
+        /// Maximum voltage in mV.
+        max_voltage_mv: u32,
+        /// Maximum power in mW.
+        max_power_mw: u32,
+    },
+    /// Variable (non-battery) supply.
+    Variable {
+        /// Minimum voltage in mV.
+        min_voltage_mv: u32,
+        /// Maximum voltage in mV.
+        max_voltage_mv: u32,
+        /// Maximum current in mA.
+        max_current_ma: u32,
+    },
+    /// Programmable Power Supply (PPS).
+    Pps {
+        /// Minimum voltage in mV.
+        min_voltage_mv: u32,
+        /// Maximum voltage in mV.
+        max_voltage_mv: u32,
+        /// Maximum current in mA.
+        max_current_ma: u32,
+    },
+    /// Unsupported augmented PDO, holding the raw value.
+    Unknown(u32),
+}
+
+impl PowerDataObject {
+    /// Decodes a raw PDO.
+    pub fn from_raw(raw: u32) -> Self {
+        let bits = |shift: u32, width: u32| (raw >> shift) & ((1 << width) - 1);
+        match raw >> 30 {
+            0b00 => Self::Fixed {
+                voltage_mv: bits(10, 10) * 50,
+                max_current_ma: bits(0, 10) * 10,
+            },
+            0b01 => Self::Battery {
+                min_voltage_mv: bits(10, 10) * 50,
+                max_voltage_mv: bits(20, 10) * 50,
+                max_power_mw: bits(0, 10) * 250,
+            },
+            0b10 => Self::Variable {
+                min_voltage_mv: bits(10, 10) * 50,
+                max_voltage_mv: bits(20, 10) * 50,
+                max_current_ma: bits(0, 10) * 10,
+            },
+            _ if bits(28, 2) == 0b00 => Self::Pps {
+                min_voltage_mv: bits(8, 8) * 100,
+                max_voltage_mv: bits(17, 8) * 100,
+                max_current_ma: bits(0, 7) * 50,
+            },
+            _ => Self::Unknown(raw),
+        }
+    }
+}
+
+/// Capabilities advertised by a source.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceCapabilities {
+    pdos: [u32; MAX_DATA_OBJECTS],
+    len: usize,
+}
+
+impl SourceCapabilities {
+    /// Returns the number of PDOs.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no capabilities were received yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the raw PDOs.
+    pub fn raw(&self) -> &[u32] {
+        &self.pdos[..self.len]
+    }
+
+    /// Returns an iterator over the decoded PDOs.
+    pub fn iter(&self) -> impl Iterator<Item = PowerDataObject> + '_ {
+        self.raw().iter().map(|&raw| PowerDataObject::from_raw(raw))
+    }
+}
+
+/// Power requested by the sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerRequest {
+    /// A fixed supply with this voltage, able to deliver this current.
+    Fixed {
+        /// Voltage in mV.
+        voltage_mv: u32,
+        /// Operating current in mA.
+        current_ma: u32,
+    },
+    /// A Programmable Power Supply set to this voltage, able to deliver this current.
+    ///
+    /// The voltage is rounded down to the 20 mV resolution of PPS.
+    Pps {
+        /// Voltage in mV.
+        voltage_mv: u32,
+        /// Operating current in mA.
+        current_ma: u32,
+    },
+}
+
+/// Explicit power contract with the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Contract {
+    /// Position of the selected PDO in the source capabilities, starting at 1.
+    pub position: u8,
+    /// Negotiated voltage in mV.
+    pub voltage_mv: u32,
+    /// Negotiated operating current in mA.
+    pub current_ma: u32,
+    /// Whether the contract is for a Programmable Power Supply.
+    pub pps: bool,
+    /// The requested power is not available, 5 V was negotiated instead.
+    pub capability_mismatch: bool,
+}
+
+/// Status reported by a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Status {
+    /// Internal temperature in °C, or 0 if not supported.
+    pub internal_temperature: u8,
+    /// Present input flags.
+    pub present_input: u8,
+    /// Present battery input flags.
+    pub present_battery_input: u8,
+    /// Event flags (OCP, OTP, OVP, CF mode).
+    pub event_flags: u8,
+    /// Temperature status.
+    pub temperature_status: u8,
+    /// Power status.
+    pub power_status: u8,
+}
+
+impl Status {
+    fn from_bytes(sdb: &[u8]) -> Self {
+        let byte = |i: usize| sdb.get(i).copied().unwrap_or(0);
+        Self {
+            internal_temperature: byte(0),
+            present_input: byte(1),
+            present_battery_input: byte(2),
+            event_flags: byte(3),
+            temperature_status: byte(4),
+            power_status: byte(5),
+        }
+    }
+}
+
+/// Event reported by [`Sink::run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A new explicit contract is in place.
+    Contract(Contract),
+    /// The source rejected the request, the previous contract stays in place.
+    Rejected,
+    /// The source sent an alert.
+    Alert {
+        /// Raw alert data object.
+        alert: u32,
+        /// Status of the source, if it answered the status request.
+        status: Option<Status>,
+    },
+    /// A hard reset occurred, the negotiation starts over.
+    HardReset,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    WaitCapabilities,
+    SelectCapability,
+    Ready,
+    SoftReset,
+    HardReset,
+}
+
+enum Error {
+    /// A hard reset was received.
+    HardResetReceived,
+    /// A message was not acknowledged or was unexpected.
+    SoftResetNeeded,
+    /// The source did not respond in time or violated the protocol.
+    HardResetNeeded,
+}
+
+/// USB PD sink policy engine.
+pub struct Sink<'d, T: Instance> {
+    phy: PdPhy<'d, T>,
+    request: PowerRequest,
+    renegotiate: bool,
+    state: State,
+    capabilities: SourceCapabilities,
+    contract: Option<Contract>,
+    pps_deadline: Instant,
+    spec_rev: u8,
+    tx_message_id: u8,
+    rx_message_id: Option<u8>,
+    hard_reset_count: u8,
+    rx_buf: [u8; MAX_MESSAGE_LEN],
+    rx_len: usize,
+    /// Message received while waiting for a status, handled by the next call to `ready`.
+    deferred: Option<Header>,
+}
+
+impl<'d, T: Instance> Sink<'d, T> {
+    /// Creates a sink that negotiates `request` with the attached source.
+    ///
+    /// The PHY must be set up on the CC line of an attached source.
+    pub fn new(phy: PdPhy<'d, T>, request: PowerRequest) -> Self {
+        Self {
+            phy,
+            request,
+            renegotiate: false,
+            state: State::WaitCapabilities,
+            capabilities: SourceCapabilities::default(),
+            contract: None,
+            pps_deadline: Instant::MAX,
+            spec_rev: SPEC_REV_3_0,
+            tx_message_id: 0,
+            rx_message_id: None,
+            hard_reset_count: 0,
+            rx_buf: [0; MAX_MESSAGE_LEN],
+            rx_len: 0,
+            deferred: None,
+        }
+    }
+
+    /// Changes the requested power.
+    ///
+    /// If a contract is in place, it is re-negotiated by the next call to [`Sink::run`].
+    pub fn set_request(&mut self, request: PowerRequest) {
+        self.request = request;
+        self.renegotiate = true;
+    }
+
+    /// Returns the current contract, if any.
+    pub fn contract(&self) -> Option<Contract> {
+        self.contract
+    }
+
+    /// Returns the last capabilities advertised by the source.
+    pub fn source_capabilities(&self) -> &SourceCapabilities {
+        &self.capabilities
+    }
+
+    /// Runs the policy engine until the next event.
+    ///
+    /// This must be called continuously while the source is attached, so messages are
+    /// acknowledged and PPS contracts are kept alive. Dropping the future in the middle of a
+    /// negotiation is recovered from with a soft or hard reset.
+    pub async fn run(&mut self) -> Event {
+        loop {
+            let result = match self.state {
+                State::WaitCapabilities => self.wait_capabilities().await,
+                State::SelectCapability => self.select_capability().await,
+                State::Ready => self.ready().await,
+                State::SoftReset => self.soft_reset().await,
+                State::HardReset => self.hard_reset().await,
+            };
+
+            match result {
+                Ok(Some(event)) => return event,
+                Ok(None) => {}
+                Err(Error::HardResetReceived) => {
+                    trace!("ucpd sink: hard reset received");
+                    self.reset();
+                    self.state = State::WaitCapabilities;
+                    return Event::HardReset;
+                }
+                Err(Error::SoftResetNeeded) => self.state = State::SoftReset,
+                Err(Error::HardResetNeeded) => self.state = State::HardReset,
+            }
+        }
+    }
+
+    async fn wait_capabilities(&mut self) -> Result<Option<Event>, Error> {
+        let header = if self.hard_reset_count < N_HARD_RESET_COUNT {
+            self.receive_timeout(T_SINK_WAIT_CAP)
+                .await?
+                .ok_or(Error::HardResetNeeded)?
+        } else {
+            // The source does not seem to be PD capable, keep listening in case it is.
+            self.receive().await?
+        };
+
+        if header.is_data(data::SOURCE_CAPABILITIES) {
+            self.store_capabilities(header);
+            self.state = State::SelectCapability;
+        } else if header.is_control(control::SOFT_RESET) {
+            self.send(control::ACCEPT, &[]).await?;
+        }
+
+        Ok(None)
+    }
+
+    async fn select_capability(&mut self) -> Result<Option<Event>, Error> {
+        self.renegotiate = false;
+        let (rdo, contract) = self.evaluate_capabilities();
+        trace!("ucpd sink: request {:08x}", rdo);
+        self.send(data::REQUEST, &[rdo]).await?;
+
+        let header = self
+            .receive_timeout(T_SENDER_RESPONSE)
+            .await?
+            .ok_or(Error::HardResetNeeded)?;
+        if header.is_control(control::ACCEPT) {
+            let header = self
+                .receive_timeout(T_PS_TRANSITION)
+                .await?
+                .ok_or(Error::HardResetNeeded)?;
+            if !header.is_control(control::PS_RDY) {
+                return Err(Error::HardResetNeeded);
+            }
+
+            self.contract = Some(contract);
+            self.hard_reset_count = 0;
+            self.pps_deadline = Instant::now() + T_PPS_REQUEST;
+            self.state = State::Ready;
+            Ok(Some(Event::Contract(contract)))
+        } else if header.is_control(control::WAIT) {
+            // Try again later, the source may be busy.
+            Timer::after(T_SINK_REQUEST).await;
+            Ok(None)
+        } else if header.is_control(control::REJECT) {
+            self.state = match self.contract {
+                Some(_) => State::Ready,
+                None => State::WaitCapabilities,
+            };
+            Ok(Some(Event::Rejected))
+        } else if header.is_control(control::SOFT_RESET) {
+            self.send(control::ACCEPT, &[]).await?;
+            self.state = State::WaitCapabilities;
+            Ok(None)
+        } else {
+            Err(Error::SoftResetNeeded)
+        }
+    }
+
+    async fn ready(&mut self) -> Result<Option<Event>, Error> {
+        // Its content is still in the receive buffer.
+        if let Some(header) = self.deferred.take() {
+            return self.handle_message(header).await;
+        }
+
+        if self.renegotiate {
+            self.state = State::SelectCapability;
+            return Ok(None);
+        }
+
+        let deadline = match self.contract {
+            Some(contract) if contract.pps => self.pps_deadline,
+            _ => Instant::MAX,
+        };
+        let Ok(header) = with_deadline(deadline, self.receive()).await else {
+            // Refresh the PPS contract.
+            self.state = State::SelectCapability;
+            return Ok(None);
+        };
+        let header = header?;
+        self.handle_message(header).await
+    }
+
+    /// Handles a message received while a contract is in place.
+    async fn handle_message(&mut self, header: Header) -> Result<Option<Event>, Error> {
+        if header.is_data(data::SOURCE_CAPABILITIES) {
+            self.store_capabilities(header);
+            self.state = State::SelectCapability;
+        } else if header.is_data(data::ALERT) {
+            let alert = self.data_objects().next().unwrap_or(0);
+            self.send(control::GET_STATUS, &[]).await?;
+            let status = match self.receive_timeout(T_SENDER_RESPONSE).await? {
+                // Skip the message header and the extended message header.
+                Some(header) if header.is_extended(extended::STATUS) => {
+                    Some(Status::from_bytes(self.rx_buf.get(4..self.rx_len).unwrap_or(&[])))
+                }
+                // The source sent something else first, handle it once the alert is reported.
+                Some(header) => {
+                    self.deferred = Some(header);
+                    None
+                }
+                None => None,
+            };
+            return Ok(Some(Event::Alert { alert, status }));
+        } else if header.is_control(control::SOFT_RESET) {
+            self.send(control::ACCEPT, &[]).await?;
+            self.state = State::WaitCapabilities;
+        } else if header.is_control(control::PING) {
+            // Nothing to do.
+        } else if header.is_control(control::ACCEPT)
+            || header.is_control(control::REJECT)
+            || header.is_control(control::WAIT)
+            || header.is_control(control::PS_RDY)
+        {
+            return Err(Error::SoftResetNeeded);
+        } else if self.spec_rev >= SPEC_REV_3_0 {
+            self.send(control::NOT_SUPPORTED, &[]).await?;
+        } else {
+            self.send(control::REJECT, &[]).await?;
+        }
+
+        Ok(None)
+    }
+
+    async fn soft_reset(&mut self) -> Result<Option<Event>, Error> {
+        trace!("ucpd sink: soft reset");
+        self.tx_message_id = 0;
+        self.rx_message_id = None;
+        self.deferred = None;
+
+        match self.send(control::SOFT_RESET, &[]).await {
+            Ok(()) => {}
+            Err(Error::HardResetReceived) => return Err(Error::HardResetReceived),
+            Err(_) => return Err(Error::HardResetNeeded),
+        }
+        match self.receive_timeout(T_SENDER_RESPONSE).await? {
+            Some(header) if header.is_control(control::ACCEPT) => {
+                self.state = State::WaitCapabilities;
+                Ok(None)
+            }
+            _ => Err(Error::HardResetNeeded),
+        }
+    }
+
+    async fn hard_reset(&mut self) -> Result<Option<Event>, Error> {
+        trace!("ucpd sink: sending hard reset");
+        self.hard_reset_count += 1;
+        // A hard reset received in the meantime has the same effect.
+        let _ = self.phy.transmit_hardreset().await;
+        self.reset();
+        self.state = State::WaitCapabilities;
+        Ok(Some(Event::HardReset))
+    }
+
+    fn reset(&mut self) {
+        self.tx_message_id = 0;
+        self.rx_message_id = None;
+        self.deferred = None;
+        self.contract = None;
+        self.spec_rev = SPEC_REV_3_0;
+    }
+
+    fn store_capabilities(&mut self, header: Header) {
+        self.spec_rev = header.spec_rev().min(SPEC_REV_3_0);
+
+        let mut capabilities = SourceCapabilities::default();
+        for (pdo, raw) in capabilities.pdos.iter_mut().zip(self.data_objects()) {
+            *pdo = raw;
+            capabilities.len += 1;
+        }
+        self.capabilities = capabilities;
+    }
+
+    /// Selects the PDO matching the request, returning the request data object and the resulting contract.
+    fn evaluate_capabilities(&self) -> (u32, Contract) {
+        let selected = self
+            .capabilities
+            .iter()
+            .zip(1..)
+            .find_map(|(pdo, position)| match (self.request, pdo) {
+                (
+                    PowerRequest::Fixed { voltage_mv, current_ma },
+                    PowerDataObject::Fixed {
+                        voltage_mv: pdo_voltage_mv,
+                        max_current_ma,
+                    },
+                ) if voltage_mv == pdo_voltage_mv && current_ma <= max_current_ma => {
+                    Some(fixed_request(position, voltage_mv, current_ma, false))
+                }
+                (
+                    PowerRequest::Pps { voltage_mv, current_ma },
+                    PowerDataObject::Pps {
+                        min_voltage_mv,
+                        max_voltage_mv,
+                        max_current_ma,
+                    },
+                ) if self.spec_rev >= SPEC_REV_3_0
+                    && (min_voltage_mv..=max_voltage_mv).contains(&voltage_mv)
+                    && current_ma <= max_current_ma =>
+                {
+                    Some(pps_request(position, voltage_mv, current_ma))
+                }
+                _ => None,
+            });
+
+        selected.unwrap_or_else(|| {
+            // The first PDO is always vSafe5V.
+            let current_ma = match (self.request, self.capabilities.iter().next()) {
+                (
+                    PowerRequest::Fixed { current_ma, .. } | PowerRequest::Pps { current_ma, .. },
+                    Some(PowerDataObject::Fixed { max_current_ma, .. }),
+                ) => current_ma.min(max_current_ma),
+                _ => 100,
+            };
+            fixed_request(1, 5000, current_ma, true)
+        })
+    }
+
+    fn data_objects(&self) -> impl Iterator<Item = u32> + '_ {
+        self.rx_buf[2..self.rx_len.max(2)]
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+    }
+
+    /// Receives the next message, acknowledging it with GoodCRC.
+    async fn receive(&mut self) -> Result<Header, Error> {
+        loop {
+            let len = match self.phy.receive(&mut self.rx_buf).await {
+                Ok(len) if len >= 2 => len,
+                Err(RxError::HardReset) => return Err(Error::HardResetReceived),
+                // Corrupted messages are not acknowledged, the sender retries them.
+                _ => continue,
+            };
+
+            let header = Header(u16::from_le_bytes([self.rx_buf[0], self.rx_buf[1]]));
+            if header.is_control(control::GOOD_CRC) {
+                continue;
+            }
+
+            let good_crc = Header::new(self.spec_rev, control::GOOD_CRC, 0, header.message_id());
+            if let Err(TxError::HardReset) = self.phy.transmit(&good_crc.0.to_le_bytes()).await {
+                return Err(Error::HardResetReceived);
+            }
+
+            if header.is_control(control::SOFT_RESET) {
+                self.tx_message_id = 0;
+            } else if self.rx_message_id == Some(header.message_id()) {
+                // Retransmission of a message that was already received.
+                continue;
+            }
+            self.rx_message_id = Some(header.message_id());
+            self.rx_len = len;
+            return Ok(header);
+        }
+    }
+
+    /// Receives the next message, or returns `None` after `timeout`.
+    async fn receive_timeout(&mut self, timeout: Duration) -> Result<Option<Header>, Error> {
+        match with_timeout(timeout, self.receive()).await {
+            Ok(header) => header.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Sends a control (no data objects) or data message, retrying until it is acknowledged.
+    async fn send(&mut self, message_type: u8, objects: &[u32]) -> Result<(), Error> {
+        let mut buf = [0; MAX_MESSAGE_LEN];
+        let header = Header::new(self.spec_rev, message_type, objects.len(), self.tx_message_id);
+        buf[..2].copy_from_slice(&header.0.to_le_bytes());
+        for (chunk, object) in buf[2..].chunks_exact_mut(4).zip(objects) {
+            chunk.copy_from_slice(&object.to_le_bytes());
+        }
+        let len = 2 + 4 * objects.len();
+
+        for _ in 0..=N_RETRY_COUNT {
+            match self.phy.transmit(&buf[..len]).await {
+                Ok(()) => {}
+                Err(TxError::Discarded) => continue,
+                Err(TxError::HardReset) => return Err(Error::HardResetReceived),
+            }
+
+            let mut rx_buf = [0; MAX_MESSAGE_LEN];
+            match with_timeout(T_RECEIVE, self.phy.receive(&mut rx_buf)).await {
+                Ok(Ok(2)) => {
+                    let ack = Header(u16::from_le_bytes([rx_buf[0], rx_buf[1]]));
+                    if ack.is_control(control::GOOD_CRC) && ack.message_id() == self.tx_message_id {
+                        self.tx_message_id = (self.tx_message_id + 1) & 0b111;
+                        return Ok(());
+                    }
+                }
+                Ok(Err(RxError::HardReset)) => return Err(Error::HardResetReceived),
+                _ => {}
+            }
+        }
+
+        self.tx_message_id = (self.tx_message_id + 1) & 0b111;
+        Err(Error::SoftResetNeeded)
+    }
+}
+
+fn fixed_request(position: u8, voltage_mv: u32, current_ma: u32, capability_mismatch: bool) -> (u32, Contract) {
+    let current = (current_ma / 10).min(0x3ff);
+    let rdo = ((position as u32) << 28) | ((capability_mismatch as u32) << 26) | (current << 10) | current;
+    let contract = Contract {
+        position,
+        voltage_mv,
+        current_ma: current * 10,
+        pps: false,
+        capability_mismatch,
+    };
+    (rdo, contract)
+}
+
+fn pps_request(position: u8, voltage_mv: u32, current_ma: u32) -> (u32, Contract) {
+    let voltage = (voltage_mv / 20).min(0xfff);
+    let current = (current_ma / 50).min(0x7f);
+    let rdo = ((position as u32) << 28) | (voltage << 9) | current;
+    let contract = Contract {
+        position,
+        voltage_mv: voltage * 20,
+        current_ma: current * 50,
+        pps: true,
+        capability_mismatch: false,
+    };
+    (rdo, contract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixed_request, pps_request, PowerDataObject};
+
+    #[test]
+    fn test_pdo_from_raw() {
+        // 5 V 3 A, with the dual role and USB communications flags set
+        assert_eq!(
+            PowerDataObject::from_raw(0x2601_912C),
+            PowerDataObject::Fixed {
+                voltage_mv: 5000,
+                max_current_ma: 3000
+            }
+        );
+        assert_eq!(
+            PowerDataObject::from_raw((0b01 << 30) | (240 << 20) | (100 << 10) | 240),
+            PowerDataObject::Battery {
+                min_voltage_mv: 5000,
+                max_voltage_mv: 12000,
+                max_power_mw: 60000
+            }
+        );
+        assert_eq!(
+            PowerDataObject::from_raw((0b10 << 30) | (240 << 20) | (100 << 10) | 200),
+            PowerDataObject::Variable {
+                min_voltage_mv: 5000,
+                max_voltage_mv: 12000,
+                max_current_ma: 2000
+            }
+        );
+        // PPS 3.3 V to 11 V, 3 A
+        assert_eq!(
+            PowerDataObject::from_raw((0b11 << 30) | (110 << 17) | (33 << 8) | 60),
+            PowerDataObject::Pps {
+                min_voltage_mv: 3300,
+                max_voltage_mv: 11000,
+                max_current_ma: 3000
+            }
+        );
+        // EPR adjustable voltage supply
+        let avs = (0b11 << 30) | (0b01 << 28) | 0x1234;
+        assert_eq!(PowerDataObject::from_raw(avs), PowerDataObject::Unknown(avs));
+    }
+
+    #[test]
+    fn test_fixed_request() {
+        let (rdo, contract) = fixed_request(2, 9000, 2000, false);
+        assert_eq!(rdo, (2 << 28) | (200 << 10) | 200);
+        assert_eq!(contract.position, 2);
+        assert_eq!(contract.voltage_mv, 9000);
+        assert_eq!(contract.current_ma, 2000);
+        assert!(!contract.pps);
+
+        // Capability mismatch, with the current saturated to 10 bits
+        let (rdo, contract) = fixed_request(1, 5000, 20000, true);
+        assert_eq!(rdo, (1 << 28) | (1 << 26) | (0x3ff << 10) | 0x3ff);
+        assert_eq!(contract.current_ma, 10230);
+        assert!(contract.capability_mismatch);
+
+        // Rounded down to 10 mA steps
+        let (_, contract) = fixed_request(1, 5000, 1505, false);
+        assert_eq!(contract.current_ma, 1500);
+    }
+
+    #[test]
+    fn test_pps_request() {
+        // Rounded down to 20 mV and 50 mA steps
+        let (rdo, contract) = pps_request(4, 3310, 2030);
+        assert_eq!(rdo, (4 << 28) | (165 << 9) | 40);
+        assert_eq!(contract.voltage_mv, 3300);
+        assert_eq!(contract.current_ma, 2000);
+        assert!(contract.pps);
+    }
+}
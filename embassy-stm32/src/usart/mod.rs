@@ -69,6 +69,9 @@ unsafe fn on_interrupt(r: Regs, s: &'static State) {
             // disable idle line detection
             w.set_idleie(false);
         });
+
+        #[cfg(feature = "time")]
+        s.record_idle(embassy_time::Instant::now());
     } else if cr1.tcie() && sr.tc() {
         // Transmission complete detected
         r.cr1().modify(|w| {
@@ -138,6 +141,8 @@ pub enum ConfigError {
     RxOrTxNotEnabled,
     /// Data bits and parity combination not supported
     DataParityNotSupported,
+    /// Driver enable assertion or deassertion time out of range
+    DriverEnableTimeOutOfRange,
 }
 
 #[non_exhaustive]
@@ -177,6 +182,30 @@ pub struct Config {
     #[cfg(any(usart_v3, usart_v4))]
     pub invert_rx: bool,
 
+    /// Time between the activation of the DE (driver enable) signal and the start bit, in
+    /// sample time units (1/16 or 1/8 bit, depending on the oversampling). Maximum 31.
+    ///
+    /// Only used if the UART was created with a DE pin, e.g. for an RS-485 transceiver.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub de_assertion_time: u8,
+
+    /// Time between the end of the last stop bit and the deactivation of the DE (driver enable)
+    /// signal, in sample time units (1/16 or 1/8 bit, depending on the oversampling). Maximum 31.
+    ///
+    /// Only used if the UART was created with a DE pin, e.g. for an RS-485 transceiver.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub de_deassertion_time: u8,
+
+    /// Set this to true to make the DE (driver enable) signal active low.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub invert_de: bool,
+
+    /// Multiprocessor communication: how the receiver wakes up from mute mode.
+    ///
+    /// If `None`, mute mode is disabled. See [`UartRx::enter_mute_mode`].
+    #[cfg(any(usart_v3, usart_v4))]
+    pub mute_mode: Option<MuteMode>,
+
     /// Set the pull configuration for the RX pin.
     pub rx_pull: Pull,
 
@@ -219,12 +248,39 @@ impl Default for Config {
             invert_tx: false,
             #[cfg(any(usart_v3, usart_v4))]
             invert_rx: false,
+            #[cfg(any(usart_v3, usart_v4))]
+            de_assertion_time: 0,
+            #[cfg(any(usart_v3, usart_v4))]
+            de_deassertion_time: 0,
+            #[cfg(any(usart_v3, usart_v4))]
+            invert_de: false,
+            #[cfg(any(usart_v3, usart_v4))]
+            mute_mode: None,
             rx_pull: Pull::None,
             half_duplex: false,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg(any(usart_v3, usart_v4))]
+/// Wakeup method from mute mode, for multiprocessor communication
+pub enum MuteMode {
+    /// Wake up when the line goes idle.
+    IdleLine,
+    /// Wake up when an address character matching `address` is received.
+    ///
+    /// Address characters have their MSB (the address mark) set. With 9 data bits, this is the
+    /// ninth bit, so all 8-bit values remain available for data.
+    AddressMark {
+        /// Address of this node.
+        address: u8,
+        /// Compare the 7 LSBs of the address instead of the 4 LSBs.
+        seven_bit_address: bool,
+    },
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Half duplex IO mode
@@ -535,6 +591,28 @@ impl<'d, M: Mode> UartTx<'d, M> {
         blocking_flush(self.info)
     }
 
+    /// Send an address character, with the address mark (MSB) set.
+    ///
+    /// Receivers configured with [`MuteMode::AddressMark`] wake up from mute mode if the address
+    /// matches, all others stay muted until the next address character.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub fn blocking_write_address(&mut self, address: u8) -> Result<(), Error> {
+        let r = self.info.regs;
+
+        // Enable Transmitter and disable Receiver for Half-Duplex mode
+        let mut cr1 = r.cr1().read();
+        if r.cr3().read().hdsel() && !cr1.te() {
+            cr1.set_te(true);
+            cr1.set_re(false);
+            r.cr1().write_value(cr1);
+        }
+
+        let word = address_character(cr1, address);
+        while !sr(r).read().txe() {}
+        unsafe { (tdr(r) as *mut u16).write_volatile(word) };
+        Ok(())
+    }
+
     /// Send break character
     pub fn send_break(&self) {
         send_break(&self.info.regs);
@@ -585,6 +663,23 @@ fn blocking_flush(info: &Info) -> Result<(), Error> {
     Ok(())
 }
 
+/// Build an address character for the current frame format: the address mark is the MSB of the
+/// data bits, excluding the parity bit.
+#[cfg(any(usart_v3, usart_v4))]
+fn address_character(cr1: regs::Cr1, address: u8) -> u16 {
+    let mut bits = match (cr1.m1(), cr1.m0()) {
+        (vals::M1::BIT7, _) => 7,
+        (_, vals::M0::BIT9) => 9,
+        _ => 8,
+    };
+    if cr1.pce() {
+        bits -= 1;
+    }
+
+    let mark = 1u16 << (bits - 1);
+    (address as u16 & (mark - 1)) | mark
+}
+
 /// Send break character
 pub fn send_break(regs: &Regs) {
     // Busy wait until previous break has been sent
@@ -1020,6 +1115,22 @@ impl<'d, M: Mode> UartRx<'d, M> {
         Ok(())
     }
 
+    /// Put the receiver into mute mode.
+    ///
+    /// While muted, received characters are ignored until the wakeup condition configured with
+    /// [`Config::mute_mode`] occurs. This is used on multiprocessor (e.g. RS-485) buses to skip
+    /// traffic addressed to other nodes.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub fn enter_mute_mode(&mut self) {
+        self.info.regs.rqr().write(|w| w.set_mmrq(true));
+    }
+
+    /// Get whether the receiver is in mute mode.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub fn is_muted(&self) -> bool {
+        self.info.regs.isr().read().rwu()
+    }
+
     /// Set baudrate
     pub fn set_baudrate(&self, baudrate: u32) -> Result<(), ConfigError> {
         set_baudrate(self.info, self.kernel_clock, baudrate)
@@ -1225,6 +1336,24 @@ impl<'d> Uart<'d, Async> {
     pub async fn read_until_idle(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
         self.rx.read_until_idle(buffer).await
     }
+
+    /// Write a request, then read the response with idle line detection enabled.
+    ///
+    /// This is the typical turnaround on a half-duplex bus such as RS-485: the request is fully
+    /// shifted out (and the DE signal released) before receiving, and any echo of the request
+    /// picked up by the receiver is discarded.
+    pub async fn write_then_read_until_idle(&mut self, request: &[u8], response: &mut [u8]) -> Result<usize, Error> {
+        self.tx.write(request).await?;
+        self.tx.flush().await?;
+
+        let r = self.rx.info.regs;
+        while sr(r).read().rxne() {
+            unsafe { rdr(r).read_volatile() };
+        }
+        clear_interrupt_flags(r, sr(r).read());
+
+        self.rx.read_until_idle(response).await
+    }
 }
 
 impl<'d> Uart<'d, Blocking> {
@@ -1466,6 +1595,18 @@ impl<'d, M: Mode> Uart<'d, M> {
         self.tx.send_break();
     }
 
+    /// Send an address character, see [`UartTx::blocking_write_address`].
+    #[cfg(any(usart_v3, usart_v4))]
+    pub fn blocking_write_address(&mut self, address: u8) -> Result<(), Error> {
+        self.tx.blocking_write_address(address)
+    }
+
+    /// Put the receiver into mute mode, see [`UartRx::enter_mute_mode`].
+    #[cfg(any(usart_v3, usart_v4))]
+    pub fn enter_mute_mode(&mut self) {
+        self.rx.enter_mute_mode()
+    }
+
     /// Set baudrate
     pub fn set_baudrate(&self, baudrate: u32) -> Result<(), ConfigError> {
         self.tx.set_baudrate(baudrate)?;
@@ -1638,6 +1779,11 @@ fn configure(
         return Err(ConfigError::RxOrTxNotEnabled);
     }
 
+    #[cfg(any(usart_v3, usart_v4))]
+    if config.de_assertion_time > 31 || config.de_deassertion_time > 31 {
+        return Err(ConfigError::DriverEnableTimeOutOfRange);
+    }
+
     // UART must be disabled during configuration.
     r.cr1().modify(|w| {
         w.set_ue(false);
@@ -1661,6 +1807,18 @@ fn configure(
             w.set_txinv(config.invert_tx);
             w.set_rxinv(config.invert_rx);
             w.set_swap(config.swap_rx_tx);
+
+            if let Some(MuteMode::AddressMark {
+                address,
+                seven_bit_address,
+            }) = config.mute_mode
+            {
+                w.set_add(address);
+                w.set_addm7(match seven_bit_address {
+                    true => vals::Addm7::BIT7,
+                    false => vals::Addm7::BIT4,
+                });
+            }
         }
     });
 
@@ -1668,6 +1826,11 @@ fn configure(
         #[cfg(not(usart_v1))]
         w.set_onebit(config.assume_noise_free);
         w.set_hdsel(config.half_duplex);
+        #[cfg(any(usart_v3, usart_v4))]
+        w.set_dep(match config.invert_de {
+            true => vals::Dep::LOW,
+            false => vals::Dep::HIGH,
+        });
     });
 
     r.cr1().write(|w| {
@@ -1754,6 +1917,24 @@ fn configure(
             w.set_fifoen(true);
         }
 
+        #[cfg(any(usart_v3, usart_v4))]
+        {
+            w.set_deat(config.de_assertion_time);
+            w.set_dedt(config.de_deassertion_time);
+
+            match config.mute_mode {
+                None => w.set_mme(false),
+                Some(MuteMode::IdleLine) => {
+                    w.set_mme(true);
+                    w.set_wake(vals::Wake::IDLE);
+                }
+                Some(MuteMode::AddressMark { .. }) => {
+                    w.set_mme(true);
+                    w.set_wake(vals::Wake::ADDRESS);
+                }
+            }
+        }
+
         Ok(())
     })?;
 
@@ -1916,7 +2097,7 @@ mod buffered;
 #[cfg(not(gpdma))]
 mod ringbuffered;
 #[cfg(not(gpdma))]
-pub use ringbuffered::RingBufferedUartRx;
+pub use ringbuffered::{RingBufferedUartRx, RxFrame};
 
 #[cfg(any(usart_v1, usart_v2))]
 fn tdr(r: crate::pac::usart::Usart) -> *mut u8 {
@@ -1971,6 +2152,9 @@ enum Kind {
 struct State {
     rx_waker: AtomicWaker,
     tx_rx_refcount: AtomicU8,
+    /// Instant the last idle line was detected, not yet consumed by the receiver.
+    #[cfg(feature = "time")]
+    idle_at: critical_section::Mutex<core::cell::Cell<Option<embassy_time::Instant>>>,
 }
 
 impl State {
@@ -1978,8 +2162,31 @@ impl State {
         Self {
             rx_waker: AtomicWaker::new(),
             tx_rx_refcount: AtomicU8::new(0),
+            #[cfg(feature = "time")]
+            idle_at: critical_section::Mutex::new(core::cell::Cell::new(None)),
         }
     }
+
+    /// Record an idle line detection, keeping the earliest one if several are pending.
+    #[cfg(feature = "time")]
+    fn record_idle(&self, instant: embassy_time::Instant) {
+        critical_section::with(|cs| {
+            let idle_at = self.idle_at.borrow(cs);
+            if idle_at.get().is_none() {
+                idle_at.set(Some(instant));
+            }
+        })
+    }
+
+    #[cfg(feature = "time")]
+    fn take_idle(&self) -> Option<embassy_time::Instant> {
+        critical_section::with(|cs| self.idle_at.borrow(cs).take())
+    }
+
+    #[cfg(feature = "time")]
+    fn idle_pending(&self) -> bool {
+        critical_section::with(|cs| self.idle_at.borrow(cs).get().is_some())
+    }
 }
 
 struct Info {
@@ -17,6 +17,20 @@ use crate::mode::Async;
 use crate::time::Hertz;
 use crate::usart::{Regs, Sr};
 
+/// Frame received by [`RingBufferedUartRx::read_frame`].
+#[derive(Clone, Copy, Debug)]
+pub struct RxFrame {
+    /// Number of bytes in the frame.
+    pub len: usize,
+    /// Instant the idle line ending the frame was detected.
+    ///
+    /// The line has been idle for one character time at this point, so the last character ended
+    /// one character time earlier. This is captured in the interrupt handler, so it can be used
+    /// to validate inter-frame gaps, e.g. the 3.5 character silence of Modbus RTU.
+    #[cfg(feature = "time")]
+    pub idle_at: embassy_time::Instant,
+}
+
 /// Rx-only Ring-buffered UART Driver
 ///
 /// Created with [UartRx::into_ring_buffered]
@@ -171,8 +185,110 @@ impl<'d> RingBufferedUartRx<'d> {
         }
     }
 
+    /// Read a frame, delimited by an idle line.
+    ///
+    /// Waits until at least one byte was received and the line then went idle for one character
+    /// time, and returns the frame length together with the instant the idle line was detected.
+    ///
+    /// Bytes already in the ring buffer when this is called are considered part of the frame, and
+    /// an idle line detected after them ends it, so call this in a loop to keep frames aligned. If
+    /// the frame doesn't fit into `buf`, the rest of the frame is discarded and [`Error::Overrun`]
+    /// is returned.
+    ///
+    /// Background receive is started if `start()` has not been previously called.
+    pub async fn read_frame(&mut self, buf: &mut [u8]) -> Result<RxFrame, Error> {
+        let r = self.info.regs;
+
+        // Start DMA and Uart if it was not already started,
+        // otherwise check for errors in status register.
+        let sr = clear_idle_flag(r);
+        if !r.cr3().read().dmar() {
+            self.start_uart();
+        } else {
+            check_for_errors(sr)?;
+        }
+
+        // An idle line detected while the ring buffer holds data ended the frame waiting in it,
+        // otherwise it is from before this call.
+        let buffered = match self.read_ready() {
+            Ok(buffered) => buffered,
+            Err(err) => {
+                self.stop_uart();
+                return Err(err);
+            }
+        };
+        #[cfg(feature = "time")]
+        let pending_idle = match buffered {
+            true => self.state.idle_pending(),
+            false => {
+                self.state.take_idle();
+                false
+            }
+        };
+        #[cfg(not(feature = "time"))]
+        let pending_idle = false;
+
+        let mut len = 0;
+        let mut truncated = false;
+        let mut idle = buffered && (sr.idle() || pending_idle);
+        loop {
+            loop {
+                let n = if len < buf.len() {
+                    self.read_ring(&mut buf[len..])?
+                } else {
+                    let mut discard = [0u8; 16];
+                    let n = self.read_ring(&mut discard)?;
+                    truncated |= n > 0;
+                    n
+                };
+                if n == 0 {
+                    break;
+                }
+                if !truncated {
+                    len += n;
+                }
+            }
+
+            if idle {
+                #[cfg(feature = "time")]
+                let idle_at = self.state.take_idle().unwrap_or_else(embassy_time::Instant::now);
+
+                if truncated {
+                    return Err(Error::Overrun);
+                }
+                if len > 0 {
+                    return Ok(RxFrame {
+                        len,
+                        #[cfg(feature = "time")]
+                        idle_at,
+                    });
+                }
+            }
+
+            idle = match self.wait_for_data_or_idle().await {
+                Ok(idle) => idle,
+                Err(err) => {
+                    self.stop_uart();
+                    return Err(err);
+                }
+            };
+        }
+    }
+
+    fn read_ring(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self.ring_buf.read(buf) {
+            Ok((len, _)) => Ok(len),
+            Err(_) => {
+                self.stop_uart();
+                Err(Error::Overrun)
+            }
+        }
+    }
+
     /// Wait for uart idle or dma half-full or full
-    async fn wait_for_data_or_idle(&mut self) -> Result<(), Error> {
+    ///
+    /// Returns `true` if the line went idle.
+    async fn wait_for_data_or_idle(&mut self) -> Result<bool, Error> {
         compiler_fence(Ordering::SeqCst);
 
         // Future which completes when idle line is detected
@@ -189,7 +305,10 @@ impl<'d> RingBufferedUartRx<'d> {
             check_for_errors(sr)?;
 
             if sr.idle() {
-                // Idle line is detected
+                // Idle line is detected. The interrupt handler normally records the instant
+                // first, unless the flag was cleared here before it could run.
+                #[cfg(feature = "time")]
+                s.record_idle(embassy_time::Instant::now());
                 Poll::Ready(Ok(()))
             } else {
                 Poll::Pending
@@ -211,8 +330,8 @@ impl<'d> RingBufferedUartRx<'d> {
         });
 
         match select(uart, dma).await {
-            Either::Left((result, _)) => result,
-            Either::Right(((), _)) => Ok(()),
+            Either::Left((result, _)) => result.map(|()| true),
+            Either::Right(((), _)) => Ok(false),
         }
     }
 
@@ -107,3 +107,42 @@ fn common_init<T: Instance>() {
 
     rcc::enable_and_reset::<T>();
 }
+
+/// Waits while the device drives remote wakeup (resume) signaling on the bus.
+///
+/// The USB specification requires the device to signal resume for at least 1 ms and at most 15 ms.
+async fn remote_wakeup_delay() {
+    #[cfg(feature = "time")]
+    embassy_time::Timer::after_millis(10).await;
+    #[cfg(not(feature = "time"))]
+    cortex_m::asm::delay(unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 / 100);
+}
+
+/// Waits up to 1 ms for the core to end the resume signaling from L1, returns whether it did.
+///
+/// The LPM specification gives the host 1 ms to take over the resume signaling.
+#[cfg(otg)]
+async fn l1_remote_wakeup_wait(mut done: impl FnMut() -> bool) -> bool {
+    #[cfg(feature = "time")]
+    {
+        let deadline = embassy_time::Instant::now() + embassy_time::Duration::from_millis(1);
+        while !done() {
+            if embassy_time::Instant::now() >= deadline {
+                return false;
+            }
+            embassy_futures::yield_now().await;
+        }
+        true
+    }
+    #[cfg(not(feature = "time"))]
+    {
+        let cycles_per_us = unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 / 1_000_000;
+        for _ in 0..1000 {
+            if done() {
+                return true;
+            }
+            cortex_m::asm::delay(cycles_per_us);
+        }
+        done()
+    }
+}
@@ -6,8 +6,8 @@ use embassy_usb_synopsys_otg::otg_v1::vals::Dspd;
 use embassy_usb_synopsys_otg::otg_v1::Otg;
 pub use embassy_usb_synopsys_otg::Config;
 use embassy_usb_synopsys_otg::{
-    on_interrupt as on_interrupt_impl, Bus as OtgBus, ControlPipe, Driver as OtgDriver, Endpoint, In, OtgInstance, Out,
-    PhyType, State,
+    on_interrupt as on_interrupt_impl, Bus as OtgBus, ControlPipe, DmaCache, Driver as OtgDriver, Endpoint, In,
+    OtgInstance, Out, PhyType, State,
 };
 
 use crate::gpio::{AfType, OutputType, Speed};
@@ -60,6 +60,7 @@ impl<'d, T: Instance> Driver<'d, T> {
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
     /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// With [`Config::dma`], it also holds the IN endpoint data and must be in memory the USB DMA can access.
     /// Endpoint allocation will fail if it is too small.
     pub fn new_fs(
         _peri: impl Peripheral<P = T> + 'd,
@@ -84,6 +85,7 @@ impl<'d, T: Instance> Driver<'d, T> {
             endpoint_count: T::ENDPOINT_COUNT,
             phy_type: PhyType::InternalFullSpeed,
             calculate_trdt_fn: calculate_trdt::<T>,
+            dma_cache: dma_cache::<T>(&config),
         };
 
         Self {
@@ -98,6 +100,7 @@ impl<'d, T: Instance> Driver<'d, T> {
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
     /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// With [`Config::dma`], it also holds the IN endpoint data and must be in memory the USB DMA can access.
     /// Endpoint allocation will fail if it is too small.
     pub fn new_hs(
         _peri: impl Peripheral<P = T> + 'd,
@@ -123,6 +126,7 @@ impl<'d, T: Instance> Driver<'d, T> {
             endpoint_count: T::ENDPOINT_COUNT,
             phy_type: PhyType::InternalHighSpeed,
             calculate_trdt_fn: calculate_trdt::<T>,
+            dma_cache: dma_cache::<T>(&config),
         };
 
         Self {
@@ -137,6 +141,7 @@ impl<'d, T: Instance> Driver<'d, T> {
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
     /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// With [`Config::dma`], it also holds the IN endpoint data and must be in memory the USB DMA can access.
     /// Endpoint allocation will fail if it is too small.
     pub fn new_fs_ulpi(
         _peri: impl Peripheral<P = T> + 'd,
@@ -169,6 +174,7 @@ impl<'d, T: Instance> Driver<'d, T> {
             endpoint_count: T::ENDPOINT_COUNT,
             phy_type: PhyType::ExternalFullSpeed,
             calculate_trdt_fn: calculate_trdt::<T>,
+            dma_cache: dma_cache::<T>(&config),
         };
 
         Self {
@@ -183,6 +189,7 @@ impl<'d, T: Instance> Driver<'d, T> {
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
     /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// With [`Config::dma`], it also holds the IN endpoint data and must be in memory the USB DMA can access.
     /// Endpoint allocation will fail if it is too small.
     pub fn new_hs_ulpi(
         _peri: impl Peripheral<P = T> + 'd,
@@ -217,6 +224,7 @@ impl<'d, T: Instance> Driver<'d, T> {
             endpoint_count: T::ENDPOINT_COUNT,
             phy_type: PhyType::ExternalHighSpeed,
             calculate_trdt_fn: calculate_trdt::<T>,
+            dma_cache: dma_cache::<T>(&config),
         };
 
         Self {
@@ -390,7 +398,19 @@ impl<'d, T: Instance> embassy_usb_driver::Bus for Bus<'d, T> {
     }
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        self.inner.remote_wakeup().await
+        self.inner.start_remote_wakeup();
+        super::remote_wakeup_delay().await;
+        self.inner.stop_remote_wakeup();
+        Ok(())
+    }
+
+    async fn l1_remote_wakeup(&mut self) -> Result<(), Unsupported> {
+        self.inner.start_l1_remote_wakeup()?;
+        if !super::l1_remote_wakeup_wait(|| self.inner.l1_remote_wakeup_done()).await {
+            // The link already left L1, the core won't end the signaling.
+            self.inner.stop_remote_wakeup();
+        }
+        Ok(())
     }
 }
 
@@ -552,6 +572,38 @@ foreach_interrupt!(
     };
 );
 
+/// Returns the cache maintenance the core needs for DMA transfers.
+fn dma_cache<T: Instance>(config: &Config) -> Option<DmaCache> {
+    assert!(
+        !config.dma || T::HIGH_SPEED,
+        "DMA is only supported by the USB_OTG_HS peripheral"
+    );
+    DMA_CACHE
+}
+
+// Cortex-M7 chips have a data cache.
+#[cfg(any(stm32f7, stm32h7, stm32h7rs))]
+const DMA_CACHE: Option<DmaCache> = Some(DmaCache {
+    clean: clean_dcache,
+    invalidate: invalidate_dcache,
+});
+#[cfg(not(any(stm32f7, stm32h7, stm32h7rs)))]
+const DMA_CACHE: Option<DmaCache> = None;
+
+#[cfg(any(stm32f7, stm32h7, stm32h7rs))]
+unsafe fn clean_dcache(addr: *const u8, len: usize) {
+    cortex_m::Peripherals::steal()
+        .SCB
+        .clean_dcache_by_address(addr as usize, len);
+}
+
+#[cfg(any(stm32f7, stm32h7, stm32h7rs))]
+unsafe fn invalidate_dcache(addr: *mut u8, len: usize) {
+    cortex_m::Peripherals::steal()
+        .SCB
+        .invalidate_dcache_by_address(addr as usize, len);
+}
+
 fn calculate_trdt<T: Instance>(speed: Dspd) -> u8 {
     let ahb_freq = T::frequency().0;
     match speed {
@@ -92,6 +92,14 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
             }
             if epr.ctr_tx() {
                 //trace!("EP {} TX", index);
+                if epr.ep_type() == EpType::ISO {
+                    // The packet buffer that was just sent is the one not selected by DTOG_TX. Clear its count, so
+                    // that an empty packet goes out in its next frame unless new data is written in time.
+                    match epr.dtog_tx() {
+                        true => btable::clear_in_len_tx::<T>(index),
+                        false => btable::clear_in_len_rx::<T>(index),
+                    }
+                }
                 EP_IN_WAKERS[index].wake();
             }
             epr.set_dtog_rx(false);
@@ -182,6 +190,14 @@ mod btable {
         USBRAM.mem(index * 4 + 1).write_value(len);
     }
 
+    pub(super) fn clear_in_len_tx<T: Instance>(index: usize) {
+        USBRAM.mem(index * 4 + 1).write_value(0);
+    }
+
+    pub(super) fn clear_in_len_rx<T: Instance>(index: usize) {
+        USBRAM.mem(index * 4 + 3).write_value(0);
+    }
+
     pub(super) fn write_out_rx<T: Instance>(index: usize, addr: u16, max_len_bits: u16) {
         USBRAM.mem(index * 4 + 2).write_value(addr);
         USBRAM.mem(index * 4 + 3).write_value(max_len_bits);
@@ -218,6 +234,16 @@ mod btable {
             .write_value((addr as u32) | ((len as u32) << 16));
     }
 
+    pub(super) fn clear_in_len_tx<T: Instance>(index: usize) {
+        let val = USBRAM.mem(index * 2).read();
+        USBRAM.mem(index * 2).write_value(val & 0xFFFF);
+    }
+
+    pub(super) fn clear_in_len_rx<T: Instance>(index: usize) {
+        let val = USBRAM.mem(index * 2 + 1).read();
+        USBRAM.mem(index * 2 + 1).write_value(val & 0xFFFF);
+    }
+
     pub(super) fn write_out_tx<T: Instance>(index: usize, addr: u16, max_len_bits: u16) {
         USBRAM
             .mem(index * 2)
@@ -246,6 +272,14 @@ struct EndpointBuffer<T: Instance> {
 }
 
 impl<T: Instance> EndpointBuffer<T> {
+    fn new(addr: u16, len: u16) -> Self {
+        Self {
+            addr,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+
     fn read(&mut self, buf: &mut [u8]) {
         assert!(buf.len() <= self.len as usize);
         for i in 0..(buf.len() + USBRAM_ALIGN - 1) / USBRAM_ALIGN {
@@ -386,7 +420,9 @@ impl<'d, T: Instance> Driver<'d, T> {
 
         ep.ep_type = ep_type;
 
-        let buf = match D::dir() {
+        // Isochronous endpoints get a separate second packet buffer, so that the peripheral can send or receive
+        // the packet of the next frame while the current one is being accessed.
+        let (buf, buf_alt) = match D::dir() {
             Direction::Out => {
                 assert!(!ep.used_out);
                 ep.used_out = true;
@@ -397,15 +433,15 @@ impl<'d, T: Instance> Driver<'d, T> {
                 trace!("  len_bits = {:04x}", len_bits);
                 btable::write_out_rx::<T>(index, addr, len_bits);
 
-                if ep_type == EndpointType::Isochronous {
+                let buf_alt = if ep_type == EndpointType::Isochronous {
+                    let addr = self.alloc_ep_mem(len);
                     btable::write_out_tx::<T>(index, addr, len_bits);
-                }
+                    Some(EndpointBuffer::new(addr, len))
+                } else {
+                    None
+                };
 
-                EndpointBuffer {
-                    addr,
-                    len,
-                    _phantom: PhantomData,
-                }
+                (EndpointBuffer::new(addr, len), buf_alt)
             }
             Direction::In => {
                 assert!(!ep.used_in);
@@ -417,15 +453,19 @@ impl<'d, T: Instance> Driver<'d, T> {
                 // ep_in_len is written when actually TXing packets.
                 btable::write_in_tx::<T>(index, addr);
 
-                if ep_type == EndpointType::Isochronous {
-                    btable::write_in_rx::<T>(index, addr);
-                }
+                let buf_alt = if ep_type == EndpointType::Isochronous {
+                    let addr_alt = self.alloc_ep_mem(len);
+                    btable::write_in_rx::<T>(index, addr_alt);
 
-                EndpointBuffer {
-                    addr,
-                    len,
-                    _phantom: PhantomData,
-                }
+                    // Start out sending empty packets until the first write.
+                    btable::write_in_len_tx::<T>(index, addr, 0);
+                    btable::write_in_len_rx::<T>(index, addr_alt, 0);
+                    Some(EndpointBuffer::new(addr_alt, len))
+                } else {
+                    None
+                };
+
+                (EndpointBuffer::new(addr, len), buf_alt)
             }
         };
 
@@ -440,6 +480,7 @@ impl<'d, T: Instance> Driver<'d, T> {
                 interval_ms,
             },
             buf,
+            buf_alt,
         })
     }
 }
@@ -649,6 +690,9 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
                 loop {
                     let want_stat = match enabled {
                         false => Stat::DISABLED,
+                        // Isochronous endpoints have no handshake, the only meaningful states are DISABLED and VALID.
+                        // While no data is written, empty packets are sent.
+                        true if self.is_isochronous(ep_addr) => Stat::VALID,
                         true => Stat::NAK,
                     };
                     let r = reg.read();
@@ -685,7 +729,26 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
     async fn disable(&mut self) {}
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        Err(Unsupported)
+        let regs = T::regs();
+
+        // Leave suspend mode before driving the resume signaling.
+        regs.cntr().modify(|w| {
+            w.set_fsusp(false);
+            w.set_lpmode(false);
+            w.set_resume(true);
+        });
+
+        super::remote_wakeup_delay().await;
+
+        regs.cntr().modify(|w| w.set_resume(false));
+
+        Ok(())
+    }
+}
+
+impl<'d, T: Instance> Bus<'d, T> {
+    fn is_isochronous(&self, ep_addr: EndpointAddress) -> bool {
+        ep_addr.index() != 0 && self.ep_types[ep_addr.index() - 1] == EpType::ISO
     }
 }
 
@@ -714,6 +777,7 @@ impl Dir for Out {
 /// For double-buffered endpoints, both the `Rx` and `Tx` buffer from a channel are used for the same
 /// direction of transfer. This is opposed to single-buffered endpoints, where one channel can serve
 /// two directions at the same time.
+#[derive(Clone, Copy)]
 enum PacketBuffer {
     /// The RX buffer - must be used for single-buffered OUT endpoints
     Rx,
@@ -726,22 +790,40 @@ pub struct Endpoint<'d, T: Instance, D> {
     _phantom: PhantomData<(&'d mut T, D)>,
     info: EndpointInfo,
     buf: EndpointBuffer<T>,
+    /// Second packet buffer, only present for isochronous endpoints.
+    buf_alt: Option<EndpointBuffer<T>>,
 }
 
 impl<'d, T: Instance, D> Endpoint<'d, T, D> {
+    /// Get the memory backing a packet buffer.
+    ///
+    /// The primary buffer is the one matching the direction of the endpoint (`Rx` for OUT, `Tx` for IN). The other
+    /// one is only separate for isochronous endpoints, all other endpoints share a single buffer for both.
+    fn packet_buffer(&mut self, packet_buffer: PacketBuffer) -> &mut EndpointBuffer<T> {
+        let primary = matches!(
+            (self.info.addr.direction(), packet_buffer),
+            (Direction::Out, PacketBuffer::Rx) | (Direction::In, PacketBuffer::Tx)
+        );
+        match (primary, &mut self.buf_alt) {
+            (false, Some(buf_alt)) => buf_alt,
+            _ => &mut self.buf,
+        }
+    }
+
     /// Write to a double-buffered endpoint.
     ///
-    /// For double-buffered endpoints, the data buffers overlap, but we still need to write to the right counter field.
     /// The DTOG_TX bit indicates the buffer that is currently in use by the USB peripheral, that is, the buffer in
-    /// which the next transmit packet will be stored, so we need to write the counter of the OTHER buffer, which is
-    /// where the last transmitted packet was stored.
+    /// which the next transmit packet will be stored, so we need to write the OTHER buffer and its counter field,
+    /// which is where the last transmitted packet was stored.
     fn write_data_double_buffered(&mut self, buf: &[u8], packet_buffer: PacketBuffer) {
         let index = self.info.addr.index();
-        self.buf.write(buf);
+        let ep_buf = self.packet_buffer(packet_buffer);
+        ep_buf.write(buf);
+        let addr = ep_buf.addr;
 
         match packet_buffer {
-            PacketBuffer::Rx => btable::write_in_len_rx::<T>(index, self.buf.addr, buf.len() as _),
-            PacketBuffer::Tx => btable::write_in_len_tx::<T>(index, self.buf.addr, buf.len() as _),
+            PacketBuffer::Rx => btable::write_in_len_rx::<T>(index, addr, buf.len() as _),
+            PacketBuffer::Tx => btable::write_in_len_tx::<T>(index, addr, buf.len() as _),
         }
     }
 
@@ -752,10 +834,9 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
 
     /// Read from a double-buffered endpoint.
     ///
-    /// For double-buffered endpoints, the data buffers overlap, but we still need to read from the right counter field.
     /// The DTOG_RX bit indicates the buffer that is currently in use by the USB peripheral, that is, the buffer in
-    /// which the next received packet will be stored, so we need to read the counter of the OTHER buffer, which is
-    /// where the last received packet was stored.
+    /// which the next received packet will be stored, so we need to read the OTHER buffer and its counter field,
+    /// which is where the last received packet was stored.
     fn read_data_double_buffered(
         &mut self,
         buf: &mut [u8],
@@ -773,7 +854,7 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
         if rx_len > buf.len() {
             return Err(EndpointError::BufferOverflow);
         }
-        self.buf.read(&mut buf[..rx_len]);
+        self.packet_buffer(packet_buffer).read(&mut buf[..rx_len]);
         Ok(rx_len)
     }
 
@@ -216,6 +216,22 @@ pub trait Bus {
     /// * [`Unsupported`](crate::Unsupported) - This UsbBus implementation doesn't support
     ///   remote wakeup or it has not been enabled at creation time.
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported>;
+
+    /// Initiate a remote wakeup of the host by the device while the link is in the L1
+    /// (LPM sleep) state.
+    ///
+    /// The L1 resume signaling lasts about 50 µs and is timed by the hardware, unlike the 1 to
+    /// 15 ms of [`remote_wakeup`](Self::remote_wakeup) from suspend.
+    ///
+    /// The default implementation just returns `Unsupported`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Unsupported`](crate::Unsupported) - This UsbBus implementation doesn't support
+    ///   L1 remote wakeup.
+    async fn l1_remote_wakeup(&mut self) -> Result<(), Unsupported> {
+        Err(Unsupported)
+    }
 }
 
 /// Endpoint trait, common for OUT and IN.
@@ -364,6 +380,16 @@ pub enum Event {
     /// devices, the device has been connected to the USB bus.
     Resume,
 
+    /// The host has put the link into the L1 (LPM sleep) state.
+    ///
+    /// The link leaves L1 with a [`Event::Resume`] or [`Event::Reset`].
+    Sleep {
+        /// Best Effort Service Latency requested by the host, from 0 to 15.
+        besl: u8,
+        /// Whether the host allows the device to wake it up from L1.
+        remote_wakeup: bool,
+    },
+
     /// The USB power has been detected.
     PowerDetected,
 
@@ -9,7 +9,7 @@ mod fmt;
 use core::cell::UnsafeCell;
 use core::future::poll_fn;
 use core::marker::PhantomData;
-use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use core::sync::atomic::{fence, AtomicBool, AtomicU16, AtomicU32, Ordering};
 use core::task::Poll;
 
 use embassy_sync::waitqueue::AtomicWaker;
@@ -28,19 +28,29 @@ use otg_v1::{regs, vals, Otg};
 pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_EP_COUNT>, ep_count: usize) {
     trace!("irq");
 
+    // In DMA mode the core empties the RX FIFO into the endpoint buffers by itself.
+    let dma = r.gahbcfg().read().dmaen();
+
     let ints = r.gintsts().read();
-    if ints.wkupint() || ints.usbsusp() || ints.usbrst() || ints.enumdne() || ints.otgint() || ints.srqint() {
+    if ints.wkupint()
+        || ints.usbsusp()
+        || ints.lpmint()
+        || ints.usbrst()
+        || ints.enumdne()
+        || ints.otgint()
+        || ints.srqint()
+    {
         // Mask interrupts and notify `Bus` to process them
         r.gintmsk().write(|w| {
             w.set_iepint(true);
             w.set_oepint(true);
-            w.set_rxflvlm(true);
+            w.set_rxflvlm(!dma);
         });
         state.bus_waker.wake();
     }
 
     // Handle RX
-    while r.gintsts().read().rxflvl() {
+    while !dma && r.gintsts().read().rxflvl() {
         let status = r.grxstsp().read();
         trace!("=== status {:08x}", status.0);
         let ep_num = status.epnum() as usize;
@@ -151,6 +161,15 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
                 if ep_ints.stup() {
                     state.cp_state.setup_ready.store(true, Ordering::Release);
                 }
+
+                // In DMA mode, the packet is already in the endpoint buffer when the transfer completes.
+                // Newer cores also signal transfer complete for SETUP packets, which are not data.
+                if dma && ep_ints.xfrc() && !ep_ints.stup() && !ep_ints.stpktrx() {
+                    let remaining = r.doeptsiz(ep_num).read().xfrsiz() as u16;
+                    let len = out_max_packet_size(r, ep_num).saturating_sub(remaining);
+                    state.ep_states[ep_num].out_size.store(len, Ordering::Release);
+                }
+
                 state.ep_states[ep_num].out_waker.wake();
                 trace!("out ep={} irq val={:08x}", ep_num, ep_ints.0);
             }
@@ -208,13 +227,26 @@ impl PhyType {
 /// Indicates that [State::ep_out_buffers] is empty.
 const EP_OUT_BUFFER_EMPTY: u16 = u16::MAX;
 
+/// Alignment of endpoint buffers in DMA mode.
+///
+/// The DMA needs word alignment, and buffers must not share a cache line with other data so that invalidating
+/// them cannot discard unrelated writes.
+const DMA_BUFFER_ALIGN: usize = 32;
+
+/// Space for the three back-to-back SETUP packets the core may write to the EP0 OUT buffer in DMA mode.
+const DMA_SETUP_BUFFER_SIZE: u16 = 3 * 8;
+
 struct EpState {
     in_waker: AtomicWaker,
     out_waker: AtomicWaker,
     /// RX FIFO is shared so extra buffers are needed to dequeue all data without waiting on each endpoint.
     /// Buffers are ready when associated [State::ep_out_size] != [EP_OUT_BUFFER_EMPTY].
+    ///
+    /// In DMA mode, the core writes received packets directly into this buffer.
     out_buffer: UnsafeCell<*mut u8>,
     out_size: AtomicU16,
+    /// Buffer the core reads IN packets from, only used in DMA mode.
+    in_buffer: UnsafeCell<*mut u8>,
 }
 
 // SAFETY: The EndpointAllocator ensures that the buffer points to valid memory exclusive for each endpoint and is
@@ -253,6 +285,7 @@ impl<const EP_COUNT: usize> State<EP_COUNT> {
                     out_waker: AtomicWaker::new(),
                     out_buffer: UnsafeCell::new(0 as _),
                     out_size: AtomicU16::new(EP_OUT_BUFFER_EMPTY),
+                    in_buffer: UnsafeCell::new(0 as _),
                 }
             }; EP_COUNT],
             bus_waker: AtomicWaker::new(),
@@ -294,6 +327,24 @@ pub struct Config {
     /// enumerates in FS mode. Some USB Link IP like those in the STM32H7 series support adding this delay to work with
     /// the affected PHYs.
     pub xcvrdly: bool,
+
+    /// Use the internal DMA of the core to move endpoint data (buffer DMA mode).
+    ///
+    /// Instead of the CPU copying every packet through the FIFOs, the core transfers packets between the FIFOs and
+    /// the endpoint buffer passed to [`Driver::new`]. The buffer must then be located in memory the USB DMA can
+    /// access, and it also holds a copy of the data of each IN endpoint.
+    ///
+    /// Only supported by cores with DMA, usually the high-speed capable ones.
+    pub dma: bool,
+
+    /// Enable USB 2.0 Link Power Management (LPM).
+    ///
+    /// The core acknowledges LPM tokens from the host, and the bus reports [`Event::Sleep`] with the BESL requested
+    /// by the host when the link enters the L1 state. The LPM capability must also be advertised in the BOS
+    /// descriptor, otherwise the host never sends LPM tokens.
+    ///
+    /// Only supported by cores with LPM.
+    pub lpm: bool,
 }
 
 impl Default for Config {
@@ -301,10 +352,21 @@ impl Default for Config {
         Self {
             vbus_detection: false,
             xcvrdly: false,
+            dma: false,
+            lpm: false,
         }
     }
 }
 
+/// Data cache maintenance needed for DMA transfers on cores with a data cache.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DmaCache {
+    /// Writes back the cache lines covering a memory range, so the DMA reads the data written by the CPU.
+    pub clean: unsafe fn(addr: *const u8, len: usize),
+    /// Discards the cache lines covering a memory range, so the CPU reads the data written by the DMA.
+    pub invalidate: unsafe fn(addr: *mut u8, len: usize),
+}
+
 /// USB OTG driver.
 pub struct Driver<'d, const MAX_EP_COUNT: usize> {
     config: Config,
@@ -322,6 +384,8 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
     /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// When [`Config::dma`] is enabled, it must also fit all IN endpoint max packet sizes, with each
+    /// endpoint buffer aligned to 32 bytes.
     /// Endpoint allocation will fail if it is too small.
     /// * `instance` - The USB OTG peripheral instance and its configuration.
     /// * `config` - The USB driver configuration.
@@ -336,6 +400,26 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
         }
     }
 
+    /// Reserves `len` bytes of the endpoint buffer, returns `None` if it is too small.
+    fn alloc_buffer(&mut self, len: u16) -> Option<*mut u8> {
+        let (align, len) = if self.config.dma {
+            (DMA_BUFFER_ALIGN, (len as usize).next_multiple_of(DMA_BUFFER_ALIGN))
+        } else {
+            (1, len as usize)
+        };
+
+        let base = self.ep_out_buffer.as_mut_ptr();
+        // SAFETY: the offset is within the buffer or one past its end.
+        let offset = self.ep_out_buffer_offset + unsafe { base.add(self.ep_out_buffer_offset) }.align_offset(align);
+        if offset + len > self.ep_out_buffer.len() {
+            return None;
+        }
+
+        self.ep_out_buffer_offset = offset + len;
+        // SAFETY: checked above that the allocation is within the buffer.
+        Some(unsafe { base.add(offset) })
+    }
+
     /// Returns the total amount of words (u32) allocated in dedicated FIFO.
     fn allocated_fifo_words(&self) -> u16 {
         self.instance.extra_rx_fifo_words + ep_fifo_size(&self.ep_out) + ep_fifo_size(&self.ep_in)
@@ -356,11 +440,14 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
             D::dir()
         );
 
-        if D::dir() == Direction::Out {
-            if self.ep_out_buffer_offset + max_packet_size as usize >= self.ep_out_buffer.len() {
-                error!("Not enough endpoint out buffer capacity");
-                return Err(EndpointAllocError);
+        let buffer_len = match D::dir() {
+            // In DMA mode, EP0 OUT also receives SETUP packets directly into its buffer.
+            Direction::Out if self.config.dma && ep_type == EndpointType::Control => {
+                u16::max(max_packet_size, DMA_SETUP_BUFFER_SIZE)
             }
+            Direction::Out => max_packet_size,
+            Direction::In if self.config.dma => max_packet_size,
+            Direction::In => 0,
         };
 
         let fifo_size_words = match D::dir() {
@@ -406,18 +493,34 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
 
         trace!("  index={}", index);
 
-        let state = &self.instance.state.ep_states[index];
-        if D::dir() == Direction::Out {
-            // Buffer capacity check was done above, now allocation cannot fail
-            unsafe {
-                *state.out_buffer.get() = self.ep_out_buffer.as_mut_ptr().offset(self.ep_out_buffer_offset as _);
+        let buffer = if buffer_len > 0 {
+            match self.alloc_buffer(buffer_len) {
+                Some(buffer) => buffer,
+                None => {
+                    error!("Not enough endpoint buffer capacity");
+                    match D::dir() {
+                        Direction::Out => self.ep_out[index] = None,
+                        Direction::In => self.ep_in[index] = None,
+                    }
+                    return Err(EndpointAllocError);
+                }
             }
-            self.ep_out_buffer_offset += max_packet_size as usize;
+        } else {
+            core::ptr::null_mut()
+        };
+
+        let state = &self.instance.state.ep_states[index];
+        // SAFETY: the endpoint slot was free, so nothing else accesses these buffer pointers.
+        match D::dir() {
+            Direction::Out => unsafe { *state.out_buffer.get() = buffer },
+            Direction::In => unsafe { *state.in_buffer.get() = buffer },
         }
 
         Ok(Endpoint {
             _phantom: PhantomData,
             regs: self.instance.regs,
+            dma: self.config.dma,
+            dma_cache: self.instance.dma_cache,
             state,
             info: EndpointInfo {
                 addr: EndpointAddress::from_parts(index, D::dir()),
@@ -504,7 +607,8 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
             w.set_wuim(true);
             w.set_iepint(true);
             w.set_oepint(true);
-            w.set_rxflvlm(true);
+            w.set_rxflvlm(!self.config.dma);
+            w.set_lpmintm(self.config.lpm);
             w.set_srqim(true);
             w.set_otgint(true);
         });
@@ -515,6 +619,46 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
         self.instance.phy_type
     }
 
+    /// Starts driving remote wakeup (resume) signaling on the bus.
+    ///
+    /// The signaling must be stopped with [`Bus::stop_remote_wakeup`] after 1 to 15 ms.
+    pub fn start_remote_wakeup(&mut self) {
+        let r = self.instance.regs;
+
+        // Ungate the PHY clock in case it was stopped while suspended.
+        r.pcgcctl().modify(|w| {
+            w.set_stppclk(false);
+            w.set_gatehclk(false);
+        });
+        r.dctl().modify(|w| w.set_rwusig(true));
+    }
+
+    /// Stops driving remote wakeup signaling on the bus.
+    pub fn stop_remote_wakeup(&mut self) {
+        self.instance.regs.dctl().modify(|w| w.set_rwusig(false));
+    }
+
+    /// Starts remote wakeup signaling from the L1 (LPM sleep) state.
+    ///
+    /// In L1 the core drives resume for 50 µs, then clears RWUSIG itself, see
+    /// [`l1_remote_wakeup_done`](Self::l1_remote_wakeup_done). Returns [`Unsupported`] if LPM is
+    /// disabled in the configuration.
+    pub fn start_l1_remote_wakeup(&mut self) -> Result<(), Unsupported> {
+        if !self.config.lpm {
+            return Err(Unsupported);
+        }
+        self.start_remote_wakeup();
+        Ok(())
+    }
+
+    /// Returns whether the core finished the L1 resume signaling.
+    ///
+    /// If the link already left L1, the core may never clear RWUSIG. Call
+    /// [`stop_remote_wakeup`](Self::stop_remote_wakeup) after waiting about 1 ms.
+    pub fn l1_remote_wakeup_done(&self) -> bool {
+        !self.instance.regs.dctl().read().rwusig()
+    }
+
     /// Configures the PHY as a device.
     pub fn configure_as_device(&mut self) {
         let r = self.instance.regs;
@@ -616,11 +760,21 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
             w.set_xfrcm(true);
         });
 
-        // Unmask SETUP received EP interrupt
+        // Unmask SETUP received EP interrupt, and transfer complete when the DMA receives packets
         r.doepmsk().write(|w| {
             w.set_stupm(true);
+            w.set_xfrcm(self.config.dma);
         });
 
+        // Acknowledge LPM tokens, interpreting the latency as BESL
+        if self.config.lpm {
+            r.glpmcfg().write(|w| {
+                w.set_lpmen(true);
+                w.set_lpmack(true);
+                w.set_enbesl(true);
+            });
+        }
+
         // Unmask and clear core interrupts
         self.restore_irqs();
         r.gintsts().write_value(regs::Gintsts(0xFFFF_FFFF));
@@ -628,6 +782,10 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
         // Unmask global interrupt
         r.gahbcfg().write(|w| {
             w.set_gint(true); // unmask global interrupt
+            if self.config.dma {
+                w.set_dmaen(true);
+                w.set_hbstlen(0b0011); // INCR4 bursts
+            }
         });
 
         // Connect
@@ -737,6 +895,13 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
                             w.set_pktcnt(1);
                         }
                     });
+
+                    // In DMA mode, EP0 must be enabled with a buffer to receive SETUP packets.
+                    if index == 0 && self.config.dma {
+                        let state = &self.instance.state.ep_states[index];
+                        // SAFETY: the buffer pointer is only written during endpoint allocation.
+                        dma_start_out(regs, index, unsafe { *state.out_buffer.get() }, ep.max_packet_size);
+                    }
                 });
             }
         }
@@ -837,6 +1002,21 @@ impl<'d, const MAX_EP_COUNT: usize> embassy_usb_driver::Bus for Bus<'d, MAX_EP_C
                 return Poll::Ready(Event::Suspend);
             }
 
+            if ints.lpmint() {
+                regs.gintsts().write(|w| w.set_lpmint(true)); // clear
+                self.restore_irqs();
+
+                // Only an acknowledged LPM token puts the link into L1.
+                let lpm = regs.glpmcfg().read();
+                if lpm.lpmrst() == 0b11 {
+                    trace!("lpm sleep, besl={}", lpm.besl());
+                    return Poll::Ready(Event::Sleep {
+                        besl: lpm.besl(),
+                        remote_wakeup: lpm.remwake(),
+                    });
+                }
+            }
+
             if ints.wkupint() {
                 trace!("resume");
                 regs.gintsts().write(|w| w.set_wkupint(true)); // clear
@@ -922,6 +1102,25 @@ impl<'d, const MAX_EP_COUNT: usize> embassy_usb_driver::Bus for Bus<'d, MAX_EP_C
                         w.set_usbaep(enabled);
                     });
 
+                    // In DMA mode, the endpoint must be given a buffer to receive into, unless it still holds a
+                    // packet not yet consumed by `read()`.
+                    let ep_state = &state.ep_states[ep_addr.index()];
+                    if enabled
+                        && self.config.dma
+                        && !regs.doepctl(ep_addr.index()).read().epena()
+                        && ep_state.out_size.load(Ordering::Acquire) == EP_OUT_BUFFER_EMPTY
+                    {
+                        if let Some(ep) = self.ep_out[ep_addr.index()] {
+                            // SAFETY: the buffer pointer is only written during endpoint allocation.
+                            dma_start_out(
+                                regs,
+                                ep_addr.index(),
+                                unsafe { *ep_state.out_buffer.get() },
+                                ep.max_packet_size,
+                            );
+                        }
+                    }
+
                     // Flush tx fifo
                     regs.grstctl().write(|w| {
                         w.set_txfflsh(true);
@@ -973,8 +1172,26 @@ impl<'d, const MAX_EP_COUNT: usize> embassy_usb_driver::Bus for Bus<'d, MAX_EP_C
     }
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
+        // This crate has no timer to time the resume signaling. HALs implement remote
+        // wakeup with `start_remote_wakeup` and `stop_remote_wakeup` instead.
         Err(Unsupported)
     }
+
+    async fn l1_remote_wakeup(&mut self) -> Result<(), Unsupported> {
+        self.start_l1_remote_wakeup()?;
+
+        // This crate has no timer to bound the wait, so yield to other tasks while polling. HALs
+        // give up after a timeout with `l1_remote_wakeup_done` and `stop_remote_wakeup` instead.
+        poll_fn(|cx| {
+            if self.l1_remote_wakeup_done() {
+                return Poll::Ready(());
+            }
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await;
+        Ok(())
+    }
 }
 
 /// USB endpoint direction.
@@ -1003,10 +1220,32 @@ impl Dir for Out {
 pub struct Endpoint<'d, D> {
     _phantom: PhantomData<D>,
     regs: Otg,
+    dma: bool,
+    dma_cache: Option<DmaCache>,
     info: EndpointInfo,
     state: &'d EpState,
 }
 
+impl<'d, D> Endpoint<'d, D> {
+    /// Makes data written by the CPU to an endpoint buffer visible to the DMA.
+    fn dma_clean(&self, buf: *const u8, len: usize) {
+        if let Some(cache) = self.dma_cache {
+            // SAFETY: the buffer is aligned to and padded up to cache line boundaries on allocation.
+            unsafe { (cache.clean)(buf, len.next_multiple_of(DMA_BUFFER_ALIGN)) };
+        }
+        fence(Ordering::SeqCst);
+    }
+
+    /// Makes data written by the DMA to an endpoint buffer visible to the CPU.
+    fn dma_invalidate(&self, buf: *mut u8, len: usize) {
+        fence(Ordering::SeqCst);
+        if let Some(cache) = self.dma_cache {
+            // SAFETY: the buffer is aligned to and padded up to cache line boundaries on allocation.
+            unsafe { (cache.invalidate)(buf, len.next_multiple_of(DMA_BUFFER_ALIGN)) };
+        }
+    }
+}
+
 impl<'d> embassy_usb_driver::Endpoint for Endpoint<'d, In> {
     fn info(&self) -> &EndpointInfo {
         &self.info
@@ -1073,7 +1312,11 @@ impl<'d> embassy_usb_driver::EndpointOut for Endpoint<'d, Out> {
                 }
 
                 // SAFETY: exclusive access ensured by `out_size` atomic variable
-                let data = unsafe { core::slice::from_raw_parts(*self.state.out_buffer.get(), len as usize) };
+                let out_buffer = unsafe { *self.state.out_buffer.get() };
+                if self.dma {
+                    self.dma_invalidate(out_buffer, len as usize);
+                }
+                let data = unsafe { core::slice::from_raw_parts(out_buffer, len as usize) };
                 buf[..len as usize].copy_from_slice(data);
 
                 // Release buffer
@@ -1101,9 +1344,17 @@ impl<'d> embassy_usb_driver::EndpointOut for Endpoint<'d, Out> {
                         });
                     }
 
+                    if self.dma {
+                        self.regs.doepdma(index).write_value(out_buffer as u32);
+                    }
+
                     // Clear NAK to indicate we are ready to receive more data
                     self.regs.doepctl(index).modify(|w| {
                         w.set_cnak(true);
+                        // The DMA only transfers data to the buffer while the endpoint is enabled.
+                        if self.dma {
+                            w.set_epena(true);
+                        }
                     });
                 });
 
@@ -1150,7 +1401,16 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
         })
         .await?;
 
-        if buf.len() > 0 {
+        // In DMA mode, the core fetches the data from the endpoint buffer into the FIFO by itself.
+        let in_buffer = unsafe { *self.state.in_buffer.get() };
+        if self.dma {
+            // SAFETY: the buffer is max_packet_size long and not in use by the DMA since the previous transfer
+            // completed.
+            unsafe { core::ptr::copy_nonoverlapping(buf.as_ptr(), in_buffer, buf.len()) };
+            self.dma_clean(in_buffer, buf.len());
+        }
+
+        if buf.len() > 0 && !self.dma {
             poll_fn(|cx| {
                 self.state.in_waker.register(cx.waker());
 
@@ -1203,6 +1463,10 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
                 });
             }
 
+            if self.dma {
+                self.regs.diepdma(index).write_value(in_buffer as u32);
+            }
+
             // Enable endpoint
             self.regs.diepctl(index).modify(|w| {
                 w.set_cnak(true);
@@ -1210,10 +1474,12 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
             });
 
             // Write data to FIFO
-            for chunk in buf.chunks(4) {
-                let mut tmp = [0u8; 4];
-                tmp[0..chunk.len()].copy_from_slice(chunk);
-                self.regs.fifo(index).write_value(regs::Fifo(u32::from_ne_bytes(tmp)));
+            if !self.dma {
+                for chunk in buf.chunks(4) {
+                    let mut tmp = [0u8; 4];
+                    tmp[0..chunk.len()].copy_from_slice(chunk);
+                    self.regs.fifo(index).write_value(regs::Fifo(u32::from_ne_bytes(tmp)));
+                }
             }
         });
 
@@ -1243,19 +1509,47 @@ impl<'d> embassy_usb_driver::ControlPipe for ControlPipe<'d> {
 
             if self.setup_state.setup_ready.load(Ordering::Relaxed) {
                 let mut data = [0; 8];
-                data[0..4].copy_from_slice(&self.setup_state.setup_data[0].load(Ordering::Relaxed).to_ne_bytes());
-                data[4..8].copy_from_slice(&self.setup_state.setup_data[1].load(Ordering::Relaxed).to_ne_bytes());
+                if self.ep_out.dma {
+                    // Back-to-back SETUP packets are written one after another, the last one is the valid one.
+                    let stupcnt = self
+                        .regs
+                        .doeptsiz(self.ep_out.info.addr.index())
+                        .read()
+                        .rxdpid_stupcnt();
+                    let offset = 8 * (2 - stupcnt.min(2)) as usize;
+
+                    // SAFETY: the EP0 OUT buffer holds 3 SETUP packets and is not written while the endpoint NAKs.
+                    let out_buffer = unsafe { *self.ep_out.state.out_buffer.get() };
+                    self.ep_out.dma_invalidate(out_buffer, DMA_SETUP_BUFFER_SIZE as usize);
+                    let setup = unsafe { core::slice::from_raw_parts(out_buffer.add(offset), 8) };
+                    data.copy_from_slice(setup);
+                } else {
+                    data[0..4].copy_from_slice(&self.setup_state.setup_data[0].load(Ordering::Relaxed).to_ne_bytes());
+                    data[4..8].copy_from_slice(&self.setup_state.setup_data[1].load(Ordering::Relaxed).to_ne_bytes());
+                }
                 self.setup_state.setup_ready.store(false, Ordering::Release);
 
-                // EP0 should not be controlled by `Bus` so this RMW does not need a critical section
-                self.regs.doeptsiz(self.ep_out.info.addr.index()).modify(|w| {
-                    w.set_rxdpid_stupcnt(3);
-                });
+                if self.ep_out.dma {
+                    // Receive the next SETUP or data packet at the start of the buffer again.
+                    // SAFETY: the buffer pointer is only written during endpoint allocation.
+                    let out_buffer = unsafe { *self.ep_out.state.out_buffer.get() };
+                    dma_start_out(
+                        self.regs,
+                        self.ep_out.info.addr.index(),
+                        out_buffer,
+                        self.ep_out.info.max_packet_size,
+                    );
+                } else {
+                    // EP0 should not be controlled by `Bus` so this RMW does not need a critical section
+                    self.regs.doeptsiz(self.ep_out.info.addr.index()).modify(|w| {
+                        w.set_rxdpid_stupcnt(3);
+                    });
 
-                // Clear NAK to indicate we are ready to receive more data
-                self.regs
-                    .doepctl(self.ep_out.info.addr.index())
-                    .modify(|w| w.set_cnak(true));
+                    // Clear NAK to indicate we are ready to receive more data
+                    self.regs
+                        .doepctl(self.ep_out.info.addr.index())
+                        .modify(|w| w.set_cnak(true));
+                }
 
                 trace!("SETUP received: {:?}", Bytes(&data));
                 Poll::Ready(data)
@@ -1350,6 +1644,33 @@ fn ep_irq_mask(eps: &[Option<EndpointData>]) -> u16 {
     )
 }
 
+/// Returns the max packet size of an OUT endpoint, as configured in DOEPCTL.
+fn out_max_packet_size(r: Otg, index: usize) -> u16 {
+    let mpsiz = r.doepctl(index).read().mpsiz();
+    if index == 0 {
+        // Inverse of `ep0_mpsiz`
+        64 >> (mpsiz & 0b11)
+    } else {
+        mpsiz
+    }
+}
+
+/// Enables an OUT endpoint to receive one packet into `buf` in DMA mode.
+fn dma_start_out(r: Otg, index: usize, buf: *mut u8, max_packet_size: u16) {
+    r.doepdma(index).write_value(buf as u32);
+    r.doeptsiz(index).modify(|w| {
+        w.set_xfrsiz(max_packet_size as _);
+        w.set_pktcnt(1);
+        if index == 0 {
+            w.set_rxdpid_stupcnt(3);
+        }
+    });
+    r.doepctl(index).modify(|w| {
+        w.set_cnak(true);
+        w.set_epena(true);
+    });
+}
+
 /// Calculates MPSIZ value for EP0, which uses special values.
 fn ep0_mpsiz(max_packet_size: u16) -> u16 {
     match max_packet_size {
@@ -1377,4 +1698,6 @@ pub struct OtgInstance<'d, const MAX_EP_COUNT: usize> {
     pub extra_rx_fifo_words: u16,
     /// Function to calculate TRDT value based on some internal clock speed.
     pub calculate_trdt_fn: fn(speed: vals::Dspd) -> u8,
+    /// Cache maintenance for DMA transfers, `None` if the CPU has no data cache.
+    pub dma_cache: Option<DmaCache>,
 }
@@ -351,6 +351,12 @@ impl Otg {
         assert!(n < 16usize);
         unsafe { Reg::from_ptr(self.ptr.add(0x0910usize + n * 32usize) as _) }
     }
+    #[doc = "Device IN endpoint DMA address register"]
+    #[inline(always)]
+    pub fn diepdma(self, n: usize) -> Reg<u32, RW> {
+        assert!(n < 16usize);
+        unsafe { Reg::from_ptr(self.ptr.add(0x0914usize + n * 32usize) as _) }
+    }
     #[doc = "Device IN endpoint transmit FIFO status register"]
     #[inline(always)]
     pub fn dtxfsts(self, n: usize) -> Reg<regs::Dtxfsts, R> {
@@ -1279,6 +1285,17 @@ pub mod regs {
         pub fn set_b2bstup(&mut self, val: bool) {
             self.0 = (self.0 & !(0x01 << 6usize)) | (((val as u32) & 0x01) << 6usize);
         }
+        #[doc = "STPKTRX"]
+        #[inline(always)]
+        pub const fn stpktrx(&self) -> bool {
+            let val = (self.0 >> 15usize) & 0x01;
+            val != 0
+        }
+        #[doc = "STPKTRX"]
+        #[inline(always)]
+        pub fn set_stpktrx(&mut self, val: bool) {
+            self.0 = (self.0 & !(0x01 << 15usize)) | (((val as u32) & 0x01) << 15usize);
+        }
     }
     impl Default for Doepint {
         #[inline(always)]
@@ -2680,6 +2697,17 @@ pub mod regs {
         pub fn set_ptxfe(&mut self, val: bool) {
             self.0 = (self.0 & !(0x01 << 26usize)) | (((val as u32) & 0x01) << 26usize);
         }
+        #[doc = "LPM interrupt"]
+        #[inline(always)]
+        pub const fn lpmint(&self) -> bool {
+            let val = (self.0 >> 27usize) & 0x01;
+            val != 0
+        }
+        #[doc = "LPM interrupt"]
+        #[inline(always)]
+        pub fn set_lpmint(&mut self, val: bool) {
+            self.0 = (self.0 & !(0x01 << 27usize)) | (((val as u32) & 0x01) << 27usize);
+        }
         #[doc = "Connector ID status change"]
         #[inline(always)]
         pub const fn cidschg(&self) -> bool {
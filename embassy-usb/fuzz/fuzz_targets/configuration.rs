@@ -0,0 +1,22 @@
+#![no_main]
+
+use embassy_usb::descriptor_parser::parse_configuration;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok((_, descriptors)) = parse_configuration(data) else {
+        return;
+    };
+
+    // Every descriptor is at least 2 bytes long, and iteration stops at the first error
+    assert!(descriptors.clone().count() <= data.len() / 2);
+    for function in descriptors.clone().functions().flatten() {
+        let _ = function.interfaces().count();
+    }
+    for interface in descriptors.interfaces().flatten() {
+        let _ = interface.descriptors.clone().count();
+        for endpoint in interface.endpoints() {
+            let _ = endpoint.ep_type();
+        }
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use embassy_usb::hid_report::FieldMap;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte splits the input into a report descriptor and an input report
+    let Some((&split, data)) = data.split_first() else {
+        return;
+    };
+    let (descriptor, report) = data.split_at((split as usize).min(data.len()));
+
+    let Ok(map) = FieldMap::<32>::parse(descriptor) else {
+        return;
+    };
+    let elements: usize = map.fields().iter().map(|field| field.count as usize).sum();
+    assert!(map.input_values(report).count() <= elements);
+});
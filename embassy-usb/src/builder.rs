@@ -82,6 +82,24 @@ pub struct Config<'a> {
     /// Default: `false`
     pub supports_remote_wakeup: bool,
 
+    /// Whether the device supports USB 2.0 Link Power Management (LPM).
+    ///
+    /// If set, the USB 2.0 Extension capability in the BOS descriptor advertises LPM and the host
+    /// may put the link into the L1 sleep state. The driver must have LPM enabled as well.
+    ///
+    /// Default: `false`
+    pub supports_lpm: bool,
+
+    /// Baseline Best Effort Service Latency (BESL) advertised for LPM, from 0 to 15.
+    ///
+    /// Default: (none)
+    pub lpm_baseline_besl: Option<u8>,
+
+    /// Deep Best Effort Service Latency (BESL) advertised for LPM, from 0 to 15.
+    ///
+    /// Default: (none)
+    pub lpm_deep_besl: Option<u8>,
+
     /// Configures the device as a composite device with interface association descriptors.
     ///
     /// If set to `true`, the following fields should have the given values:
@@ -130,6 +148,9 @@ impl<'a> Config<'a> {
             serial_number: None,
             self_powered: false,
             supports_remote_wakeup: false,
+            supports_lpm: false,
+            lpm_baseline_besl: None,
+            lpm_deep_besl: None,
             composite_with_iads: false,
             max_power: 100,
         }
@@ -183,11 +204,17 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
             _ => panic!("invalid max_packet_size_0, the allowed values are 8, 16, 32 or 64"),
         }
 
+        for besl in [config.lpm_baseline_besl, config.lpm_deep_besl].into_iter().flatten() {
+            if besl > 15 {
+                panic!("invalid LPM BESL value, the allowed values are 0 to 15");
+            }
+        }
+
         let mut config_descriptor = DescriptorWriter::new(config_descriptor_buf);
         let mut bos_descriptor = BosWriter::new(DescriptorWriter::new(bos_descriptor_buf));
 
         config_descriptor.configuration(&config);
-        bos_descriptor.bos();
+        bos_descriptor.bos(&config);
 
         Builder {
             driver,
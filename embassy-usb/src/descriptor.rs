@@ -363,6 +363,22 @@ pub(crate) fn device_qualifier_descriptor(config: &Config) -> [u8; 10] {
     ]
 }
 
+/// Returns the `bmAttributes` of the USB 2.0 Extension capability descriptor.
+fn usb_2_0_extension_attributes(config: &Config) -> u32 {
+    let mut attributes = 0;
+    if config.supports_lpm {
+        // LPM, using the BESL and alternate HIRD definitions.
+        attributes |= (1 << 1) | (1 << 2);
+        if let Some(besl) = config.lpm_baseline_besl {
+            attributes |= (1 << 3) | ((besl as u32 & 0x0f) << 8);
+        }
+        if let Some(besl) = config.lpm_deep_besl {
+            attributes |= (1 << 4) | ((besl as u32 & 0x0f) << 12);
+        }
+    }
+    attributes
+}
+
 /// A writer for Binary Object Store descriptor.
 pub struct BosWriter<'a> {
     pub(crate) writer: DescriptorWriter<'a>,
@@ -377,7 +393,7 @@ impl<'a> BosWriter<'a> {
         }
     }
 
-    pub(crate) fn bos(&mut self) {
+    pub(crate) fn bos(&mut self, config: &Config) {
         if (self.writer.buf.len() - self.writer.position) < 5 {
             return;
         }
@@ -391,7 +407,8 @@ impl<'a> BosWriter<'a> {
             &[],
         );
 
-        self.capability(capability_type::USB_2_0_EXTENSION, &[0; 4]);
+        let attributes = usb_2_0_extension_attributes(config);
+        self.capability(capability_type::USB_2_0_EXTENSION, &attributes.to_le_bytes());
     }
 
     /// Writes capability descriptor to a BOS
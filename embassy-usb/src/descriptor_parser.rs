@@ -0,0 +1,698 @@
+//! Zero-copy parsing of USB configuration and string descriptors.
+//!
+//! [`parse_configuration`] walks the bytes returned by `GET_DESCRIPTOR(CONFIGURATION)` and yields
+//! typed descriptors borrowing from them. [`StringDescriptor`] decodes the UTF-16LE strings and
+//! language ids returned by `GET_DESCRIPTOR(STRING)`. Nothing is allocated, and every length is checked
+//! against the data, so malformed descriptors from a device result in a [`ParseError`] instead of
+//! a panic.
+
+use crate::descriptor::descriptor_type;
+use crate::driver::{EndpointAddress, EndpointType};
+use crate::types::InterfaceNumber;
+
+/// Class specific interface descriptor type.
+pub const CS_INTERFACE: u8 = 0x24;
+/// Class specific endpoint descriptor type.
+pub const CS_ENDPOINT: u8 = 0x25;
+
+/// Error returned when descriptors are malformed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    /// A descriptor extends past the end of the data.
+    Truncated,
+    /// `bLength` is shorter than required for the descriptor type.
+    InvalidLength,
+    /// The data doesn't start with the expected descriptor type.
+    UnexpectedType,
+}
+
+/// Configuration descriptor.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConfigurationDescriptor {
+    /// Total length of the configuration, including all its descriptors.
+    pub total_length: u16,
+    /// Number of interfaces.
+    pub num_interfaces: u8,
+    /// Value selecting the configuration with `SET_CONFIGURATION`.
+    pub configuration_value: u8,
+    /// Index of the string describing the configuration, 0 if none.
+    pub configuration_string: u8,
+    /// Attributes: self powered (bit 6) and remote wakeup (bit 5).
+    pub attributes: u8,
+    /// Maximum power consumption, in 2 mA units.
+    pub max_power: u8,
+}
+
+impl ConfigurationDescriptor {
+    /// Whether the device is self powered in this configuration.
+    pub fn self_powered(&self) -> bool {
+        self.attributes & 0x40 != 0
+    }
+
+    /// Whether the device supports remote wakeup in this configuration.
+    pub fn remote_wakeup(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+}
+
+/// Interface descriptor.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterfaceDescriptor {
+    /// Interface number.
+    pub interface_number: InterfaceNumber,
+    /// Alternate setting.
+    pub alternate_setting: u8,
+    /// Number of endpoints, excluding endpoint 0.
+    pub num_endpoints: u8,
+    /// Class code.
+    pub class: u8,
+    /// Subclass code.
+    pub subclass: u8,
+    /// Protocol code.
+    pub protocol: u8,
+    /// Index of the string describing the interface, 0 if none.
+    pub interface_string: u8,
+}
+
+/// Endpoint descriptor.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EndpointDescriptor {
+    /// Endpoint address.
+    pub address: EndpointAddress,
+    /// Raw attributes, including the synchronization and usage types of isochronous endpoints.
+    pub attributes: u8,
+    /// Maximum packet size, including the additional transactions bits of high speed endpoints.
+    pub max_packet_size: u16,
+    /// Polling interval.
+    pub interval: u8,
+}
+
+impl EndpointDescriptor {
+    /// Transfer type of the endpoint.
+    pub fn ep_type(&self) -> EndpointType {
+        match self.attributes & 0b11 {
+            0b00 => EndpointType::Control,
+            0b01 => EndpointType::Isochronous,
+            0b10 => EndpointType::Bulk,
+            _ => EndpointType::Interrupt,
+        }
+    }
+}
+
+/// Interface association descriptor, grouping the interfaces of a function.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterfaceAssociationDescriptor {
+    /// First interface of the function.
+    pub first_interface: InterfaceNumber,
+    /// Number of contiguous interfaces of the function.
+    pub interface_count: u8,
+    /// Function class code.
+    pub function_class: u8,
+    /// Function subclass code.
+    pub function_subclass: u8,
+    /// Function protocol code.
+    pub function_protocol: u8,
+    /// Index of the string describing the function, 0 if none.
+    pub function_string: u8,
+}
+
+/// Class specific descriptor, such as the CDC functional or HID descriptors.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClassSpecificDescriptor<'a> {
+    /// Descriptor type.
+    pub descriptor_type: u8,
+    /// Descriptor contents following `bDescriptorType`.
+    pub data: &'a [u8],
+}
+
+impl ClassSpecificDescriptor<'_> {
+    /// Descriptor subtype of [`CS_INTERFACE`] and [`CS_ENDPOINT`] descriptors, the first byte of
+    /// the contents.
+    pub fn subtype(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+}
+
+/// A descriptor of a configuration.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Descriptor<'a> {
+    /// Configuration descriptor.
+    Configuration(ConfigurationDescriptor),
+    /// Interface descriptor.
+    Interface(InterfaceDescriptor),
+    /// Endpoint descriptor.
+    Endpoint(EndpointDescriptor),
+    /// Interface association descriptor.
+    InterfaceAssociation(InterfaceAssociationDescriptor),
+    /// Any other descriptor, usually class specific.
+    ClassSpecific(ClassSpecificDescriptor<'a>),
+}
+
+impl<'a> Descriptor<'a> {
+    /// Decodes a descriptor of `descriptor_type`, whose contents follow `bDescriptorType`.
+    fn parse(descriptor_type: u8, data: &'a [u8]) -> Result<Self, ParseError> {
+        let byte = |i: usize| data.get(i).copied().ok_or(ParseError::InvalidLength);
+        let word = |i: usize| Ok(u16::from_le_bytes([byte(i)?, byte(i + 1)?]));
+
+        Ok(match descriptor_type {
+            descriptor_type::CONFIGURATION => Self::Configuration(ConfigurationDescriptor {
+                total_length: word(0)?,
+                num_interfaces: byte(2)?,
+                configuration_value: byte(3)?,
+                configuration_string: byte(4)?,
+                attributes: byte(5)?,
+                max_power: byte(6)?,
+            }),
+            descriptor_type::INTERFACE => Self::Interface(InterfaceDescriptor {
+                interface_number: InterfaceNumber(byte(0)?),
+                alternate_setting: byte(1)?,
+                num_endpoints: byte(2)?,
+                class: byte(3)?,
+                subclass: byte(4)?,
+                protocol: byte(5)?,
+                interface_string: byte(6)?,
+            }),
+            descriptor_type::ENDPOINT => Self::Endpoint(EndpointDescriptor {
+                address: EndpointAddress::from(byte(0)?),
+                attributes: byte(1)?,
+                max_packet_size: word(2)?,
+                interval: byte(4)?,
+            }),
+            descriptor_type::IAD => Self::InterfaceAssociation(InterfaceAssociationDescriptor {
+                first_interface: InterfaceNumber(byte(0)?),
+                interface_count: byte(1)?,
+                function_class: byte(2)?,
+                function_subclass: byte(3)?,
+                function_protocol: byte(4)?,
+                function_string: byte(5)?,
+            }),
+            _ => Self::ClassSpecific(ClassSpecificDescriptor { descriptor_type, data }),
+        })
+    }
+}
+
+/// Iterator over the descriptors of a buffer.
+///
+/// Stops after the first error.
+#[derive(Clone)]
+pub struct DescriptorIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DescriptorIter<'a> {
+    /// Iterates over the descriptors in `data`.
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Returns the bytes not iterated over yet.
+    pub const fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Groups the remaining descriptors by interface.
+    pub fn interfaces(self) -> InterfaceIter<'a> {
+        InterfaceIter { descriptors: self }
+    }
+
+    /// Groups the remaining descriptors by function, following the interface associations.
+    pub fn functions(self) -> FunctionIter<'a> {
+        FunctionIter { descriptors: self }
+    }
+
+    /// Splits off the descriptors up to the first one matching `end`, checking them on the way.
+    fn split_until(&mut self, end: impl Fn(&Descriptor<'a>) -> bool) -> Result<DescriptorIter<'a>, ParseError> {
+        let start = self.data;
+        loop {
+            let mut next = self.clone();
+            match next.next() {
+                None => break,
+                Some(Ok(descriptor)) if end(&descriptor) => break,
+                Some(Ok(_)) => *self = next,
+                Some(Err(e)) => {
+                    *self = next;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(DescriptorIter::new(&start[..start.len() - self.data.len()]))
+    }
+
+    /// Splits the next descriptor off the data, returning its type and contents.
+    fn next_raw(&mut self) -> Result<(u8, &'a [u8]), ParseError> {
+        let (&len, rest) = self.data.split_first().ok_or(ParseError::Truncated)?;
+        let len = len as usize;
+        if len < 2 {
+            return Err(ParseError::InvalidLength);
+        }
+        if len > self.data.len() {
+            return Err(ParseError::Truncated);
+        }
+        let descriptor_type = rest[0];
+        let data = &self.data[2..len];
+        self.data = &self.data[len..];
+        Ok((descriptor_type, data))
+    }
+}
+
+impl<'a> Iterator for DescriptorIter<'a> {
+    type Item = Result<Descriptor<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let result = self
+            .next_raw()
+            .and_then(|(descriptor_type, data)| Descriptor::parse(descriptor_type, data));
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// An interface, with the descriptors following it.
+#[derive(Clone)]
+pub struct Interface<'a> {
+    /// Interface descriptor.
+    pub descriptor: InterfaceDescriptor,
+    /// Descriptors up to the next interface or interface association, such as the class specific
+    /// descriptors and the endpoints of the interface.
+    pub descriptors: DescriptorIter<'a>,
+}
+
+impl<'a> Interface<'a> {
+    /// Iterates over the endpoints of the interface.
+    pub fn endpoints(&self) -> impl Iterator<Item = EndpointDescriptor> + 'a {
+        self.descriptors.clone().filter_map(|d| match d {
+            Ok(Descriptor::Endpoint(ep)) => Some(ep),
+            _ => None,
+        })
+    }
+}
+
+/// Iterator over the interfaces of a configuration, returned by [`DescriptorIter::interfaces`].
+///
+/// Descriptors before the first interface, like interface associations, are skipped.
+#[derive(Clone)]
+pub struct InterfaceIter<'a> {
+    descriptors: DescriptorIter<'a>,
+}
+
+impl<'a> Iterator for InterfaceIter<'a> {
+    type Item = Result<Interface<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let descriptor = loop {
+            match self.descriptors.next()? {
+                Ok(Descriptor::Interface(descriptor)) => break descriptor,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        };
+
+        let descriptors = self
+            .descriptors
+            .split_until(|d| matches!(d, Descriptor::Interface(_) | Descriptor::InterfaceAssociation(_)));
+        Some(descriptors.map(|descriptors| Interface {
+            descriptor,
+            descriptors,
+        }))
+    }
+}
+
+/// A function of a device, made of the interfaces grouped by an interface association, or of a
+/// single interface outside of any association.
+///
+/// Composite devices, like a device with several CDC ACM serial ports, use interface associations
+/// so the host binds one class driver per function.
+#[derive(Clone)]
+pub struct Function<'a> {
+    /// Interface association, `None` for a single interface.
+    pub association: Option<InterfaceAssociationDescriptor>,
+    /// Descriptors of the function, starting with its first interface descriptor.
+    pub descriptors: DescriptorIter<'a>,
+}
+
+impl<'a> Function<'a> {
+    /// Iterates over the interfaces of the function, including their alternate settings.
+    pub fn interfaces(&self) -> InterfaceIter<'a> {
+        self.descriptors.clone().interfaces()
+    }
+}
+
+/// Iterator over the functions of a configuration, returned by [`DescriptorIter::functions`].
+///
+/// An interface association groups the following interfaces it lists. Other interfaces form a
+/// function on their own, with their alternate settings.
+#[derive(Clone)]
+pub struct FunctionIter<'a> {
+    descriptors: DescriptorIter<'a>,
+}
+
+impl<'a> Iterator for FunctionIter<'a> {
+    type Item = Result<Function<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (association, first, count) = loop {
+            let start = self.descriptors.clone();
+            match self.descriptors.next()? {
+                Ok(Descriptor::InterfaceAssociation(iad)) => {
+                    break (Some(iad), iad.first_interface.0, iad.interface_count);
+                }
+                Ok(Descriptor::Interface(descriptor)) => {
+                    // The interface descriptor is part of the function.
+                    self.descriptors = start;
+                    break (None, descriptor.interface_number.0, 1);
+                }
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        };
+
+        let member = |number: u8| number >= first && (number - first) < count;
+        let descriptors = self.descriptors.split_until(|d| match d {
+            Descriptor::InterfaceAssociation(_) => true,
+            Descriptor::Interface(descriptor) => !member(descriptor.interface_number.0),
+            _ => false,
+        });
+        Some(descriptors.map(|descriptors| Function {
+            association,
+            descriptors,
+        }))
+    }
+}
+
+/// Parses the data returned by `GET_DESCRIPTOR(CONFIGURATION)`.
+///
+/// Returns the configuration descriptor and an iterator over the descriptors following it. Data
+/// past `wTotalLength` is ignored, and [`ParseError::Truncated`] is returned if there is less.
+pub fn parse_configuration(data: &[u8]) -> Result<(ConfigurationDescriptor, DescriptorIter<'_>), ParseError> {
+    let mut descriptors = DescriptorIter::new(data);
+    let (descriptor_type, contents) = descriptors.next_raw()?;
+    if descriptor_type != descriptor_type::CONFIGURATION {
+        return Err(ParseError::UnexpectedType);
+    }
+    let Descriptor::Configuration(config) = Descriptor::parse(descriptor_type, contents)? else {
+        unreachable!()
+    };
+
+    let total_length = config.total_length as usize;
+    let header_len = data.len() - descriptors.remaining().len();
+    if total_length < header_len {
+        return Err(ParseError::InvalidLength);
+    }
+    let data = data.get(header_len..total_length).ok_or(ParseError::Truncated)?;
+    Ok((config, DescriptorIter::new(data)))
+}
+
+/// String descriptor, holding UTF-16LE code units.
+///
+/// String descriptor 0 holds the language ids supported by the device instead of a string, see
+/// [`language_ids`](Self::language_ids).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StringDescriptor<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StringDescriptor<'a> {
+    /// Parses a string descriptor.
+    ///
+    /// A trailing byte left by an odd `bLength` is ignored.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ParseError> {
+        let (descriptor_type, data) = DescriptorIter::new(data).next_raw()?;
+        if descriptor_type != descriptor_type::STRING {
+            return Err(ParseError::UnexpectedType);
+        }
+        Ok(Self { data })
+    }
+
+    /// Returns the UTF-16 code units of the string.
+    pub fn code_units(&self) -> impl Iterator<Item = u16> + 'a {
+        self.data
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+    }
+
+    /// Returns the language ids supported by the device, from string descriptor 0.
+    ///
+    /// See [`lang_id`](crate::descriptor::lang_id).
+    pub fn language_ids(&self) -> impl Iterator<Item = u16> + 'a {
+        self.code_units()
+    }
+
+    /// Returns the characters of the string, with invalid UTF-16 replaced by U+FFFD.
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'a {
+        char::decode_utf16(self.code_units()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Decodes the string into `buf` as UTF-8.
+    ///
+    /// If `buf` is too small, the string is cut after the last character that fits.
+    pub fn decode<'b>(&self, buf: &'b mut [u8]) -> &'b str {
+        let mut len = 0;
+        for c in self.chars() {
+            let Some(dst) = buf.get_mut(len..len + c.len_utf8()) else {
+                break;
+            };
+            len += c.encode_utf8(dst).len();
+        }
+        // Only whole characters were written.
+        core::str::from_utf8(&buf[..len]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::Direction;
+
+    /// CDC ACM configuration: an IAD, a communication interface with functional descriptors and
+    /// a notification endpoint, and a data interface with two bulk endpoints.
+    const CDC_ACM: [u8; 75] = [
+        0x09, 0x02, 0x4b, 0x00, 0x02, 0x01, 0x00, 0xa0, 0x32, // configuration
+        0x08, 0x0b, 0x00, 0x02, 0x02, 0x02, 0x00, 0x00, // IAD
+        0x09, 0x04, 0x00, 0x00, 0x01, 0x02, 0x02, 0x00, 0x00, // interface 0
+        0x05, 0x24, 0x00, 0x10, 0x01, // CDC header
+        0x05, 0x24, 0x01, 0x00, 0x01, // call management
+        0x04, 0x24, 0x02, 0x02, // ACM
+        0x05, 0x24, 0x06, 0x00, 0x01, // union
+        0x07, 0x05, 0x82, 0x03, 0x08, 0x00, 0xff, // notification endpoint
+        0x09, 0x04, 0x01, 0x00, 0x02, 0x0a, 0x00, 0x00, 0x00, // interface 1
+        0x07, 0x05, 0x01, 0x02, 0x40, 0x00, 0x00, // bulk out
+        0x07, 0x05, 0x81, 0x02, 0x40, 0x00, 0x00, // bulk in
+    ];
+
+    #[test]
+    fn test_parse_configuration() {
+        let (config, descriptors) = parse_configuration(&CDC_ACM).unwrap();
+        assert_eq!(config.total_length, 75);
+        assert_eq!(config.num_interfaces, 2);
+        assert_eq!(config.configuration_value, 1);
+        assert!(config.remote_wakeup());
+        assert!(!config.self_powered());
+        assert_eq!(config.max_power, 0x32);
+
+        let mut descriptors = descriptors;
+        assert_eq!(
+            descriptors.next(),
+            Some(Ok(Descriptor::InterfaceAssociation(InterfaceAssociationDescriptor {
+                first_interface: InterfaceNumber(0),
+                interface_count: 2,
+                function_class: 0x02,
+                function_subclass: 0x02,
+                function_protocol: 0x00,
+                function_string: 0,
+            })))
+        );
+        let Some(Ok(Descriptor::Interface(interface))) = descriptors.next() else {
+            panic!("expected an interface");
+        };
+        assert_eq!(interface.interface_number, InterfaceNumber(0));
+        assert_eq!(interface.class, 0x02);
+
+        let Some(Ok(Descriptor::ClassSpecific(header))) = descriptors.next() else {
+            panic!("expected a class specific descriptor");
+        };
+        assert_eq!(header.descriptor_type, CS_INTERFACE);
+        assert_eq!(header.subtype(), Some(0x00));
+        assert_eq!(header.data, &[0x00, 0x10, 0x01]);
+
+        assert_eq!(descriptors.count(), 7);
+    }
+
+    #[test]
+    fn test_interfaces() {
+        let (_, descriptors) = parse_configuration(&CDC_ACM).unwrap();
+        let mut interfaces = descriptors.interfaces();
+
+        let comm = interfaces.next().unwrap().unwrap();
+        assert_eq!(comm.descriptor.interface_number, InterfaceNumber(0));
+        assert_eq!(comm.descriptors.clone().count(), 5);
+        let mut endpoints = comm.endpoints();
+        let notification = endpoints.next().unwrap();
+        assert_eq!(notification.address, EndpointAddress::from_parts(2, Direction::In));
+        assert_eq!(notification.ep_type(), EndpointType::Interrupt);
+        assert_eq!(notification.max_packet_size, 8);
+        assert_eq!(notification.interval, 0xff);
+        assert!(endpoints.next().is_none());
+
+        let data = interfaces.next().unwrap().unwrap();
+        assert_eq!(data.descriptor.interface_number, InterfaceNumber(1));
+        assert_eq!(data.descriptor.num_endpoints, 2);
+        let endpoints: heapless::Vec<_, 4> = data.endpoints().collect();
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(endpoints[0].address, EndpointAddress::from_parts(1, Direction::Out));
+        assert_eq!(endpoints[1].ep_type(), EndpointType::Bulk);
+        assert_eq!(endpoints[1].max_packet_size, 64);
+
+        assert!(interfaces.next().is_none());
+    }
+
+    #[test]
+    fn test_functions() {
+        let data = [
+            0x08, 0x0b, 0x00, 0x02, 0x02, 0x02, 0x00, 0x00, // IAD, interfaces 0 and 1
+            0x09, 0x04, 0x00, 0x00, 0x01, 0x02, 0x02, 0x00, 0x00, // interface 0
+            0x05, 0x24, 0x00, 0x10, 0x01, // CDC header
+            0x07, 0x05, 0x81, 0x03, 0x08, 0x00, 0xff, // notification endpoint
+            0x09, 0x04, 0x01, 0x00, 0x02, 0x0a, 0x00, 0x00, 0x00, // interface 1
+            0x07, 0x05, 0x02, 0x02, 0x40, 0x00, 0x00, // bulk out
+            0x07, 0x05, 0x82, 0x02, 0x40, 0x00, 0x00, // bulk in
+            0x08, 0x0b, 0x02, 0x02, 0x02, 0x02, 0x00, 0x00, // IAD, interfaces 2 and 3
+            0x09, 0x04, 0x02, 0x00, 0x01, 0x02, 0x02, 0x00, 0x00, // interface 2
+            0x07, 0x05, 0x83, 0x03, 0x08, 0x00, 0xff, // notification endpoint
+            0x09, 0x04, 0x03, 0x00, 0x02, 0x0a, 0x00, 0x00, 0x00, // interface 3
+            0x07, 0x05, 0x04, 0x02, 0x40, 0x00, 0x00, // bulk out
+            0x07, 0x05, 0x84, 0x02, 0x40, 0x00, 0x00, // bulk in
+            0x09, 0x04, 0x04, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, // interface 4
+            0x09, 0x04, 0x04, 0x01, 0x01, 0xff, 0x00, 0x00, 0x00, // interface 4, alternate setting 1
+            0x07, 0x05, 0x85, 0x02, 0x40, 0x00, 0x00, // bulk in
+            0x09, 0x04, 0x05, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, // interface 5
+        ];
+        let mut functions = DescriptorIter::new(&data).functions();
+
+        for first in [0, 2] {
+            let function = functions.next().unwrap().unwrap();
+            let association = function.association.unwrap();
+            assert_eq!(association.first_interface, InterfaceNumber(first));
+            assert_eq!(association.function_class, 0x02);
+            let interfaces: heapless::Vec<_, 4> = function.interfaces().map(Result::unwrap).collect();
+            assert_eq!(interfaces.len(), 2);
+            assert_eq!(interfaces[0].descriptor.interface_number, InterfaceNumber(first));
+            assert_eq!(interfaces[1].descriptor.interface_number, InterfaceNumber(first + 1));
+            assert_eq!(interfaces[1].endpoints().count(), 2);
+        }
+
+        let vendor = functions.next().unwrap().unwrap();
+        assert!(vendor.association.is_none());
+        let interfaces: heapless::Vec<_, 4> = vendor.interfaces().map(Result::unwrap).collect();
+        assert_eq!(interfaces.len(), 2);
+        assert_eq!(interfaces[1].descriptor.alternate_setting, 1);
+        assert_eq!(interfaces[1].endpoints().count(), 1);
+
+        let last = functions.next().unwrap().unwrap();
+        assert_eq!(last.descriptors.count(), 1);
+        assert!(functions.next().is_none());
+
+        // A single function for the CDC ACM configuration
+        let (_, descriptors) = parse_configuration(&CDC_ACM).unwrap();
+        let mut functions = descriptors.functions();
+        assert_eq!(functions.next().unwrap().unwrap().interfaces().count(), 2);
+        assert!(functions.next().is_none());
+    }
+
+    #[test]
+    fn test_string_descriptor() {
+        // "E", "m", U+2603 and U+1F980 as a surrogate pair
+        let data = [
+            0x0c, 0x03, 0x45, 0x00, 0x6d, 0x00, 0x03, 0x26, 0x3e, 0xd8, 0x80, 0xdd, // descriptor
+            0xff, // trailing data
+        ];
+        let string = StringDescriptor::parse(&data).unwrap();
+        assert_eq!(string.code_units().count(), 5);
+        let chars: heapless::Vec<_, 8> = string.chars().collect();
+        assert_eq!(chars, ['E', 'm', '\u{2603}', '\u{1f980}']);
+
+        let mut buf = [0u8; 16];
+        assert_eq!(string.decode(&mut buf), "Em\u{2603}\u{1f980}");
+        // Cut before the character that doesn't fit
+        assert_eq!(string.decode(&mut buf[..8]), "Em\u{2603}");
+        assert_eq!(string.decode(&mut buf[..0]), "");
+
+        // Unpaired surrogate and odd length
+        let string = StringDescriptor::parse(&[0x07, 0x03, 0x41, 0x00, 0x00, 0xdc, 0x42]).unwrap();
+        assert_eq!(string.decode(&mut buf), "A\u{fffd}");
+
+        // Empty string
+        let string = StringDescriptor::parse(&[0x02, 0x03]).unwrap();
+        assert_eq!(string.decode(&mut buf), "");
+    }
+
+    #[test]
+    fn test_language_ids() {
+        let data = [0x06, 0x03, 0x09, 0x04, 0x07, 0x04];
+        let string = StringDescriptor::parse(&data).unwrap();
+        let ids: heapless::Vec<_, 4> = string.language_ids().collect();
+        assert_eq!(ids, [crate::descriptor::lang_id::ENGLISH_US, 0x0407]);
+
+        assert_eq!(StringDescriptor::parse(&data[..4]).err(), Some(ParseError::Truncated));
+        assert_eq!(
+            StringDescriptor::parse(&CDC_ACM).err(),
+            Some(ParseError::UnexpectedType)
+        );
+        assert_eq!(
+            StringDescriptor::parse(&[0x01, 0x03]).err(),
+            Some(ParseError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        // Shorter than wTotalLength
+        assert_eq!(parse_configuration(&CDC_ACM[..74]).err(), Some(ParseError::Truncated));
+        // Not a configuration descriptor
+        assert_eq!(
+            parse_configuration(&CDC_ACM[9..]).err(),
+            Some(ParseError::UnexpectedType)
+        );
+        // Configuration descriptor cut short
+        assert_eq!(parse_configuration(&CDC_ACM[..5]).err(), Some(ParseError::Truncated));
+        assert_eq!(
+            parse_configuration(&[0x05, 0x02, 0x05, 0x00, 0x01]).err(),
+            Some(ParseError::InvalidLength)
+        );
+
+        // bLength of 0 would loop forever
+        let mut iter = DescriptorIter::new(&[0x00, 0x04, 0x00]);
+        assert_eq!(iter.next(), Some(Err(ParseError::InvalidLength)));
+        assert_eq!(iter.next(), None);
+
+        // Endpoint descriptor too short for its type
+        let mut iter = DescriptorIter::new(&[0x04, 0x05, 0x81, 0x02]);
+        assert_eq!(iter.next(), Some(Err(ParseError::InvalidLength)));
+
+        // Descriptor extending past the data
+        let mut iter = DescriptorIter::new(&[0x09, 0x04, 0x00, 0x00]);
+        assert_eq!(iter.next(), Some(Err(ParseError::Truncated)));
+
+        // Errors are reported by the interface iterator
+        let data = [
+            0x09, 0x04, 0x00, 0x00, 0x01, 0xff, 0x00, 0x00, 0x00, // interface 0
+            0x07, 0x05, 0x81, 0x02, 0x40, 0x00, // truncated endpoint
+        ];
+        let mut interfaces = DescriptorIter::new(&data).interfaces();
+        assert_eq!(interfaces.next().map(|i| i.err()), Some(Some(ParseError::Truncated)));
+        assert!(interfaces.next().is_none());
+    }
+}
@@ -0,0 +1,698 @@
+//! HID report descriptor parsing and input report decoding.
+//!
+//! [`FieldMap::parse`] walks a HID report descriptor and records where each input field lives
+//! in the reports, so devices beyond the boot protocol, like gamepads, can be decoded with
+//! [`FieldMap::input_values`]. Nothing is allocated, the capacity is set by the `N` parameter.
+//!
+//! Constant (padding) fields are not recorded. Array fields must list contiguous usages, as is
+//! the case with a usage minimum and maximum.
+
+use heapless::Vec;
+
+/// Maximum number of usages of a main item.
+const MAX_USAGES: usize = 16;
+/// Maximum number of report ids.
+const MAX_REPORT_IDS: usize = 16;
+/// Maximum depth of the global item stack.
+const MAX_STACK_DEPTH: usize = 4;
+
+mod item {
+    // Main items
+    pub const INPUT: u8 = 0x80;
+    pub const OUTPUT: u8 = 0x90;
+    pub const FEATURE: u8 = 0xb0;
+    pub const COLLECTION: u8 = 0xa0;
+    pub const END_COLLECTION: u8 = 0xc0;
+
+    // Global items
+    pub const USAGE_PAGE: u8 = 0x04;
+    pub const LOGICAL_MINIMUM: u8 = 0x14;
+    pub const LOGICAL_MAXIMUM: u8 = 0x24;
+    pub const REPORT_SIZE: u8 = 0x74;
+    pub const REPORT_ID: u8 = 0x84;
+    pub const REPORT_COUNT: u8 = 0x94;
+    pub const PUSH: u8 = 0xa4;
+    pub const POP: u8 = 0xb4;
+
+    // Local items
+    pub const USAGE: u8 = 0x08;
+    pub const USAGE_MINIMUM: u8 = 0x18;
+    pub const USAGE_MAXIMUM: u8 = 0x28;
+
+    /// Prefix of long items.
+    pub const LONG: u8 = 0xfe;
+}
+
+/// Error returned when a report descriptor can't be parsed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    /// An item extends past the end of the descriptor.
+    Truncated,
+    /// The descriptor has more input fields than the map can hold.
+    TooManyFields,
+    /// A main item has more than 16 usages.
+    TooManyUsages,
+    /// The descriptor uses more than 16 report ids.
+    TooManyReportIds,
+    /// Push and pop items are unbalanced or nested too deep.
+    InvalidStack,
+    /// An input field is larger than 32 bits, or an array lists non-contiguous usages.
+    UnsupportedField,
+}
+
+/// HID usage, made of a usage page and a usage id.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Usage {
+    /// Usage page.
+    pub page: u16,
+    /// Usage id.
+    pub id: u16,
+}
+
+impl Usage {
+    const fn from_u32(usage: u32) -> Self {
+        Self {
+            page: (usage >> 16) as u16,
+            id: usage as u16,
+        }
+    }
+
+    const fn to_u32(self) -> u32 {
+        ((self.page as u32) << 16) | self.id as u32
+    }
+}
+
+/// An input field of the reports.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Field {
+    /// Report id, 0 if the device doesn't use report ids.
+    pub report_id: u8,
+    /// Offset in bits of the first element, after the report id.
+    pub bit_offset: u32,
+    /// Size in bits of each element.
+    pub bit_size: u8,
+    /// Number of elements.
+    pub count: u16,
+    /// Data bits of the input item: variable (bit 1), relative (bit 2) and so on.
+    pub flags: u16,
+    /// Minimum value of an element.
+    pub logical_min: i32,
+    /// Maximum value of an element.
+    pub logical_max: i32,
+    /// Usage of the first element of a variable field, or of the array index `logical_min`.
+    pub usage_min: Usage,
+    /// Usage of the last elements of a variable field, or of the array index `logical_max`.
+    pub usage_max: Usage,
+}
+
+impl Field {
+    /// Whether each element is a value for its own usage, rather than an array of usage indexes.
+    pub fn is_variable(&self) -> bool {
+        self.flags & (1 << 1) != 0
+    }
+
+    /// Whether the values are relative to the previous report.
+    pub fn is_relative(&self) -> bool {
+        self.flags & (1 << 2) != 0
+    }
+
+    /// Reads element `index` of the field from report data following the report id.
+    fn read(&self, data: &[u8], index: u16) -> Option<i32> {
+        let start = (index as usize)
+            .checked_mul(self.bit_size as usize)?
+            .checked_add(self.bit_offset as usize)?;
+        let mut raw = 0u32;
+        for bit in 0..self.bit_size as usize {
+            let pos = start.checked_add(bit)?;
+            let byte = *data.get(pos / 8)?;
+            raw |= ((byte >> (pos % 8)) as u32 & 1) << bit;
+        }
+
+        // Logical values are signed only if the minimum is negative.
+        if self.logical_min < 0 && self.bit_size < 32 && raw & (1 << (self.bit_size - 1)) != 0 {
+            raw |= !0 << self.bit_size;
+        }
+        Some(raw as i32)
+    }
+}
+
+/// Value of a usage, decoded from an input report.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UsageValue {
+    /// Usage
+    pub usage: Usage,
+    /// Value of a variable field, or 1 for the usages selected by an array field.
+    pub value: i32,
+}
+
+#[derive(Copy, Clone, Default)]
+struct GlobalState {
+    usage_page: u16,
+    logical_min: i32,
+    logical_max: i32,
+    /// Logical maximum read as unsigned
+    logical_max_unsigned: u32,
+    report_size: u32,
+    report_count: u32,
+    report_id: u8,
+}
+
+#[derive(Default)]
+struct LocalState {
+    usages: Vec<u32, MAX_USAGES>,
+    usage_min: Option<u32>,
+    usage_max: Option<u32>,
+}
+
+/// Input fields of a HID report descriptor, holding up to `N` fields.
+#[derive(Clone, Debug)]
+pub struct FieldMap<const N: usize> {
+    fields: Vec<Field, N>,
+    report_ids: bool,
+}
+
+impl<const N: usize> FieldMap<N> {
+    /// Parses a report descriptor.
+    pub fn parse(descriptor: &[u8]) -> Result<Self, ParseError> {
+        let mut map = Self {
+            fields: Vec::new(),
+            report_ids: false,
+        };
+        let mut global = GlobalState::default();
+        let mut stack: Vec<GlobalState, MAX_STACK_DEPTH> = Vec::new();
+        let mut local = LocalState::default();
+        // Size of the input reports seen so far, by report id
+        let mut offsets: Vec<(u8, u32), MAX_REPORT_IDS> = Vec::new();
+
+        let mut data = descriptor;
+        while let Some((&prefix, rest)) = data.split_first() {
+            if prefix == item::LONG {
+                let len = *rest.first().ok_or(ParseError::Truncated)? as usize;
+                data = rest.get(2 + len..).ok_or(ParseError::Truncated)?;
+                continue;
+            }
+
+            let size = match prefix & 0b11 {
+                3 => 4,
+                n => n as usize,
+            };
+            let bytes = rest.get(..size).ok_or(ParseError::Truncated)?;
+            data = &rest[size..];
+
+            let mut buf = [0; 4];
+            buf[..size].copy_from_slice(bytes);
+            let unsigned = u32::from_le_bytes(buf);
+            let signed = match size {
+                1 => unsigned as i8 as i32,
+                2 => unsigned as i16 as i32,
+                _ => unsigned as i32,
+            };
+
+            match prefix & 0xfc {
+                item::INPUT => {
+                    let index = match offsets.iter().position(|(id, _)| *id == global.report_id) {
+                        Some(index) => index,
+                        None => {
+                            offsets
+                                .push((global.report_id, 0))
+                                .map_err(|_| ParseError::TooManyReportIds)?;
+                            offsets.len() - 1
+                        }
+                    };
+                    let offset = &mut offsets[index].1;
+                    map.add_input(&global, &local, unsigned as u16, *offset)?;
+                    *offset = global
+                        .report_size
+                        .checked_mul(global.report_count)
+                        .and_then(|size| offset.checked_add(size))
+                        .ok_or(ParseError::UnsupportedField)?;
+                    local = LocalState::default();
+                }
+                item::OUTPUT | item::FEATURE | item::COLLECTION | item::END_COLLECTION => {
+                    local = LocalState::default();
+                }
+                item::USAGE_PAGE => global.usage_page = unsigned as u16,
+                item::LOGICAL_MINIMUM => global.logical_min = signed,
+                item::LOGICAL_MAXIMUM => {
+                    global.logical_max = signed;
+                    global.logical_max_unsigned = unsigned;
+                }
+                item::REPORT_SIZE => global.report_size = unsigned,
+                item::REPORT_COUNT => global.report_count = unsigned,
+                item::REPORT_ID => {
+                    global.report_id = unsigned as u8;
+                    map.report_ids = true;
+                }
+                item::PUSH => stack.push(global).map_err(|_| ParseError::InvalidStack)?,
+                item::POP => global = stack.pop().ok_or(ParseError::InvalidStack)?,
+                item::USAGE => local.usages.push(unsigned).map_err(|_| ParseError::TooManyUsages)?,
+                item::USAGE_MINIMUM => local.usage_min = Some(unsigned),
+                item::USAGE_MAXIMUM => local.usage_max = Some(unsigned),
+                _ => {}
+            }
+        }
+
+        Ok(map)
+    }
+
+    fn add_input(
+        &mut self,
+        global: &GlobalState,
+        local: &LocalState,
+        flags: u16,
+        offset: u32,
+    ) -> Result<(), ParseError> {
+        // Constant fields are padding
+        if flags & 1 != 0 || global.report_count == 0 {
+            return Ok(());
+        }
+        if global.report_size == 0 || global.report_size > 32 || global.report_count > u16::MAX as u32 {
+            return Err(ParseError::UnsupportedField);
+        }
+
+        // Usages without a page use the current usage page.
+        let full = |usage: u32| match usage {
+            0..=0xffff => ((global.usage_page as u32) << 16) | usage,
+            _ => usage,
+        };
+        let logical_min = global.logical_min;
+        // A maximum below a non-negative minimum is meant as unsigned, like 255 encoded in one byte.
+        let logical_max = match global.logical_max < logical_min && logical_min >= 0 {
+            true => global.logical_max_unsigned.min(i32::MAX as u32) as i32,
+            false => global.logical_max,
+        };
+
+        let mut field = Field {
+            report_id: global.report_id,
+            bit_offset: offset,
+            bit_size: global.report_size as u8,
+            count: global.report_count as u16,
+            flags,
+            logical_min,
+            logical_max,
+            usage_min: Usage::from_u32(0),
+            usage_max: Usage::from_u32(0),
+        };
+
+        if flags & (1 << 1) == 0 {
+            // Array of usage indexes
+            let (min, max) = match (local.usage_min, local.usage_max, local.usages.as_slice()) {
+                (Some(min), Some(max), _) => (full(min), full(max)),
+                (_, _, [first, rest @ ..]) => {
+                    let first = full(*first);
+                    for (i, &usage) in rest.iter().enumerate() {
+                        if Some(full(usage)) != first.checked_add(i as u32 + 1) {
+                            return Err(ParseError::UnsupportedField);
+                        }
+                    }
+                    let last = first.checked_add(rest.len() as u32);
+                    (first, last.ok_or(ParseError::UnsupportedField)?)
+                }
+                _ => (0, 0),
+            };
+            field.usage_min = Usage::from_u32(min);
+            field.usage_max = Usage::from_u32(max);
+            return self.fields.push(field).map_err(|_| ParseError::TooManyFields);
+        }
+
+        // Variable field: element i has the usage i of the list, or usage minimum + i, the last
+        // usage applies to the remaining elements. Runs of consecutive usages share a field.
+        let usage = |i: u32| -> u32 {
+            if let Some(&last) = local.usages.last() {
+                full(local.usages.get(i as usize).copied().unwrap_or(last))
+            } else if let (Some(min), Some(max)) = (local.usage_min, local.usage_max) {
+                full(min).saturating_add(i).min(full(max))
+            } else {
+                0
+            }
+        };
+
+        let mut start = 0;
+        while start < global.report_count {
+            let first = usage(start);
+            let mut end = start + 1;
+            let mut last = first;
+            let mut repeating = false;
+            while end < global.report_count {
+                let next = usage(end);
+                if next == last {
+                    repeating = true;
+                } else if repeating || Some(next) != last.checked_add(1) {
+                    break;
+                }
+                last = next;
+                end += 1;
+            }
+
+            field.bit_offset = start
+                .checked_mul(global.report_size)
+                .and_then(|bits| offset.checked_add(bits))
+                .ok_or(ParseError::UnsupportedField)?;
+            field.count = (end - start) as u16;
+            field.usage_min = Usage::from_u32(first);
+            field.usage_max = Usage::from_u32(last);
+            self.fields.push(field).map_err(|_| ParseError::TooManyFields)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Returns the input fields.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Whether reports start with a report id.
+    pub fn uses_report_ids(&self) -> bool {
+        self.report_ids
+    }
+
+    /// Decodes an input report into the values of its usages.
+    ///
+    /// `report` starts with the report id if the device uses report ids. Elements beyond the end
+    /// of the report, and array indexes outside the logical range, are skipped.
+    pub fn input_values<'a>(&'a self, report: &'a [u8]) -> impl Iterator<Item = UsageValue> + 'a {
+        let (report_id, data) = match (self.report_ids, report.split_first()) {
+            (true, Some((&id, data))) => (id, data),
+            (true, None) => (0, &[][..]),
+            (false, _) => (0, report),
+        };
+
+        self.fields
+            .iter()
+            .filter(move |field| field.report_id == report_id)
+            .flat_map(move |field| {
+                (0..field.count).filter_map(move |i| {
+                    let value = field.read(data, i)?;
+                    if field.is_variable() {
+                        let usage = field.usage_min.to_u32().saturating_add(i as u32);
+                        let usage = Usage::from_u32(usage.min(field.usage_max.to_u32()));
+                        return Some(UsageValue { usage, value });
+                    }
+
+                    // Array index, values outside the logical range mean no usage.
+                    if value < field.logical_min || value > field.logical_max {
+                        return None;
+                    }
+                    let index = value.abs_diff(field.logical_min);
+                    let usage = Usage::from_u32(field.usage_min.to_u32().checked_add(index)?);
+                    // Usage id 0 is reserved, keyboards report it for no key.
+                    if usage.to_u32() > field.usage_max.to_u32() || usage.id == 0 {
+                        return None;
+                    }
+                    Some(UsageValue { usage, value: 1 })
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(page: u16, id: u16) -> Usage {
+        Usage { page, id }
+    }
+
+    /// Mouse with report id 1: three buttons, padding, and relative X, Y and wheel.
+    const MOUSE: &[u8] = &[
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x02, // Usage (Mouse)
+        0xa1, 0x01, // Collection (Application)
+        0x85, 0x01, //   Report ID (1)
+        0x09, 0x01, //   Usage (Pointer)
+        0xa1, 0x00, //   Collection (Physical)
+        0x05, 0x09, //     Usage Page (Button)
+        0x19, 0x01, //     Usage Minimum (1)
+        0x29, 0x03, //     Usage Maximum (3)
+        0x15, 0x00, //     Logical Minimum (0)
+        0x25, 0x01, //     Logical Maximum (1)
+        0x95, 0x03, //     Report Count (3)
+        0x75, 0x01, //     Report Size (1)
+        0x81, 0x02, //     Input (Data, Variable, Absolute)
+        0x95, 0x01, //     Report Count (1)
+        0x75, 0x05, //     Report Size (5)
+        0x81, 0x03, //     Input (Constant)
+        0x05, 0x01, //     Usage Page (Generic Desktop)
+        0x09, 0x30, //     Usage (X)
+        0x09, 0x31, //     Usage (Y)
+        0x09, 0x38, //     Usage (Wheel)
+        0x15, 0x81, //     Logical Minimum (-127)
+        0x25, 0x7f, //     Logical Maximum (127)
+        0x75, 0x08, //     Report Size (8)
+        0x95, 0x03, //     Report Count (3)
+        0x81, 0x06, //     Input (Data, Variable, Relative)
+        0xc0, //       End Collection
+        0xc0, //     End Collection
+    ];
+
+    /// Boot keyboard: modifiers, reserved byte, LED output and an array of six keys.
+    const KEYBOARD: &[u8] = &[
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x06, // Usage (Keyboard)
+        0xa1, 0x01, // Collection (Application)
+        0x05, 0x07, //   Usage Page (Keyboard)
+        0x19, 0xe0, //   Usage Minimum (Left Control)
+        0x29, 0xe7, //   Usage Maximum (Right GUI)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x25, 0x01, //   Logical Maximum (1)
+        0x75, 0x01, //   Report Size (1)
+        0x95, 0x08, //   Report Count (8)
+        0x81, 0x02, //   Input (Data, Variable, Absolute)
+        0x95, 0x01, //   Report Count (1)
+        0x75, 0x08, //   Report Size (8)
+        0x81, 0x01, //   Input (Constant)
+        0x95, 0x05, //   Report Count (5)
+        0x75, 0x01, //   Report Size (1)
+        0x05, 0x08, //   Usage Page (LEDs)
+        0x19, 0x01, //   Usage Minimum (1)
+        0x29, 0x05, //   Usage Maximum (5)
+        0x91, 0x02, //   Output (Data, Variable, Absolute)
+        0x95, 0x01, //   Report Count (1)
+        0x75, 0x03, //   Report Size (3)
+        0x91, 0x01, //   Output (Constant)
+        0x95, 0x06, //   Report Count (6)
+        0x75, 0x08, //   Report Size (8)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x25, 0xff, //   Logical Maximum (255), encoded as -1
+        0x05, 0x07, //   Usage Page (Keyboard)
+        0x19, 0x00, //   Usage Minimum (0)
+        0x2a, 0xff, 0x00, // Usage Maximum (255)
+        0x81, 0x00, //   Input (Data, Array, Absolute)
+        0xc0, //     End Collection
+    ];
+
+    #[test]
+    fn test_mouse() {
+        let map = FieldMap::<8>::parse(MOUSE).unwrap();
+        assert!(map.uses_report_ids());
+
+        let fields = map.fields();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].bit_offset, 0);
+        assert_eq!(fields[0].count, 3);
+        assert_eq!(fields[0].usage_min, usage(0x09, 1));
+        assert_eq!(fields[0].usage_max, usage(0x09, 3));
+        // X and Y share a field, the wheel usage isn't contiguous
+        assert_eq!(fields[1].bit_offset, 8);
+        assert_eq!(fields[1].count, 2);
+        assert_eq!(fields[1].usage_min, usage(0x01, 0x30));
+        assert_eq!(fields[1].usage_max, usage(0x01, 0x31));
+        assert!(fields[1].is_relative());
+        assert_eq!(fields[2].bit_offset, 24);
+        assert_eq!(fields[2].usage_min, usage(0x01, 0x38));
+        assert_eq!((fields[2].logical_min, fields[2].logical_max), (-127, 127));
+
+        let report = [0x01, 0b1111_1101, 0xfe, 0x05, 0x81];
+        let values: Vec<_, 8> = map.input_values(&report).collect();
+        let expected = [
+            (usage(0x09, 1), 1),
+            (usage(0x09, 2), 0),
+            (usage(0x09, 3), 1),
+            (usage(0x01, 0x30), -2),
+            (usage(0x01, 0x31), 5),
+            (usage(0x01, 0x38), -127),
+        ];
+        assert_eq!(values.len(), expected.len());
+        for (value, (usage, expected)) in values.iter().zip(expected) {
+            assert_eq!(value.usage, usage);
+            assert_eq!(value.value, expected);
+        }
+
+        // Other report ids have no fields
+        assert_eq!(map.input_values(&[0x02, 0xff, 0xff]).count(), 0);
+        // Elements past the end of a short report are skipped
+        assert_eq!(map.input_values(&report[..3]).count(), 4);
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let map = FieldMap::<8>::parse(KEYBOARD).unwrap();
+        assert!(!map.uses_report_ids());
+
+        let fields = map.fields();
+        assert_eq!(fields.len(), 2);
+        let keys = fields[1];
+        assert!(!keys.is_variable());
+        assert_eq!(keys.bit_offset, 16);
+        assert_eq!(keys.count, 6);
+        assert_eq!((keys.logical_min, keys.logical_max), (0, 255));
+        assert_eq!(keys.usage_min, usage(0x07, 0x00));
+        assert_eq!(keys.usage_max, usage(0x07, 0xff));
+
+        // Left Shift with A and B pressed
+        let report = [0x02, 0x00, 0x04, 0x05, 0x00, 0x00, 0x00, 0x00];
+        let values: Vec<_, 16> = map.input_values(&report).collect();
+        assert_eq!(values.len(), 8 + 2);
+        assert_eq!(values[1].usage, usage(0x07, 0xe1));
+        assert_eq!(values[1].value, 1);
+        assert_eq!(values[0].value, 0);
+        assert_eq!(
+            values[8],
+            UsageValue {
+                usage: usage(0x07, 0x04),
+                value: 1
+            }
+        );
+        assert_eq!(values[9].usage, usage(0x07, 0x05));
+    }
+
+    #[test]
+    fn test_usages_and_globals() {
+        let descriptor = [
+            0x05, 0x09, // Usage Page (Button)
+            0xa4, // Push
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x15, 0x00, // Logical Minimum (0)
+            0x26, 0xff, 0x0f, // Logical Maximum (4095)
+            0x75, 0x10, // Report Size (16)
+            0x09, 0x30, // Usage (X)
+            0x09, 0x31, // Usage (Y)
+            0x95, 0x04, // Report Count (4)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xb4, // Pop
+            0x0b, 0x39, 0x00, 0x01, 0x00, // Usage (Generic Desktop, Hat Switch)
+            0x19, 0x01, // Usage Minimum (1)
+            0x75, 0x04, // Report Size (4)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+            0xfe, 0x02, 0x10, 0xaa, 0xbb, // Long item, skipped
+        ];
+        let map = FieldMap::<8>::parse(&descriptor).unwrap();
+        let fields = map.fields();
+        assert_eq!(fields.len(), 2);
+        // The last usage applies to the remaining elements
+        assert_eq!(fields[0].count, 4);
+        assert_eq!(fields[0].usage_min, usage(0x01, 0x30));
+        assert_eq!(fields[0].usage_max, usage(0x01, 0x31));
+        assert_eq!(fields[0].logical_max, 4095);
+        // Pop restored the report size and usage page, the extended usage keeps its own page
+        assert_eq!(fields[1].bit_offset, 64);
+        assert_eq!(fields[1].bit_size, 4);
+        assert_eq!(fields[1].usage_min, usage(0x01, 0x39));
+
+        let report = [0x34, 0x12, 0xff, 0x0f, 0x01, 0x00, 0x02, 0x00, 0x07];
+        let values: Vec<_, 8> = map.input_values(&report).collect();
+        assert_eq!(values.len(), 5);
+        assert_eq!(values[0].value, 0x1234);
+        assert_eq!(values[1].value, 0x0fff);
+        assert_eq!(values[3].usage, usage(0x01, 0x31));
+        assert_eq!(values[3].value, 2);
+        assert_eq!(values[4].value, 7);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(FieldMap::<8>::parse(&[0x05]).err(), Some(ParseError::Truncated));
+        assert_eq!(FieldMap::<8>::parse(&[0x26, 0xff]).err(), Some(ParseError::Truncated));
+        assert_eq!(
+            FieldMap::<8>::parse(&[0xfe, 0x04, 0x00]).err(),
+            Some(ParseError::Truncated)
+        );
+        assert_eq!(FieldMap::<8>::parse(&[0xb4]).err(), Some(ParseError::InvalidStack));
+        assert_eq!(FieldMap::<2>::parse(MOUSE).err(), Some(ParseError::TooManyFields));
+
+        // Array of non-contiguous usages
+        let descriptor = [
+            0x05, 0x0c, // Usage Page (Consumer)
+            0x09, 0xe9, // Usage (Volume Up)
+            0x09, 0xcd, // Usage (Play/Pause)
+            0x15, 0x01, // Logical Minimum (1)
+            0x25, 0x02, // Logical Maximum (2)
+            0x75, 0x02, // Report Size (2)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x00, // Input (Data, Array, Absolute)
+        ];
+        assert_eq!(
+            FieldMap::<8>::parse(&descriptor).err(),
+            Some(ParseError::UnsupportedField)
+        );
+
+        // Fields larger than 32 bits
+        let descriptor = [0x75, 0x28, 0x95, 0x01, 0x09, 0x01, 0x81, 0x02];
+        assert_eq!(
+            FieldMap::<8>::parse(&descriptor).err(),
+            Some(ParseError::UnsupportedField)
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Array of extended usages past the last usage
+        let descriptor = [
+            0x0b, 0xff, 0xff, 0xff, 0xff, // Usage (0xffff, 0xffff)
+            0x0b, 0x00, 0x00, 0x01, 0x00, // Usage (0x0001, 0x0000)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x00, // Input (Data, Array, Absolute)
+        ];
+        assert_eq!(
+            FieldMap::<8>::parse(&descriptor).err(),
+            Some(ParseError::UnsupportedField)
+        );
+
+        // Same usages in a variable field, each gets its own field
+        let mut descriptor = descriptor;
+        descriptor[12..].copy_from_slice(&[0x95, 0x02, 0x81, 0x02]);
+        let map = FieldMap::<8>::parse(&descriptor).unwrap();
+        assert_eq!(map.fields().len(), 2);
+        assert_eq!(map.fields()[0].usage_min, usage(0xffff, 0xffff));
+        assert_eq!(map.fields()[1].usage_min, usage(0x0001, 0x0000));
+
+        // Constant field of more than 2^32 bits
+        let descriptor = [
+            0x75, 0x20, // Report Size (32)
+            0x97, 0xff, 0xff, 0xff, 0xff, // Report Count (0xffffffff)
+            0x81, 0x01, // Input (Constant)
+            0x09, 0x01, // Usage (1)
+            0x95, 0x02, // Report Count (2)
+            0x81, 0x02, // Input (Data, Variable, Absolute)
+        ];
+        assert_eq!(
+            FieldMap::<8>::parse(&descriptor).err(),
+            Some(ParseError::UnsupportedField)
+        );
+
+        // Array with the whole i32 range as logical range
+        let descriptor = [
+            0x05, 0x09, // Usage Page (Button)
+            0x17, 0x00, 0x00, 0x00, 0x80, // Logical Minimum (i32::MIN)
+            0x27, 0xff, 0xff, 0xff, 0x7f, // Logical Maximum (i32::MAX)
+            0x19, 0x01, // Usage Minimum (1)
+            0x29, 0x03, // Usage Maximum (3)
+            0x75, 0x20, // Report Size (32)
+            0x95, 0x01, // Report Count (1)
+            0x81, 0x00, // Input (Data, Array, Absolute)
+        ];
+        let map = FieldMap::<8>::parse(&descriptor).unwrap();
+        assert_eq!(map.input_values(&[0xff, 0xff, 0xff, 0x7f]).count(), 0);
+        assert_eq!(map.input_values(&[0x00, 0x00, 0x00, 0x00]).count(), 0);
+        let values: Vec<_, 8> = map.input_values(&[0x01, 0x00, 0x00, 0x80]).collect();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].usage, usage(0x09, 2));
+    }
+}
@@ -11,8 +11,12 @@ mod builder;
 pub mod class;
 pub mod control;
 pub mod descriptor;
+pub mod descriptor_parser;
 mod descriptor_reader;
+pub mod hid_report;
 pub mod msos;
+#[cfg(test)]
+mod prop_test;
 pub mod types;
 
 mod config {
@@ -104,6 +108,9 @@ pub trait Handler {
     /// Called when remote wakeup feature is enabled or disabled.
     fn remote_wakeup_enabled(&mut self, _enabled: bool) {}
 
+    /// Called when the link has entered or exited the L1 (LPM sleep) state.
+    fn lpm_sleeping(&mut self, _sleeping: bool) {}
+
     /// Called when a "set alternate setting" control request is done on the interface.
     fn set_alternate_setting(&mut self, iface: InterfaceNumber, alternate_setting: u8) {
         let _ = iface;
@@ -198,6 +205,10 @@ struct Inner<'d, D: Driver<'d>> {
     device_state: UsbDeviceState,
     suspended: bool,
     remote_wakeup_enabled: bool,
+    /// Whether the link is in the L1 (LPM sleep) state.
+    lpm_sleeping: bool,
+    /// Whether the host allowed remote wakeup when entering L1.
+    lpm_remote_wakeup: bool,
     self_powered: bool,
 
     /// Our device address, or 0 if none.
@@ -243,6 +254,8 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
                 device_state: UsbDeviceState::Unpowered,
                 suspended: false,
                 remote_wakeup_enabled: false,
+                lpm_sleeping: false,
+                lpm_remote_wakeup: false,
                 self_powered: false,
                 address: 0,
                 set_address_pending: false,
@@ -301,6 +314,7 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
             self.inner.device_state = UsbDeviceState::Disabled;
             self.inner.suspended = false;
             self.inner.remote_wakeup_enabled = false;
+            self.inner.lpm_sleeping = false;
 
             for h in &mut self.inner.handlers {
                 h.enabled(false);
@@ -320,14 +334,23 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
 
     /// Initiates a device remote wakeup on the USB bus.
     ///
-    /// If the bus is not suspended or remote wakeup is not enabled, an error
-    /// will be returned.
+    /// If the bus is not suspended or in L1 sleep, or remote wakeup is not enabled,
+    /// an error will be returned.
     ///
     /// This future may leave the bus in an inconsistent state if dropped.
     /// After dropping the future, [`UsbDevice::disable()`] should be called
     /// before calling any other `UsbDevice` methods to fully reset the peripheral.
     pub async fn remote_wakeup(&mut self) -> Result<(), RemoteWakeupError> {
-        if self.inner.suspended && self.inner.remote_wakeup_enabled {
+        if self.inner.lpm_sleeping && self.inner.lpm_remote_wakeup {
+            self.inner.bus.l1_remote_wakeup().await?;
+            self.inner.lpm_sleeping = false;
+
+            for h in &mut self.inner.handlers {
+                h.lpm_sleeping(false);
+            }
+
+            Ok(())
+        } else if self.inner.suspended && self.inner.remote_wakeup_enabled {
             self.inner.bus.remote_wakeup().await?;
             self.inner.suspended = false;
 
@@ -448,6 +471,7 @@ impl<'d, D: Driver<'d>> Inner<'d, D> {
                 self.device_state = UsbDeviceState::Default;
                 self.suspended = false;
                 self.remote_wakeup_enabled = false;
+                self.lpm_sleeping = false;
                 self.address = 0;
 
                 for h in &mut self.handlers {
@@ -464,9 +488,24 @@ impl<'d, D: Driver<'d>> Inner<'d, D> {
             }
             Event::Resume => {
                 trace!("usb: resume");
-                self.suspended = false;
+                if self.lpm_sleeping {
+                    self.lpm_sleeping = false;
+                    for h in &mut self.handlers {
+                        h.lpm_sleeping(false);
+                    }
+                } else {
+                    self.suspended = false;
+                    for h in &mut self.handlers {
+                        h.suspended(false);
+                    }
+                }
+            }
+            Event::Sleep { besl, remote_wakeup } => {
+                trace!("usb: lpm sleep, besl={}", besl);
+                self.lpm_sleeping = true;
+                self.lpm_remote_wakeup = remote_wakeup;
                 for h in &mut self.handlers {
-                    h.suspended(false);
+                    h.lpm_sleeping(true);
                 }
             }
             Event::Suspend => {
@@ -511,6 +550,9 @@ impl<'d, D: Driver<'d>> Inner<'d, D> {
                     OutResponse::Accepted
                 }
                 (Request::SET_FEATURE, Request::FEATURE_DEVICE_REMOTE_WAKEUP) => {
+                    if !self.config.supports_remote_wakeup {
+                        return OutResponse::Rejected;
+                    }
                     self.remote_wakeup_enabled = true;
                     for h in &mut self.handlers {
                         h.remote_wakeup_enabled(true);
@@ -0,0 +1,237 @@
+//! Property tests feeding arbitrary and generated descriptors to the parsers.
+//!
+//! Descriptors come from the device, so no input may make the parsers panic or loop forever.
+//! The fuzz targets in `fuzz/` run the same checks on inputs found by libFuzzer.
+
+extern crate std;
+
+use std::vec::Vec;
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::descriptor::descriptor_type;
+use crate::descriptor_parser::{parse_configuration, Descriptor, DescriptorIter, ParseError, StringDescriptor};
+use crate::hid_report::FieldMap;
+
+/// Walks all descriptors and interfaces of a configuration.
+fn check_configuration(data: &[u8]) {
+    let Ok((_, descriptors)) = parse_configuration(data) else {
+        return;
+    };
+
+    // Every descriptor is at least 2 bytes long, and iteration stops at the first error.
+    let count = descriptors.clone().count();
+    assert!(count <= data.len() / 2);
+    let errors = descriptors.clone().filter(Result::is_err).count();
+    assert!(errors <= 1);
+
+    let mut functions = 0;
+    for function in descriptors.clone().functions() {
+        functions += 1;
+        assert!(functions <= count);
+        if let Ok(function) = function {
+            assert!(function.interfaces().count() <= count);
+        }
+    }
+
+    let mut interfaces = 0;
+    for interface in descriptors.interfaces() {
+        interfaces += 1;
+        assert!(interfaces <= count);
+        if let Ok(interface) = interface {
+            assert!(interface.descriptors.clone().count() < count);
+            assert!(interface.endpoints().count() < count);
+        }
+    }
+}
+
+/// Parses a report descriptor and decodes a report with it.
+fn check_report(descriptor: &[u8], report: &[u8]) {
+    let Ok(map) = FieldMap::<16>::parse(descriptor) else {
+        return;
+    };
+
+    let elements: usize = map.fields().iter().map(|field| field.count as usize).sum();
+    let mut values = 0;
+    for value in map.input_values(report) {
+        values += 1;
+        assert!(values <= elements);
+        let field = map.fields().iter().find(|field| {
+            (field.usage_min.page, field.usage_min.id) <= (value.usage.page, value.usage.id)
+                && (value.usage.page, value.usage.id) <= (field.usage_max.page, field.usage_max.id)
+        });
+        assert!(field.is_some(), "usage outside of the fields: {:?}", value.usage);
+    }
+}
+
+/// A descriptor with a valid `bLength`, of a type the parser knows or a random one.
+fn descriptor() -> impl Strategy<Value = (u8, Vec<u8>)> {
+    let descriptor_type = prop_oneof![
+        Just(descriptor_type::INTERFACE),
+        Just(descriptor_type::ENDPOINT),
+        Just(descriptor_type::IAD),
+        Just(0x24),
+        Just(0x25),
+        any::<u8>(),
+    ];
+    (descriptor_type, vec(any::<u8>(), 0..12))
+}
+
+/// A configuration made of well-formed descriptors.
+fn configuration() -> impl Strategy<Value = (Vec<u8>, Vec<(u8, Vec<u8>)>)> {
+    vec(descriptor(), 0..16).prop_map(|descriptors| {
+        let mut data = std::vec![9, descriptor_type::CONFIGURATION, 0, 0, 1, 1, 0, 0x80, 50];
+        for (descriptor_type, body) in &descriptors {
+            data.push(body.len() as u8 + 2);
+            data.push(*descriptor_type);
+            data.extend_from_slice(body);
+        }
+        let total_length = data.len() as u16;
+        data[2..4].copy_from_slice(&total_length.to_le_bytes());
+        (data, descriptors)
+    })
+}
+
+/// Minimum length of the body of the descriptors with a type the parser decodes.
+fn min_body_len(descriptor_type: u8) -> usize {
+    match descriptor_type {
+        descriptor_type::CONFIGURATION | descriptor_type::INTERFACE => 7,
+        descriptor_type::ENDPOINT => 5,
+        descriptor_type::IAD => 6,
+        _ => 0,
+    }
+}
+
+/// Main, global and local items the report descriptor parser handles.
+const ITEM_TAGS: &[u8] = &[
+    0x80, 0x90, 0xa0, 0xb0, 0xc0, 0x04, 0x14, 0x24, 0x74, 0x84, 0x94, 0xa4, 0xb4, 0x08, 0x18, 0x28,
+];
+
+/// Item data, biased towards the edges of the range.
+fn value() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        Just(0u32),
+        Just(1),
+        Just(0x7f),
+        Just(0x80),
+        Just(0xff),
+        Just(0x7fff_ffff),
+        Just(0x8000_0000),
+        Just(u32::MAX),
+        0u32..64,
+        any::<u32>(),
+    ]
+}
+
+/// Encodes a short item, keeping `size` bytes of `value`.
+fn encode(item: &mut Vec<u8>, tag: u8, size: u8, value: u32) {
+    let len = match size {
+        3 => 4,
+        n => n as usize,
+    };
+    item.push(tag | size);
+    item.extend_from_slice(&value.to_le_bytes()[..len]);
+}
+
+/// A report descriptor item.
+fn item() -> impl Strategy<Value = Vec<u8>> {
+    let tag = prop_oneof![prop::sample::select(ITEM_TAGS), any::<u8>().prop_map(|tag| tag & 0xfc),];
+    (tag, 0u8..4, value()).prop_map(|(tag, size, value)| {
+        let mut item = Vec::new();
+        encode(&mut item, tag, size, value);
+        item
+    })
+}
+
+/// The items of a complete input field, which random items rarely form.
+fn input() -> impl Strategy<Value = Vec<u8>> {
+    let report_size = prop_oneof![1u32..=32, value()];
+    let report_count = prop_oneof![1u32..=8, value()];
+    let usage = (prop::sample::select(&[0x08u8, 0x18, 0x28][..]), value());
+    let logical = (value(), value());
+    (report_size, report_count, logical, vec(usage, 0..4), any::<u8>()).prop_map(
+        |(report_size, report_count, (min, max), usages, flags)| {
+            let mut items = Vec::new();
+            encode(&mut items, 0x74, 3, report_size);
+            encode(&mut items, 0x94, 3, report_count);
+            encode(&mut items, 0x14, 3, min);
+            encode(&mut items, 0x24, 3, max);
+            for (tag, usage) in usages {
+                encode(&mut items, tag, 3, usage);
+            }
+            encode(&mut items, 0x80, 1, flags as u32);
+            items
+        },
+    )
+}
+
+proptest! {
+    #[test]
+    fn test_configuration_arbitrary(data in vec(any::<u8>(), 0..256)) {
+        check_configuration(&data);
+    }
+
+    #[test]
+    fn test_configuration_well_formed((data, descriptors) in configuration()) {
+        let (_, iter) = parse_configuration(&data).unwrap();
+        let parsed: Vec<_> = iter.clone().collect();
+        prop_assert!(parsed.len() <= descriptors.len());
+
+        for (result, (descriptor_type, body)) in parsed.iter().zip(&descriptors) {
+            match result {
+                Err(e) => {
+                    // Only a descriptor too short for its type is an error, and it ends the iteration.
+                    prop_assert_eq!(*e, ParseError::InvalidLength);
+                    prop_assert!(body.len() < min_body_len(*descriptor_type));
+                }
+                Ok(Descriptor::ClassSpecific(descriptor)) => {
+                    prop_assert_eq!(descriptor.descriptor_type, *descriptor_type);
+                    prop_assert_eq!(descriptor.data, &body[..]);
+                }
+                Ok(_) => prop_assert!(body.len() >= min_body_len(*descriptor_type)),
+            }
+        }
+        if !matches!(parsed.last(), Some(Err(_))) {
+            prop_assert_eq!(parsed.len(), descriptors.len());
+        }
+
+        // Truncated copies must not panic either.
+        for len in 0..data.len() {
+            check_configuration(&data[..len]);
+        }
+        check_configuration(&data);
+    }
+
+    #[test]
+    fn test_descriptor_iter_corrupted((data, _) in configuration(), index in any::<prop::sample::Index>(), byte in any::<u8>()) {
+        let mut data = data;
+        if !data.is_empty() {
+            let index = index.index(data.len());
+            data[index] = byte;
+        }
+        check_configuration(&data);
+        prop_assert!(DescriptorIter::new(&data).count() <= data.len() / 2 + 1);
+    }
+
+    #[test]
+    fn test_string_arbitrary(data in vec(any::<u8>(), 0..64), len in 0usize..64) {
+        if let Ok(string) = StringDescriptor::parse(&data) {
+            let mut buf = [0u8; 64];
+            let decoded = string.decode(&mut buf[..len]);
+            prop_assert!(decoded.chars().count() <= string.code_units().count());
+            prop_assert!(decoded.chars().zip(string.chars()).all(|(a, b)| a == b));
+        }
+    }
+
+    #[test]
+    fn test_report_arbitrary(descriptor in vec(any::<u8>(), 0..256), report in vec(any::<u8>(), 0..64)) {
+        check_report(&descriptor, &report);
+    }
+
+    #[test]
+    fn test_report_items(items in vec(prop_oneof![item(), input()], 0..32), report in vec(any::<u8>(), 0..64)) {
+        let descriptor: Vec<u8> = items.concat();
+        check_report(&descriptor, &report);
+    }
+}
@@ -0,0 +1,43 @@
+//! This example shows how to act as an SPI slave, answering each frame with the byte count of the previous one.
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::spi::{SlaveConfig, SpiSlave};
+use static_cell::StaticCell;
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    info!("Hello World!");
+
+    static RX_BUF: StaticCell<[u8; 256]> = StaticCell::new();
+    let rx_buf = RX_BUF.init([0; 256]);
+
+    let mut spi = SpiSlave::new(
+        p.SPI1,
+        p.PA5,
+        p.PA7,
+        p.PA6,
+        p.PA4,
+        p.EXTI4,
+        p.DMA2_CH3,
+        p.DMA2_CH2,
+        rx_buf,
+        SlaveConfig::default(),
+    );
+
+    let mut frame = [0u8; 64];
+    loop {
+        match spi.read_frame(&mut frame).await {
+            Ok(len) => {
+                info!("received frame: {:x}", frame[..len]);
+                // Clocked out during the next frame.
+                unwrap!(spi.write(&[len as u8]).await);
+            }
+            Err(e) => warn!("frame error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+use defmt::{info, warn, Format};
+use embassy_executor::Spawner;
+use embassy_stm32::ucpd::sink::{Event, PowerRequest, Sink};
+use embassy_stm32::ucpd::{self, CcPhy, CcPull, CcSel, CcVState, Ucpd};
+use embassy_stm32::{bind_interrupts, peripherals, Config};
+use embassy_time::{with_timeout, Duration};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    UCPD1 => ucpd::InterruptHandler<peripherals::UCPD1>;
+});
+
+#[derive(Debug, Format)]
+enum CableOrientation {
+    Normal,
+    Flipped,
+    DebugAccessoryMode,
+}
+
+// Returns true when the cable
+async fn wait_attached<T: ucpd::Instance>(cc_phy: &mut CcPhy<'_, T>) -> CableOrientation {
+    loop {
+        let (cc1, cc2) = cc_phy.vstate();
+        if cc1 == CcVState::LOWEST && cc2 == CcVState::LOWEST {
+            // Detached, wait until attached by monitoring the CC lines.
+            cc_phy.wait_for_vstate_change().await;
+            continue;
+        }
+
+        // Attached, wait for CC lines to be stable for tCCDebounce (100..200ms).
+        if with_timeout(Duration::from_millis(100), cc_phy.wait_for_vstate_change())
+            .await
+            .is_ok()
+        {
+            // State has changed, restart detection procedure.
+            continue;
+        };
+
+        // State was stable for the complete debounce period, check orientation.
+        return match (cc1, cc2) {
+            (_, CcVState::LOWEST) => CableOrientation::Normal,  // CC1 connected
+            (CcVState::LOWEST, _) => CableOrientation::Flipped, // CC2 connected
+            _ => CableOrientation::DebugAccessoryMode,          // Both connected (special cable)
+        };
+    }
+}
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = Config::default();
+    config.enable_ucpd1_dead_battery = true;
+    let p = embassy_stm32::init(config);
+
+    info!("Hello World!");
+
+    let mut ucpd = Ucpd::new(p.UCPD1, Irqs {}, p.PB6, p.PB4, Default::default());
+    ucpd.cc_phy().set_pull(CcPull::Sink);
+
+    info!("Waiting for USB connection...");
+    let cc_sel = match wait_attached(ucpd.cc_phy()).await {
+        CableOrientation::Normal => CcSel::CC1,
+        CableOrientation::Flipped => CcSel::CC2,
+        CableOrientation::DebugAccessoryMode => panic!("No PD communication in DAM"),
+    };
+    let (_cc_phy, pd_phy) = ucpd.split_pd_phy(p.DMA1_CH1, p.DMA1_CH2, cc_sel);
+
+    // Ask for 9 V / 2 A, the sink falls back to 5 V if the source can't provide it.
+    let mut sink = Sink::new(
+        pd_phy,
+        PowerRequest::Fixed {
+            voltage_mv: 9000,
+            current_ma: 2000,
+        },
+    );
+
+    loop {
+        match sink.run().await {
+            Event::Contract(contract) => info!("USB PD contract: {}", contract),
+            Event::Rejected => warn!("USB PD request rejected"),
+            Event::Alert { alert, status } => warn!("USB PD alert: {:08x}, status: {}", alert, status),
+            Event::HardReset => warn!("USB PD hard reset"),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! This example shows how to emulate a simple I2C register device.
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::i2c::{Address, I2c, TargetCommandKind, TargetConfig};
+use embassy_stm32::time::Hertz;
+use embassy_stm32::{bind_interrupts, i2c, peripherals};
+use {defmt_rtt as _, panic_probe as _};
+
+const ADDRESS: u8 = 0x42;
+
+bind_interrupts!(struct Irqs {
+    I2C2_EV => i2c::EventInterruptHandler<peripherals::I2C2>;
+    I2C2_ER => i2c::ErrorInterruptHandler<peripherals::I2C2>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_stm32::init(Default::default());
+    let mut i2c = I2c::new(
+        p.I2C2,
+        p.PB10,
+        p.PB11,
+        Irqs,
+        p.DMA1_CH4,
+        p.DMA1_CH5,
+        Hertz(100_000),
+        Default::default(),
+    );
+
+    let mut config = TargetConfig::new(Address::SevenBit(ADDRESS));
+    config.general_call = true;
+    i2c.set_target_config(config);
+
+    let mut registers = [0u8; 16];
+    let mut index = 0;
+
+    loop {
+        let command = unwrap!(i2c.listen().await);
+        match command.kind {
+            TargetCommandKind::Write => {
+                // First byte selects the register, the following bytes are written to it.
+                let mut buf = [0u8; 17];
+                match i2c.respond_to_write(&mut buf).await {
+                    Ok(0) => {}
+                    Ok(len) if command.address == Address::SevenBit(0) => {
+                        info!("General call: {:x}", buf[..len]);
+                    }
+                    Ok(len) => {
+                        index = buf[0] as usize % registers.len();
+                        for &byte in &buf[1..len] {
+                            registers[index] = byte;
+                            index = (index + 1) % registers.len();
+                        }
+                    }
+                    Err(e) => error!("Write error: {}", e),
+                }
+            }
+            TargetCommandKind::Read => {
+                let (tail, head) = registers.split_at(index);
+                let mut buf = [0u8; 16];
+                buf[..head.len()].copy_from_slice(head);
+                buf[head.len()..].copy_from_slice(tail);
+                match i2c.respond_to_read(&buf).await {
+                    Ok(sent) => index = (index + sent) % registers.len(),
+                    Err(e) => error!("Read error: {}", e),
+                }
+            }
+        }
+    }
+}